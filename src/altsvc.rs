@@ -0,0 +1,310 @@
+use crate::io::*;
+use crate::prty::*;
+
+///The "alt-svc" header field.
+pub const ALT_SVC: &[u8] = b"alt-svc";
+///The default freshness lifetime of an alternative service, in seconds.
+pub const DEFAULT_MAX_AGE: u64 = 86400;
+
+const INVALID_ALT_SVC: &str = "invalid alt-svc field value";
+
+///Represents an alternative service.
+///
+///The protocol-id is the ALPN protocol name, which is percent-decoded. The host is empty if it is the same as the origin's.
+///The max age ("ma") is the freshness lifetime in seconds, 86400 by default.
+///The persist ("persist=1") indicates the alternative service is kept after network configuration changes.
+#[derive(Clone, CopyGetters, Debug, Eq, Getters, PartialEq, Setters)]
+pub struct Alternative {
+    #[getset(get = "pub")]
+    protocol_id: Vec<u8>,
+    #[getset(get = "pub")]
+    host: Vec<u8>,
+    #[getset(get_copy = "pub", set = "pub")]
+    port: u16,
+    #[getset(get_copy = "pub", set = "pub")]
+    max_age: u64,
+    #[getset(get_copy = "pub", set = "pub")]
+    persist: bool,
+}
+
+impl Alternative {
+    ///Creates.
+    pub fn new(protocol_id: impl Into<Vec<u8>>, host: impl Into<Vec<u8>>, port: u16) -> Self {
+        Self {
+            protocol_id: protocol_id.into(),
+            host: host.into(),
+            port,
+            max_age: DEFAULT_MAX_AGE,
+            persist: false,
+        }
+    }
+
+    fn export(&self, o: &mut dyn PutU8) {
+        for &b in &self.protocol_id {
+            if is_tchar(b) && b != b'%' {
+                o.put_u8(b);
+            } else {
+                o.put_exact(format!("%{b:02X}").as_bytes());
+            }
+        }
+        o.put_exact(b"=\"");
+        for &b in &self.host {
+            if b == b'"' || b == b'\\' {
+                o.put_u8(b'\\');
+            }
+            o.put_u8(b);
+        }
+        o.put_exact(format!(":{}\"", self.port).as_bytes());
+        if self.max_age != DEFAULT_MAX_AGE {
+            o.put_exact(format!("; ma={}", self.max_age).as_bytes());
+        }
+        if self.persist {
+            o.put_exact(b"; persist=1");
+        }
+    }
+}
+
+///Represents an Alt-Svc field value, which is used by the "alt-svc" header field and the ALTSVC frame.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum AltSvcValue {
+    ///Invalidates all alternative services of the origin.
+    Clear,
+    ///Alternative services in the order of preference.
+    Alternatives(Vec<Alternative>),
+}
+
+impl AltSvcValue {
+    ///Parses an Alt-Svc field value. Unknown parameters and parameters with invalid values are ignored.
+    ///Returns an error if the field value can't be parsed, then the field value should be ignored.
+    pub fn parse(value: &[u8]) -> Result<Self, &'static str> {
+        let mut p = Parser(value, 0);
+        p.skip_ows();
+        let a = p.1;
+        if p.token() == b"clear" {
+            p.skip_ows();
+            if p.is_end() {
+                return Ok(Self::Clear);
+            }
+        }
+        p.1 = a;
+        let mut v = Vec::new();
+        loop {
+            p.skip_ows();
+            if p.is_end() {
+                break;
+            }
+            if p.eat(b',') {
+                continue;
+            }
+            v.push(p.alternative()?);
+            p.skip_ows();
+            if !p.is_end() && !p.eat(b',') {
+                return Err(INVALID_ALT_SVC);
+            }
+        }
+        if v.is_empty() {
+            return Err(INVALID_ALT_SVC);
+        }
+        Ok(Self::Alternatives(v))
+    }
+
+    ///Exports an Alt-Svc field value.
+    pub fn export(&self, o: &mut dyn PutU8) {
+        match self {
+            Self::Clear => {
+                o.put_exact(b"clear");
+            }
+            Self::Alternatives(v) => {
+                for (i, r) in v.iter().enumerate() {
+                    if i > 0 {
+                        o.put_exact(b", ");
+                    }
+                    r.export(o);
+                }
+            }
+        }
+    }
+
+    ///Returns an Alt-Svc field value.
+    pub fn to_field_value(&self) -> FieldValue {
+        let mut v = Vec::new();
+        self.export(&mut v);
+        FieldValue::owned(v)
+    }
+}
+
+#[inline(always)]
+fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+#[inline(always)]
+fn hex(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|n| n as u8)
+}
+
+///A parser of Alt-Svc field values.
+struct Parser<'a>(&'a [u8], usize);
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.0.get(self.1).copied()
+    }
+
+    fn is_end(&self) -> bool {
+        self.1 >= self.0.len()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let r = self.peek() == Some(b);
+        if r {
+            self.1 += 1;
+        }
+        r
+    }
+
+    fn skip_ows(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.1 += 1;
+        }
+    }
+
+    fn token(&mut self) -> &'a [u8] {
+        let a = self.1;
+        while self.peek().is_some_and(is_tchar) {
+            self.1 += 1;
+        }
+        &self.0[a..self.1]
+    }
+
+    fn quoted_string(&mut self) -> Result<Vec<u8>, &'static str> {
+        if !self.eat(b'"') {
+            return Err(INVALID_ALT_SVC);
+        }
+        let mut v = Vec::new();
+        loop {
+            match self.peek() {
+                Some(b'\\') => {
+                    self.1 += 1;
+                    match self.peek() {
+                        Some(b'\t' | 0x20..=0x7e | 0x80..=0xff) => {}
+                        _ => return Err(INVALID_ALT_SVC),
+                    }
+                }
+                Some(b'"') => {
+                    self.1 += 1;
+                    return Ok(v);
+                }
+                Some(b'\t' | 0x20..=0x7e | 0x80..=0xff) => {}
+                _ => return Err(INVALID_ALT_SVC),
+            }
+            v.push(self.0[self.1]);
+            self.1 += 1;
+        }
+    }
+
+    fn alternative(&mut self) -> Result<Alternative, &'static str> {
+        let s = self.token();
+        let mut protocol_id = Vec::with_capacity(s.len());
+        let mut i = 0;
+        while i < s.len() {
+            if s[i] == b'%' {
+                let n = s
+                    .get(i + 1..i + 3)
+                    .and_then(|o| Some((hex(o[0])? << 4) | hex(o[1])?))
+                    .ok_or(INVALID_ALT_SVC)?;
+                protocol_id.push(n);
+                i += 3;
+            } else {
+                protocol_id.push(s[i]);
+                i += 1;
+            }
+        }
+        if protocol_id.is_empty() || !self.eat(b'=') {
+            return Err(INVALID_ALT_SVC);
+        }
+        let authority = self.quoted_string()?;
+        let n = authority
+            .iter()
+            .rposition(|&b| b == b':')
+            .ok_or(INVALID_ALT_SVC)?;
+        let port = std::str::from_utf8(&authority[n + 1..])
+            .ok()
+            .filter(|o| !o.is_empty() && o.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|o| o.parse().ok())
+            .ok_or(INVALID_ALT_SVC)?;
+        let mut r = Alternative::new(protocol_id, &authority[..n], port);
+        loop {
+            let a = self.1;
+            self.skip_ows();
+            if !self.eat(b';') {
+                self.1 = a;
+                return Ok(r);
+            }
+            self.skip_ows();
+            let name = self.token();
+            if name.is_empty() || !self.eat(b'=') {
+                return Err(INVALID_ALT_SVC);
+            }
+            let value = if self.peek() == Some(b'"') {
+                self.quoted_string()?
+            } else {
+                self.token().to_vec()
+            };
+            let digits = !value.is_empty() && value.iter().all(|b| b.is_ascii_digit());
+            match name {
+                b"ma" if digits => {
+                    r.max_age = value.iter().fold(0u64, |n, b| {
+                        n.saturating_mul(10).saturating_add((b - b'0') as u64)
+                    });
+                }
+                b"persist" => {
+                    r.persist = value == b"1";
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alt_svc() {
+        assert_eq!(AltSvcValue::parse(b" clear "), Ok(AltSvcValue::Clear));
+        let r = AltSvcValue::parse(
+            b"h3=\":443\"; ma=3600, h2=\"alt.example.com:8000\"; persist=1; x=\"y\",",
+        )
+        .unwrap();
+        let AltSvcValue::Alternatives(v) = &r else {
+            panic!("alternatives");
+        };
+        assert_eq!(v.len(), 2);
+        assert_eq!(v[0].protocol_id(), b"h3");
+        assert!(v[0].host().is_empty());
+        assert_eq!(v[0].port(), 443);
+        assert_eq!(v[0].max_age(), 3600);
+        assert_eq!(v[1].host(), b"alt.example.com");
+        assert!(v[1].persist());
+        assert_eq!(v[1].max_age(), DEFAULT_MAX_AGE);
+        assert_eq!(
+            r.to_field_value(),
+            "h3=\":443\"; ma=3600, h2=\"alt.example.com:8000\"; persist=1"
+        );
+
+        let r = AltSvcValue::parse(b"w%3Dx%3Ay=\"[::1]:80\"").unwrap();
+        assert_eq!(
+            r,
+            AltSvcValue::Alternatives(vec![Alternative::new("w=x:y", "[::1]", 80)])
+        );
+        assert_eq!(r.to_field_value(), "w%3Dx%3Ay=\"[::1]:80\"");
+
+        assert!(AltSvcValue::parse(b"").is_err());
+        assert!(AltSvcValue::parse(b"h3=\"443\"").is_err());
+        assert!(AltSvcValue::parse(b"h3=\":443\" h2=\":443\"").is_err());
+        assert!(AltSvcValue::parse(b"h3=:443").is_err());
+    }
+}
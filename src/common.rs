@@ -139,6 +139,7 @@ const FIELD_NAME: &[&[u8]] = &[
     b"max-forwards",
     b"origin",
     b"pragma",
+    b"priority",
     b"proxy-authenticate",
     b"proxy-authorization",
     b"public-key-pins",
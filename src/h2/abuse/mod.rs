@@ -0,0 +1,332 @@
+/*!
+A module for detecting abusive peers.
+
+[`AbuseDetector`] consumes parsed frames, and counts the frames which are cheap to send but expensive to process:
+streams reset by the peer after HEADERS (the rapid reset attack, CVE-2023-44487), SETTINGS and PING frames which need an ACK,
+and empty DATA, HEADERS and CONTINUATION frames which end nothing. The frames are counted in a fixed window,
+when a count exceeds its limit, the peer is abusive, and the connection should be closed by a GOAWAY frame with ENHANCE_YOUR_CALM.
+The number of CONTINUATION frames in a field block is limited by [`FieldBlockAssembler`](crate::h2::assembler::FieldBlockAssembler).
+
+# Examples
+```
+use httpenergy::h2::abuse::*;
+use httpenergy::h2::error::ErrorCode;
+use httpenergy::h2::frame::*;
+use httpenergy::*;
+use std::time::Instant;
+
+let mut s = Vec::new();
+for i in 0..3 {
+    Ping::new(false, i).export(&mut s);
+}
+let mut limits = AbuseLimits::default();
+limits.set_max_control_frames(2);
+let mut d = AbuseDetector::new(limits);
+let now = Instant::now();
+let mut g = s.as_slice().into_get();
+let mut r = None;
+while let Ok(o) = get_frame(&mut g) {
+    r = d.push(&o, now);
+}
+assert_eq!(r, Some(Abuse::ControlFlood));
+assert_eq!(d.goaway().unwrap().error(), ErrorCode::EnhanceYourCalm);
+```
+*/
+
+use super::error::ErrorCode;
+use super::frame::*;
+use crate::io::*;
+use crate::prty::*;
+use derive_more::Debug;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+///The default length of the window in which frames are counted.
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+///The default maximum number of streams reset by the peer after HEADERS in a window.
+pub const DEFAULT_MAX_RAPID_RESETS: u32 = 100;
+///The default maximum number of SETTINGS and PING frames which need an ACK in a window.
+pub const DEFAULT_MAX_CONTROL_FRAMES: u32 = 100;
+///The default maximum number of empty frames in a window.
+pub const DEFAULT_MAX_EMPTY_FRAMES: u32 = 100;
+///The default maximum number of streams tracked for rapid resets.
+pub const DEFAULT_MAX_TRACKED_STREAMS: u32 = 1000;
+
+///The limits of an [`AbuseDetector`].
+#[derive(Clone, Copy, CopyGetters, Debug, Eq, PartialEq, Setters)]
+#[getset(get_copy = "pub", set = "pub")]
+pub struct AbuseLimits {
+    window: Duration,
+    max_rapid_resets: u32,
+    max_control_frames: u32,
+    max_empty_frames: u32,
+    max_tracked_streams: u32,
+}
+
+impl Default for AbuseLimits {
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_WINDOW,
+            max_rapid_resets: DEFAULT_MAX_RAPID_RESETS,
+            max_control_frames: DEFAULT_MAX_CONTROL_FRAMES,
+            max_empty_frames: DEFAULT_MAX_EMPTY_FRAMES,
+            max_tracked_streams: DEFAULT_MAX_TRACKED_STREAMS,
+        }
+    }
+}
+
+///Represents a kind of abuse.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum Abuse {
+    ///Too many streams are reset by the peer after HEADERS.
+    RapidReset,
+    ///Too many SETTINGS and PING frames which need an ACK.
+    ControlFlood,
+    ///Too many empty DATA, HEADERS and CONTINUATION frames.
+    EmptyFrameFlood,
+}
+
+impl Abuse {
+    ///Returns the reason, which can be the additional debug data of GOAWAY.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::RapidReset => "rapid reset",
+            Self::ControlFlood => "control frame flood",
+            Self::EmptyFrameFlood => "empty frame flood",
+        }
+    }
+
+    ///Returns the error code to signal, which is ENHANCE_YOUR_CALM.
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode::EnhanceYourCalm
+    }
+}
+
+impl std::fmt::Display for Abuse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.reason())
+    }
+}
+
+///Counts the frames received from the peer, and detects abuse.
+///
+///A stream is counted as a rapid reset if it is reset by the peer after HEADERS and before it is closed,
+///the stream must be reported by [`AbuseDetector::close`] when it is closed normally.
+///At most `max_tracked_streams` streams are tracked, the lowest ones are forgotten, and a later reset of them is not counted.
+///Once abuse is detected, it is kept.
+#[derive(Debug, CopyGetters)]
+pub struct AbuseDetector {
+    #[getset(get_copy = "pub")]
+    limits: AbuseLimits,
+    window_start: Option<Instant>,
+    rapid_resets: u32,
+    control_frames: u32,
+    empty_frames: u32,
+    #[debug("{}", streams.len())]
+    streams: BTreeSet<u32>,
+    #[getset(get_copy = "pub")]
+    last_stream_id: u32,
+    #[getset(get_copy = "pub")]
+    detected: Option<Abuse>,
+}
+
+impl Default for AbuseDetector {
+    fn default() -> Self {
+        Self::new(AbuseLimits::default())
+    }
+}
+
+impl AbuseDetector {
+    ///Creates.
+    pub fn new(limits: AbuseLimits) -> Self {
+        Self {
+            limits,
+            window_start: None,
+            rapid_resets: 0,
+            control_frames: 0,
+            empty_frames: 0,
+            streams: BTreeSet::new(),
+            last_stream_id: 0,
+            detected: None,
+        }
+    }
+
+    ///Counts a frame received at `now`. Returns the abuse if detected.
+    ///
+    ///A stream is tracked from HEADERS, [`AbuseDetector::close`] must be called when it is closed normally,
+    ///otherwise a later reset of it is counted as a rapid reset.
+    pub fn push(&mut self, r: &FrameResult, now: Instant) -> Option<Abuse> {
+        if self.detected.is_some() {
+            return self.detected;
+        }
+        match self.window_start {
+            Some(o) if now.saturating_duration_since(o) < self.limits.window => {}
+            _ => {
+                self.window_start = Some(now);
+                self.rapid_resets = 0;
+                self.control_frames = 0;
+                self.empty_frames = 0;
+            }
+        }
+        match r {
+            FrameResult::Headers(o) => {
+                let stream_id = o.stream_identifier();
+                self.streams.insert(stream_id);
+                if self.streams.len() > self.limits.max_tracked_streams as usize {
+                    self.streams.pop_first();
+                }
+                self.last_stream_id = self.last_stream_id.max(stream_id);
+                if o.field_block_fragment_length() == 0 && !o.end_stream() {
+                    self.empty_frames += 1;
+                }
+            }
+            FrameResult::Continuation(o)
+                if o.field_block_fragment_length() == 0 && !o.end_headers() =>
+            {
+                self.empty_frames += 1;
+            }
+            FrameResult::Data(o) if o.data_length() == 0 && !o.end_stream() => {
+                self.empty_frames += 1;
+            }
+            FrameResult::RstStream(o) if self.streams.remove(&o.stream_identifier()) => {
+                self.rapid_resets += 1;
+            }
+            FrameResult::Settings(o) if !o.ack() => {
+                self.control_frames += 1;
+            }
+            FrameResult::Ping(o) if !o.ack() => {
+                self.control_frames += 1;
+            }
+            _ => {}
+        }
+        let limits = &self.limits;
+        self.detected = if self.rapid_resets > limits.max_rapid_resets {
+            Some(Abuse::RapidReset)
+        } else if self.control_frames > limits.max_control_frames {
+            Some(Abuse::ControlFlood)
+        } else if self.empty_frames > limits.max_empty_frames {
+            Some(Abuse::EmptyFrameFlood)
+        } else {
+            None
+        };
+        self.detected
+    }
+
+    ///Reports a stream is closed normally, a later reset of it is not counted. Returns false if it is not tracked.
+    pub fn close(&mut self, stream_id: u32) -> bool {
+        self.streams.remove(&stream_id)
+    }
+
+    ///Returns the recommended GOAWAY frame with ENHANCE_YOUR_CALM if abuse is detected.
+    ///The last stream identifier is the largest one the peer sent HEADERS on.
+    pub fn goaway(&self) -> Option<Goaway> {
+        let r = self.detected?;
+        let s = r.reason().as_bytes();
+        let mut o = Goaway::new(s.len());
+        o.set_last_stream_id(self.last_stream_id);
+        o.set_error_code(r.error_code());
+        o.additional_debug_data_mut().put_exact(s);
+        Some(o)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(d: &mut AbuseDetector, s: &[u8], now: Instant) -> Option<Abuse> {
+        let mut g = s.into_get();
+        let mut r = None;
+        while let Ok(o) = get_frame(&mut g) {
+            r = d.push(&o, now);
+        }
+        r
+    }
+
+    #[test]
+    fn rapid_reset() {
+        let mut limits = AbuseLimits::default();
+        limits.set_max_rapid_resets(2);
+        let mut d = AbuseDetector::new(limits);
+        let now = Instant::now();
+        let mut s = Vec::new();
+        for i in [1, 3, 5] {
+            let mut o = Headers::new(i, 1);
+            o.set_end_headers(true);
+            o.field_block_fragment_mut().put_u8(0x82);
+            o.export(&mut s);
+        }
+        RstStream::new(1, ErrorCode::Cancel).export(&mut s);
+        RstStream::new(7, ErrorCode::Cancel).export(&mut s);
+        RstStream::new(3, ErrorCode::Cancel).export(&mut s);
+        assert_eq!(push(&mut d, &s, now), None);
+        assert!(d.close(5));
+
+        let mut s = Vec::new();
+        RstStream::new(5, ErrorCode::Cancel).export(&mut s);
+        assert_eq!(push(&mut d, &s, now + DEFAULT_WINDOW), None);
+        let mut s = Vec::new();
+        for i in [9, 11, 13] {
+            let mut o = Headers::new(i, 1);
+            o.set_end_headers(true);
+            o.field_block_fragment_mut().put_u8(0x82);
+            o.export(&mut s);
+            RstStream::new(i, ErrorCode::Cancel).export(&mut s);
+        }
+        let later = now + DEFAULT_WINDOW;
+        assert_eq!(push(&mut d, &s, later), Some(Abuse::RapidReset));
+        let o = d.goaway().unwrap();
+        assert_eq!(o.last_stream_id(), 13);
+        assert_eq!(o.additional_debug_data()[..], b"rapid reset"[..]);
+    }
+
+    #[test]
+    fn tracked_streams() {
+        let mut limits = AbuseLimits::default();
+        limits.set_max_rapid_resets(0);
+        limits.set_max_tracked_streams(2);
+        let mut d = AbuseDetector::new(limits);
+        let now = Instant::now();
+        let mut s = Vec::new();
+        for i in [1, 3, 5] {
+            let mut o = Headers::new(i, 1);
+            o.set_end_headers(true);
+            o.set_end_stream(true);
+            o.field_block_fragment_mut().put_u8(0x82);
+            o.export(&mut s);
+        }
+        RstStream::new(1, ErrorCode::Cancel).export(&mut s);
+        assert_eq!(push(&mut d, &s, now), None);
+        assert!(!d.close(1));
+        assert!(d.close(3));
+        let mut s = Vec::new();
+        RstStream::new(5, ErrorCode::Cancel).export(&mut s);
+        assert_eq!(push(&mut d, &s, now), Some(Abuse::RapidReset));
+    }
+
+    #[test]
+    fn empty_frames() {
+        let mut limits = AbuseLimits::default();
+        limits.set_max_empty_frames(3);
+        let mut d = AbuseDetector::new(limits);
+        let now = Instant::now();
+        let mut s = Vec::new();
+        Headers::new(1, 0).export(&mut s);
+        Continuation::new(1, 0).export(&mut s);
+        Data::new(1, 0).export(&mut s);
+        let mut o = Data::new(1, 0);
+        o.set_end_stream(true);
+        o.export(&mut s);
+        assert_eq!(push(&mut d, &s, now), None);
+        let mut s = Vec::new();
+        let mut o = Data::new(1, 0);
+        o.set_padded(true);
+        o.set_pad_length(10);
+        o.export(&mut s);
+        assert_eq!(push(&mut d, &s, now), Some(Abuse::EmptyFrameFlood));
+        let mut s = Vec::new();
+        Ping::new(false, 0).export(&mut s);
+        assert_eq!(push(&mut d, &s, now), Some(Abuse::EmptyFrameFlood));
+    }
+}
@@ -0,0 +1,319 @@
+/*!
+A module for reassembling field blocks.
+
+A field block is carried by a HEADERS or PUSH_PROMISE frame, followed by zero or more CONTINUATION frames on the same stream,
+until the END_HEADERS flag is set. No other frame may be interleaved.
+[`FieldBlockAssembler`] enforces these rules, and limits the number of CONTINUATION frames and the total size of a block,
+which defends against CONTINUATION floods.
+
+# Examples
+```
+use httpenergy::h2::assembler::*;
+use httpenergy::h2::frame::*;
+use httpenergy::h2::*;
+use httpenergy::*;
+
+let mut r = H2Request::new("GET");
+r.set_scheme(Some("https"));
+r.set_path(Some("/index.html"));
+r.add_field("user-agent", "httpenergy");
+let mut helper = HeadersHelper::new(1, 8, 8);
+handle_request_pseudo_header_fields(&r, &mut helper);
+handle_fields(&r, &mut helper);
+let (mut headers, mut v) = helper.take();
+headers.set_end_stream(true);
+v.last_mut().unwrap().set_end_headers(true);
+let mut s = Vec::new();
+headers.export(&mut s);
+for o in v {
+    o.export(&mut s);
+}
+
+let mut a = FieldBlockAssembler::default();
+let mut g = s.as_slice().into_get();
+let mut block = None;
+while let Ok(r) = get_frame(&mut g) {
+    block = a.push(&r, &mut g).unwrap();
+}
+let block = block.unwrap();
+assert_eq!(block.stream_identifier(), 1);
+assert!(block.end_stream());
+assert!(block.continuations() > 0);
+assert!(!a.is_pending());
+```
+*/
+
+use super::error::ConnectionError;
+use super::error::ErrorCode;
+use super::frame::*;
+use crate::prty::*;
+use crate::*;
+use derive_more::Debug;
+
+///The default maximum number of CONTINUATION frames in a field block.
+pub const DEFAULT_MAX_CONTINUATIONS: usize = 64;
+///The default maximum size of a field block.
+pub const DEFAULT_MAX_FIELD_BLOCK_SIZE: usize = 256 * 1024;
+
+///Represents a complete field block.
+#[derive(CopyGetters, Debug, Getters)]
+#[getset(get_copy = "pub")]
+pub struct FieldBlock {
+    stream_identifier: u32,
+    ///The promised stream identifier, or None if the block is carried by HEADERS.
+    promised_stream_id: Option<u32>,
+    end_stream: bool,
+    priority: bool,
+    exclusive: bool,
+    stream_dependency: u32,
+    weight: u8,
+    continuations: usize,
+    #[getset(skip)]
+    #[getset(get = "pub")]
+    #[debug("{}", field_block.len())]
+    field_block: Vec<u8>,
+}
+
+impl FieldBlock {
+    ///Takes the field block out of self.
+    pub fn into_field_block(self) -> Vec<u8> {
+        self.field_block
+    }
+}
+
+///Reassembles field blocks from HEADERS or PUSH_PROMISE frames and the following CONTINUATION frames.
+#[derive(CopyGetters, Debug, Setters)]
+pub struct FieldBlockAssembler {
+    #[getset(get_copy = "pub", set = "pub")]
+    max_continuations: usize,
+    #[getset(get_copy = "pub", set = "pub")]
+    max_size: usize,
+    current: Option<FieldBlock>,
+}
+
+impl Default for FieldBlockAssembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONTINUATIONS, DEFAULT_MAX_FIELD_BLOCK_SIZE)
+    }
+}
+
+impl FieldBlockAssembler {
+    ///Creates.
+    pub fn new(max_continuations: usize, max_size: usize) -> Self {
+        Self {
+            max_continuations,
+            max_size,
+            current: None,
+        }
+    }
+
+    ///Returns true if a field block is started but not ended.
+    pub fn is_pending(&self) -> bool {
+        self.current.is_some()
+    }
+
+    ///Returns the stream identifier of the pending field block.
+    pub fn pending_stream(&self) -> Option<u32> {
+        self.current.as_ref().map(|b| b.stream_identifier)
+    }
+
+    ///Discards the pending field block.
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+
+    ///Handles a parsed frame, `o` is the source it was parsed from.
+    ///Returns the field block when it ends, or None if it is pending or the frame doesn't carry a field block.
+    ///
+    ///Returns a connection error if a frame is interleaved, a CONTINUATION frame is unexpected or on another stream
+    ///(PROTOCOL_ERROR), or a limit is exceeded (ENHANCE_YOUR_CALM). The pending field block is discarded on error.
+    pub fn push(
+        &mut self,
+        r: &FrameResult,
+        o: &mut dyn GetU8,
+    ) -> Result<Option<FieldBlock>, ConnectionError> {
+        let (b, end_headers) = match r {
+            FrameResult::Headers(h) => {
+                self.check_idle()?;
+                let b = FieldBlock {
+                    stream_identifier: h.stream_identifier(),
+                    promised_stream_id: None,
+                    end_stream: h.end_stream(),
+                    priority: h.priority(),
+                    exclusive: h.exclusive(),
+                    stream_dependency: h.stream_dependency(),
+                    weight: h.weight(),
+                    continuations: 0,
+                    field_block: Vec::new(),
+                };
+                (self.current.insert(b), h.end_headers())
+            }
+            FrameResult::PushPromise(h) => {
+                self.check_idle()?;
+                let b = FieldBlock {
+                    stream_identifier: h.stream_identifier(),
+                    promised_stream_id: Some(h.promised_stream_id()),
+                    end_stream: false,
+                    priority: false,
+                    exclusive: false,
+                    stream_dependency: 0,
+                    weight: 0,
+                    continuations: 0,
+                    field_block: Vec::new(),
+                };
+                (self.current.insert(b), h.end_headers())
+            }
+            FrameResult::Continuation(h) => {
+                let Some(b) = &mut self.current else {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "unexpected CONTINUATION",
+                    ));
+                };
+                if h.stream_identifier() != b.stream_identifier {
+                    self.current = None;
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "CONTINUATION on another stream",
+                    ));
+                }
+                b.continuations += 1;
+                if b.continuations > self.max_continuations {
+                    self.current = None;
+                    return Err(ConnectionError::new(
+                        ErrorCode::EnhanceYourCalm,
+                        "too many CONTINUATION frames",
+                    ));
+                }
+                (b, h.end_headers())
+            }
+            _ => {
+                self.check_idle()?;
+                return Ok(None);
+            }
+        };
+        let fragment = match r {
+            FrameResult::Headers(h) => h.field_block_fragment(o),
+            FrameResult::PushPromise(h) => h.field_block_fragment(o),
+            FrameResult::Continuation(h) => h.field_block_fragment(o),
+            _ => None,
+        };
+        if let Some(mut f) = fragment {
+            let f = f.get_surplus();
+            if b.field_block.len() + f.len() > self.max_size {
+                self.current = None;
+                return Err(ConnectionError::new(
+                    ErrorCode::EnhanceYourCalm,
+                    "field block too large",
+                ));
+            }
+            b.field_block.extend_from_slice(f);
+        }
+        Ok(if end_headers {
+            self.current.take()
+        } else {
+            None
+        })
+    }
+
+    #[inline(always)]
+    fn check_idle(&mut self) -> Result<(), ConnectionError> {
+        if self.current.take().is_some() {
+            return Err(ConnectionError::new(
+                ErrorCode::ProtocolError,
+                "frame in field block",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(max: usize) -> Vec<u8> {
+        let mut r = Headers::new(3, 10);
+        r.set_priority(true);
+        r.set_stream_dependency(1);
+        r.set_weight(15);
+        r.field_block_fragment_mut().put_exact(b"0123456789");
+        let mut s = Vec::new();
+        r.export(&mut s);
+        for i in 0..max {
+            let mut c = Continuation::new(3, 4);
+            c.field_block_fragment_mut().put_exact(b"abcd");
+            c.set_end_headers(i + 1 == max);
+            c.export(&mut s);
+        }
+        s
+    }
+
+    #[test]
+    fn assemble() {
+        let mut a = FieldBlockAssembler::default();
+        let s = frames(3);
+        let mut g = s.as_slice().into_get();
+        let mut v = Vec::new();
+        while let Ok(r) = get_frame(&mut g) {
+            v.extend(a.push(&r, &mut g).unwrap());
+        }
+        assert_eq!(v.len(), 1);
+        let b = &v[0];
+        assert_eq!(b.continuations(), 3);
+        assert!(b.priority());
+        assert_eq!(b.stream_dependency(), 1);
+        assert_eq!(b.weight(), 15);
+        assert_eq!(b.field_block().len(), 22);
+
+        let mut a = FieldBlockAssembler::new(2, 100);
+        let mut g = s.as_slice().into_get();
+        let e = loop {
+            let r = get_frame(&mut g).unwrap();
+            if let Err(e) = a.push(&r, &mut g) {
+                break e;
+            }
+        };
+        assert_eq!(e.error_code(), ErrorCode::EnhanceYourCalm);
+        assert!(!a.is_pending());
+
+        let mut a = FieldBlockAssembler::new(10, 16);
+        let mut g = s.as_slice().into_get();
+        let r = get_frame(&mut g).unwrap();
+        a.push(&r, &mut g).unwrap();
+        let r = get_frame(&mut g).unwrap();
+        a.push(&r, &mut g).unwrap();
+        let r = get_frame(&mut g).unwrap();
+        assert!(a.push(&r, &mut g).is_err());
+    }
+
+    #[test]
+    fn interleave() {
+        let mut a = FieldBlockAssembler::default();
+        let mut s = frames(0);
+        Ping::new(false, 1).export(&mut s);
+        let mut g = s.as_slice().into_get();
+        let r = get_frame(&mut g).unwrap();
+        assert!(a.push(&r, &mut g).unwrap().is_none());
+        assert_eq!(a.pending_stream(), Some(3));
+        let r = get_frame(&mut g).unwrap();
+        let e = a.push(&r, &mut g).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+
+        let mut s = Vec::new();
+        Continuation::new(3, 0).export(&mut s);
+        let mut g = s.as_slice().into_get();
+        let r = get_frame(&mut g).unwrap();
+        assert!(a.push(&r, &mut g).is_err());
+
+        let mut s = frames(0);
+        let mut c = Continuation::new(5, 0);
+        c.set_end_headers(true);
+        c.export(&mut s);
+        let mut g = s.as_slice().into_get();
+        let r = get_frame(&mut g).unwrap();
+        a.push(&r, &mut g).unwrap();
+        let r = get_frame(&mut g).unwrap();
+        assert!(a.push(&r, &mut g).is_err());
+    }
+}
@@ -1,8 +1,9 @@
 use super::frame::*;
 use super::hpack::*;
 use super::*;
+use crate::table::*;
+use crate::validate::*;
 use derive_more::Debug;
-use std::collections::VecDeque;
 
 macro_rules! frame_helper {
     ($a:ident, $b:ident, $c:ty, $d:literal) => {
@@ -206,12 +207,36 @@ fields_vec!(add_fields_to_request, H2Request);
 
 fields_vec!(add_fields_to_response, H2Response);
 
-///This struct is used for test, maybe not meet the requirements.
-#[derive(Debug, CopyGetters)]
+macro_rules! try_fields_vec {
+    ($a:ident, $b:ty) => {
+        ///A helper to build a message from some fields, differentiate pseudo-header fields.
+        ///
+        ///Returns an error naming the offending field if the message is malformed, and r is not changed.
+        pub fn $a(v: Vec<(FieldName, FieldValue)>, r: &mut $b) -> Result<(), MalformedError> {
+            check_fields(&v)?;
+            let mut t = <$b>::default();
+            for (name, value) in v {
+                if name.is_pseudo() {
+                    t.try_set_pseudo(name.as_bytes(), value)?;
+                } else {
+                    t.add_field(name, value);
+                }
+            }
+            t.validate()?;
+            *r = t;
+            Ok(())
+        }
+    };
+}
+
+try_fields_vec!(try_add_fields_to_request, H2Request);
+
+try_fields_vec!(try_add_fields_to_response, H2Response);
+
+///A dynamic table combined with the static table into a single index address space.
+#[derive(Debug)]
 pub struct DynamicTable {
-    #[getset(get_copy = "pub")]
-    capacity: usize,
-    inner: VecDeque<(FieldName, FieldValue)>,
+    inner: FieldTable,
 }
 
 impl Default for DynamicTable {
@@ -221,57 +246,54 @@ impl Default for DynamicTable {
 }
 
 impl DynamicTable {
+    ///Creates.
     pub fn new(capacity: usize) -> Self {
         Self {
-            capacity,
-            inner: VecDeque::new(),
+            inner: FieldTable::new(capacity),
         }
     }
 
+    ///Returns the capacity.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    ///Removes all entries.
     pub fn clear(&mut self) {
         self.inner.clear();
     }
 
+    ///Sets the capacity, evicts entries to fit.
     pub fn set_capacity(&mut self, n: usize) {
-        self.capacity = n;
-        if n == 0 {
-            self.clear();
-        } else {
-            self.eviction();
-        }
+        self.inner.set_capacity(n);
     }
 
+    ///Returns the size.
     pub fn size(&self) -> usize {
-        let mut i = 0;
-        for (a, b) in &self.inner {
-            i += a.len() + b.len() + 32;
-        }
-        i
+        self.inner.size()
     }
 
+    ///Evicts entries to fit the capacity.
     pub fn eviction(&mut self) {
-        while self.size() > self.capacity {
-            self.inner.pop_back();
-        }
+        self.inner.set_capacity(self.inner.capacity());
     }
 
+    ///Adds an entry.
     pub fn add(&mut self, name: FieldName, value: FieldValue) {
-        self.inner.push_front((name, value));
-        self.eviction();
+        self.inner.insert(name, value);
     }
 
+    ///Returns an entry in the static table or the dynamic table.
     pub fn get_entry(&self, n: usize) -> Option<&(FieldName, FieldValue)> {
         match static_table_get_entry(n) {
             Ok(o) => Some(o),
-            Err(e) => e.and_then(|n| self.inner.get(n - 1)),
+            Err(e) => e.and_then(|n| self.inner.get(n)),
         }
     }
 
+    ///Returns a field name in the static table or the dynamic table.
     pub fn get_name(&self, n: usize) -> Option<&FieldName> {
-        match static_table_get_name(n) {
-            Ok(o) => Some(o),
-            Err(e) => e.and_then(|n| self.inner.get(n - 1).map(|(o, _)| o)),
-        }
+        self.get_entry(n).map(|(o, _)| o)
     }
 }
 
@@ -341,6 +363,9 @@ pub fn handle_request_pseudo_header_fields(r: &H2Request, o: &mut dyn PutU8) {
     if let Some(v) = r.indexed_path() {
         handle_index_ref(v, o)
     }
+    if let Some(v) = r.protocol() {
+        build_without_indexing_new_name(PSEUDO_PROTOCOL.into(), v.as_bytes().into(), o);
+    }
 }
 
 ///This function is used for test, maybe not meet the requirements.
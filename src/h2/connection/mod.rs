@@ -0,0 +1,1570 @@
+/*!
+A sans-IO HTTP/2 connection.
+
+[`Connection`] doesn't read or write any socket. Bytes received from the peer are given to [`Connection::receive`],
+which parses frames, tracks the state of streams and queues [`Event`]s. Frames to send are queued by the send_* methods,
+and the bytes can be taken by [`Connection::take_outbound`].
+
+# Examples
+```
+use httpenergy::h2::connection::*;
+use httpenergy::h2::*;
+
+let mut client = Connection::new(Role::Client);
+let mut server = Connection::new(Role::Server);
+
+let mut r = H2Request::new("GET");
+r.set_scheme(Some("https"));
+r.set_authority(Some("example.org"));
+r.set_path(Some("/"));
+let stream_id = client.send_request(&r, true).unwrap();
+
+server.receive(&client.take_outbound()).unwrap();
+while let Some(e) = server.poll_event() {
+    if let Event::Headers { stream_id, .. } = e {
+        server.send_response(stream_id, &H2Response::new("200"), false).unwrap();
+        server.send_data(stream_id, b"hello", true).unwrap();
+    }
+}
+
+client.receive(&server.take_outbound()).unwrap();
+let mut body = Vec::new();
+while let Some(e) = client.poll_event() {
+    if let Event::Data { data, .. } = e {
+        body.extend_from_slice(&data);
+    }
+}
+assert_eq!(body, b"hello");
+assert_eq!(client.stream_state(stream_id), StreamState::Closed);
+```
+*/
+
+use super::assembler::*;
+pub use super::error::ConnectionError;
+use super::error::ErrorCode;
+use super::flow::*;
+use super::frame::*;
+use super::hpack::*;
+use super::settings::*;
+use super::*;
+use crate::priority::PriorityParams;
+use derive_more::Debug;
+use std::collections::{HashMap, VecDeque};
+
+///The client connection preface.
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const MAX_STREAM_ID: u32 = 0x7fff_ffff;
+
+///The role of an endpoint.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum Role {
+    ///Initiates streams with odd-numbered stream identifiers.
+    Client,
+    ///Initiates streams with even-numbered stream identifiers by server push.
+    Server,
+}
+
+///Represents the state of a stream.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum StreamState {
+    Idle,
+    ReservedLocal,
+    ReservedRemote,
+    Open,
+    HalfClosedLocal,
+    HalfClosedRemote,
+    Closed,
+}
+
+///Represents an event of a connection.
+#[derive(Debug)]
+#[repr(u8)]
+pub enum Event {
+    ///A request, a response, or an interim response.
+    Headers {
+        stream_id: u32,
+        fields: Vec<(FieldName, FieldValue)>,
+        end_stream: bool,
+    },
+    ///A part of message content.
+    Data {
+        stream_id: u32,
+        #[debug("{}", data.len())]
+        data: Vec<u8>,
+        end_stream: bool,
+    },
+    ///A trailer section, which ends the stream.
+    Trailers {
+        stream_id: u32,
+        fields: Vec<(FieldName, FieldValue)>,
+    },
+    ///A stream reserved by the peer, with the promised request.
+    PushPromise {
+        stream_id: u32,
+        promised_stream_id: u32,
+        fields: Vec<(FieldName, FieldValue)>,
+    },
+    ///A stream is reset by the peer, or by self because of a stream error.
+    Reset {
+        stream_id: u32,
+        error_code: ErrorCode,
+        remote: bool,
+    },
+    ///The peer initiates shutdown.
+    Goaway {
+        last_stream_id: u32,
+        error_code: ErrorCode,
+        #[debug("{}", additional_debug_data.len())]
+        additional_debug_data: Vec<u8>,
+    },
+    ///The peer's settings, which are applied.
+    Settings(ConnectionSettings),
+    ///The peer acknowledges self's settings.
+    SettingsAck,
+    ///The peer acknowledges a PING.
+    PingAck(u64),
+    ///The peer changes the priority of a stream, the priority field value which can't be parsed is ignored.
+    PriorityUpdate {
+        prioritized_stream_id: u32,
+        priority: PriorityParams,
+    },
+    ///The peer increases a flow-control window.
+    WindowUpdate {
+        stream_id: u32,
+        window_size_increment: u32,
+    },
+    ///The server advertises alternative services, the origin is empty if they apply to the origin of the stream.
+    AltSvc {
+        stream_id: u32,
+        origin: Vec<u8>,
+        field_value: Vec<u8>,
+    },
+    ///The server lists the origins it is authoritative for.
+    Origin(Vec<Vec<u8>>),
+}
+
+#[derive(Debug)]
+struct Stream {
+    state: StreamState,
+    headers_received: bool,
+}
+
+///A sans-IO HTTP/2 connection, consumes bytes and emits events, queues outbound frames.
+#[derive(Debug, CopyGetters, Getters)]
+pub struct Connection {
+    #[getset(get_copy = "pub")]
+    role: Role,
+    streams: HashMap<u32, Stream>,
+    next_stream_id: u32,
+    #[getset(get_copy = "pub")]
+    last_peer_stream_id: u32,
+    preface_received: bool,
+    settings_received: bool,
+    assembler: FieldBlockAssembler,
+    parser: FrameParser,
+    decoder: HpackDecoder,
+    encoder: HpackEncoder,
+    #[getset(get = "pub")]
+    flow: FlowControl,
+    #[getset(get = "pub")]
+    local: SettingsTracker,
+    #[getset(get_copy = "pub")]
+    remote: ConnectionSettings,
+    goaway_received: Option<u32>,
+    goaway_sent: Option<u32>,
+    error: Option<ConnectionError>,
+    #[debug("{}", input.len())]
+    input: Vec<u8>,
+    #[debug("{}", output.len())]
+    output: Vec<u8>,
+    events: VecDeque<Event>,
+}
+
+impl Connection {
+    ///Creates. The client connection preface (client only) and an empty SETTINGS frame are queued.
+    pub fn new(role: Role) -> Self {
+        let mut o = Self {
+            role,
+            streams: HashMap::new(),
+            next_stream_id: match role {
+                Role::Client => 1,
+                Role::Server => 2,
+            },
+            last_peer_stream_id: 0,
+            preface_received: role == Role::Client,
+            settings_received: false,
+            assembler: FieldBlockAssembler::default(),
+            parser: FrameParser::default(),
+            decoder: HpackDecoder::default(),
+            encoder: HpackEncoder::default(),
+            flow: FlowControl::default(),
+            local: SettingsTracker::default(),
+            remote: ConnectionSettings::default(),
+            goaway_received: None,
+            goaway_sent: None,
+            error: None,
+            input: Vec::new(),
+            output: Vec::new(),
+            events: VecDeque::new(),
+        };
+        if role == Role::Client {
+            o.output.extend_from_slice(PREFACE);
+        }
+        let _ = o.send_settings(ConnectionSettings::default());
+        o
+    }
+
+    ///Returns the state of a stream.
+    pub fn stream_state(&self, stream_id: u32) -> StreamState {
+        if let Some(o) = self.streams.get(&stream_id) {
+            return o.state;
+        }
+        let closed = if self.is_local_id(stream_id) {
+            stream_id < self.next_stream_id
+        } else {
+            stream_id <= self.last_peer_stream_id
+        };
+        if closed {
+            StreamState::Closed
+        } else {
+            StreamState::Idle
+        }
+    }
+
+    ///Returns the number of active streams initiated by self.
+    pub fn local_active_streams(&self) -> usize {
+        self.streams
+            .iter()
+            .filter(|(k, v)| self.is_local_id(**k) && is_active(v.state))
+            .count()
+    }
+
+    ///Returns the number of active streams initiated by the peer.
+    pub fn remote_active_streams(&self) -> usize {
+        self.streams
+            .iter()
+            .filter(|(k, v)| !self.is_local_id(**k) && is_active(v.state))
+            .count()
+    }
+
+    ///Returns the next event, or None if no event.
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    ///Takes the bytes to send out of self.
+    pub fn take_outbound(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    ///Returns true if there are bytes to send.
+    pub fn has_outbound(&self) -> bool {
+        !self.output.is_empty()
+    }
+
+    ///Consumes bytes received from the peer. Incomplete frames are kept until more bytes are received.
+    ///
+    ///Returns an error if a connection error occurs, then a GOAWAY frame is queued, and the connection can't be used anymore.
+    pub fn receive(&mut self, o: &[u8]) -> Result<(), ConnectionError> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        self.input.extend_from_slice(o);
+        let input = std::mem::take(&mut self.input);
+        let mut i = 0;
+        match self.process(&input, &mut i) {
+            Ok(()) => {
+                self.input = input;
+                self.input.drain(..i);
+                Ok(())
+            }
+            Err(e) => Err(self.fail(e)),
+        }
+    }
+
+    fn process(&mut self, input: &[u8], i: &mut usize) -> Result<(), ConnectionError> {
+        if !self.preface_received {
+            let n = input.len().min(PREFACE.len());
+            if input[..n] != PREFACE[..n] {
+                return Err(ConnectionError::new(
+                    ErrorCode::ProtocolError,
+                    "invalid preface",
+                ));
+            }
+            if n < PREFACE.len() {
+                return Ok(());
+            }
+            self.preface_received = true;
+            *i = n;
+        }
+        loop {
+            let max = self.local.acked().max_frame_size();
+            self.parser
+                .set_max_frame_size(max.max(self.local.latest().max_frame_size()));
+            let mut g = input[*i..].into_get();
+            let r = match self.parser.parse(&mut g) {
+                Ok(r) => r,
+                Err(FrameError::Incomplete(_)) => return Ok(()),
+                Err(e) => {
+                    return Err(ConnectionError::new(
+                        e.error_code().unwrap_or(ErrorCode::ProtocolError),
+                        e.reason().unwrap_or_default(),
+                    ));
+                }
+            };
+            *i += g.index();
+            self.on_frame(r, &mut g)?;
+        }
+    }
+
+    fn on_frame(&mut self, r: FrameResult, g: &mut dyn GetU8) -> Result<(), ConnectionError> {
+        if !self.settings_received {
+            match r {
+                FrameResult::Settings(ref o) if !o.ack() => self.settings_received = true,
+                _ => {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "first frame is not SETTINGS",
+                    ));
+                }
+            }
+        }
+        let block = self.assembler.push(&r, g)?;
+        match r {
+            FrameResult::Data(o) => {
+                let stream_id = o.stream_identifier();
+                check_stream_id(stream_id)?;
+                let data = o
+                    .data(g)
+                    .map(|mut r| r.get_surplus().to_vec())
+                    .unwrap_or_default();
+                self.on_data(stream_id, o.length(), data, o.end_stream())
+            }
+            FrameResult::Headers(o) => {
+                check_stream_id(o.stream_identifier())?;
+                self.on_field_block(block)
+            }
+            FrameResult::Priority(o) => check_stream_id(o.stream_identifier()),
+            FrameResult::RstStream(o) => {
+                let stream_id = o.stream_identifier();
+                check_stream_id(stream_id)?;
+                if self.is_ignored(stream_id) {
+                    return Ok(());
+                }
+                if self.stream_state(stream_id) == StreamState::Idle {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "RST_STREAM on idle stream",
+                    ));
+                }
+                self.close(stream_id);
+                self.events.push_back(Event::Reset {
+                    stream_id,
+                    error_code: o.error(),
+                    remote: true,
+                });
+                Ok(())
+            }
+            FrameResult::Settings(o) => {
+                if o.stream_identifier() != 0 {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "SETTINGS on a stream",
+                    ));
+                }
+                if o.ack() {
+                    if o.length() != 0 {
+                        return Err(ConnectionError::new(
+                            ErrorCode::FrameSizeError,
+                            "SETTINGS ACK with payload",
+                        ));
+                    }
+                    let v = self.local.on_ack()?;
+                    self.decoder
+                        .set_max_table_size(v.header_table_size() as usize);
+                    self.decoder
+                        .set_max_header_list_size(v.max_header_list_size().map(|n| n as usize));
+                    self.flow
+                        .set_local_initial_window_size(v.initial_window_size())
+                        .map_err(connection_error)?;
+                    self.events.push_back(Event::SettingsAck);
+                } else {
+                    if o.length() % 6 != 0 {
+                        return Err(ConnectionError::new(
+                            ErrorCode::FrameSizeError,
+                            "invalid SETTINGS length",
+                        ));
+                    }
+                    if self.role == Role::Client && o.setting().contains(&(SETTINGS_ENABLE_PUSH, 1))
+                    {
+                        return Err(ConnectionError::new(
+                            ErrorCode::ProtocolError,
+                            "SETTINGS_ENABLE_PUSH from server",
+                        ));
+                    }
+                    self.remote.apply(o.setting())?;
+                    self.encoder
+                        .set_header_table_size(self.remote.header_table_size() as usize);
+                    self.flow
+                        .set_remote_initial_window_size(self.remote.initial_window_size())
+                        .map_err(connection_error)?;
+                    let mut ack = Settings::new(0);
+                    ack.set_ack(true);
+                    ack.export(&mut self.output);
+                    self.events.push_back(Event::Settings(self.remote));
+                }
+                Ok(())
+            }
+            FrameResult::PushPromise(o) => {
+                let stream_id = o.stream_identifier();
+                check_stream_id(stream_id)?;
+                if self.role == Role::Server || !self.local.acked().enable_push() {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "unexpected PUSH_PROMISE",
+                    ));
+                }
+                self.on_field_block(block)
+            }
+            FrameResult::Ping(o) => {
+                if o.stream_identifier() != 0 {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "PING on a stream",
+                    ));
+                }
+                if o.ack() {
+                    self.events.push_back(Event::PingAck(o.opaque_data()));
+                } else {
+                    Ping::new(true, o.opaque_data()).export(&mut self.output);
+                }
+                Ok(())
+            }
+            FrameResult::Goaway(o) => {
+                if o.stream_identifier() != 0 {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "GOAWAY on a stream",
+                    ));
+                }
+                let additional_debug_data = o
+                    .additional_debug_data(g)
+                    .map(|mut r| r.get_surplus().to_vec())
+                    .unwrap_or_default();
+                self.goaway_received = Some(o.last_stream_id());
+                self.events.push_back(Event::Goaway {
+                    last_stream_id: o.last_stream_id(),
+                    error_code: o.error(),
+                    additional_debug_data,
+                });
+                Ok(())
+            }
+            FrameResult::WindowUpdate(o) => {
+                let stream_id = o.stream_identifier();
+                if stream_id != 0 && self.is_ignored(stream_id) {
+                    return Ok(());
+                }
+                if stream_id != 0 && self.stream_state(stream_id) == StreamState::Idle {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "WINDOW_UPDATE on idle stream",
+                    ));
+                }
+                if let Err(e) = self
+                    .flow
+                    .on_window_update(stream_id, o.window_size_increment())
+                {
+                    return self.flow_error(e);
+                }
+                self.events.push_back(Event::WindowUpdate {
+                    stream_id,
+                    window_size_increment: o.window_size_increment(),
+                });
+                Ok(())
+            }
+            FrameResult::Continuation(_) => self.on_field_block(block),
+            FrameResult::PriorityUpdate(o) => {
+                if o.stream_identifier() != 0 {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "PRIORITY_UPDATE on a stream",
+                    ));
+                }
+                let prioritized_stream_id = o.prioritized_stream_id();
+                if prioritized_stream_id == 0 {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "PRIORITY_UPDATE for stream 0",
+                    ));
+                }
+                if let Ok(priority) = o.priority(g) {
+                    self.events.push_back(Event::PriorityUpdate {
+                        prioritized_stream_id,
+                        priority,
+                    });
+                }
+                Ok(())
+            }
+            FrameResult::AltSvc(o) => {
+                if self.role == Role::Client && o.is_valid() {
+                    let origin = o.origin(g).map(|mut r| r.get_surplus().to_vec());
+                    let field_value = o
+                        .alt_svc_field_value(g)
+                        .map(|mut r| r.get_surplus().to_vec());
+                    if let (Some(origin), Some(field_value)) = (origin, field_value) {
+                        self.events.push_back(Event::AltSvc {
+                            stream_id: o.stream_identifier(),
+                            origin,
+                            field_value,
+                        });
+                    }
+                }
+                Ok(())
+            }
+            FrameResult::Origin(o) => {
+                if self.role == Role::Client && o.is_valid() {
+                    self.events.push_back(Event::Origin(o.origins().clone()));
+                }
+                Ok(())
+            }
+            FrameResult::Unknown(_) | FrameResult::Extension(_) => Ok(()),
+        }
+    }
+
+    fn on_data(
+        &mut self,
+        stream_id: u32,
+        length: u32,
+        data: Vec<u8>,
+        end_stream: bool,
+    ) -> Result<(), ConnectionError> {
+        if self.is_ignored(stream_id) {
+            self.flow.on_data(0, length).map_err(connection_error)?;
+            self.release(0, length);
+            return Ok(());
+        }
+        let state = self.stream_state(stream_id);
+        if state == StreamState::Idle {
+            return Err(ConnectionError::new(
+                ErrorCode::ProtocolError,
+                "DATA on idle stream",
+            ));
+        }
+        if let Err(e) = self.flow.on_data(stream_id, length) {
+            if e.is_connection_error() {
+                return Err(connection_error(e));
+            }
+            self.stream_error(stream_id, e.error_code());
+            self.release(stream_id, length);
+            return Ok(());
+        }
+        match state {
+            StreamState::Open | StreamState::HalfClosedLocal => {
+                let padding = length - data.len() as u32;
+                if padding > 0 {
+                    self.release(stream_id, padding);
+                }
+                self.events.push_back(Event::Data {
+                    stream_id,
+                    data,
+                    end_stream,
+                });
+                if end_stream {
+                    self.end_remote(stream_id);
+                }
+                Ok(())
+            }
+            StreamState::ReservedLocal | StreamState::ReservedRemote => Err(ConnectionError::new(
+                ErrorCode::ProtocolError,
+                "DATA on reserved stream",
+            )),
+            _ => {
+                self.stream_error(stream_id, ErrorCode::StreamClosed);
+                self.release(stream_id, length);
+                Ok(())
+            }
+        }
+    }
+
+    fn on_field_block(&mut self, b: Option<FieldBlock>) -> Result<(), ConnectionError> {
+        let Some(b) = b else {
+            return Ok(());
+        };
+        let stream_id = b.stream_identifier();
+        let fields = match self.decoder.decode(b.field_block()) {
+            Ok(v) => v,
+            Err(HpackDecodeError::Compression(e)) => {
+                return Err(ConnectionError::new(ErrorCode::CompressionError, e));
+            }
+            Err(e) => {
+                let stream_id = b.promised_stream_id().unwrap_or(stream_id);
+                if self.is_ignored(stream_id) {
+                    return Ok(());
+                }
+                if !self.is_local_id(stream_id) && stream_id > self.last_peer_stream_id {
+                    self.last_peer_stream_id = stream_id;
+                }
+                self.stream_error(stream_id, e.error_code());
+                return Ok(());
+            }
+        };
+        if let Some(promised_stream_id) = b.promised_stream_id() {
+            if self.is_ignored(promised_stream_id) {
+                return Ok(());
+            }
+            return self.on_push_promise(stream_id, promised_stream_id, fields);
+        }
+        let end_stream = b.end_stream();
+        match self.stream_state(stream_id) {
+            StreamState::Idle => {
+                if self.is_local_id(stream_id) {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "HEADERS on idle stream",
+                    ));
+                }
+                if self.is_ignored(stream_id) {
+                    return Ok(());
+                }
+                if self.role == Role::Client {
+                    return Err(ConnectionError::new(
+                        ErrorCode::ProtocolError,
+                        "HEADERS on unreserved server stream",
+                    ));
+                }
+                self.last_peer_stream_id = stream_id;
+                let max = self.local.acked().max_concurrent_streams();
+                if max.is_some_and(|n| self.remote_active_streams() >= n as usize) {
+                    RstStream::new(stream_id, ErrorCode::RefusedStream).export(&mut self.output);
+                    self.events.push_back(Event::Reset {
+                        stream_id,
+                        error_code: ErrorCode::RefusedStream,
+                        remote: false,
+                    });
+                    return Ok(());
+                }
+                let state = if end_stream {
+                    StreamState::HalfClosedRemote
+                } else {
+                    StreamState::Open
+                };
+                self.insert_stream(stream_id, state, true);
+                self.events.push_back(Event::Headers {
+                    stream_id,
+                    fields,
+                    end_stream,
+                });
+            }
+            StreamState::ReservedRemote => {
+                let state = if end_stream {
+                    StreamState::Closed
+                } else {
+                    StreamState::HalfClosedLocal
+                };
+                self.set_state(stream_id, state);
+                if let Some(o) = self.streams.get_mut(&stream_id) {
+                    o.headers_received = true;
+                }
+                self.events.push_back(Event::Headers {
+                    stream_id,
+                    fields,
+                    end_stream,
+                });
+            }
+            StreamState::Open | StreamState::HalfClosedLocal => {
+                let received = self
+                    .streams
+                    .get(&stream_id)
+                    .is_some_and(|o| o.headers_received);
+                if received {
+                    if !end_stream {
+                        self.stream_error(stream_id, ErrorCode::ProtocolError);
+                        return Ok(());
+                    }
+                    self.events.push_back(Event::Trailers { stream_id, fields });
+                } else {
+                    let interim = fields
+                        .iter()
+                        .any(|(k, v)| k == PSEUDO_STATUS && v.as_bytes().starts_with(b"1"));
+                    if !interim && let Some(o) = self.streams.get_mut(&stream_id) {
+                        o.headers_received = true;
+                    }
+                    self.events.push_back(Event::Headers {
+                        stream_id,
+                        fields,
+                        end_stream,
+                    });
+                }
+                if end_stream {
+                    self.end_remote(stream_id);
+                }
+            }
+            StreamState::ReservedLocal => {
+                return Err(ConnectionError::new(
+                    ErrorCode::ProtocolError,
+                    "HEADERS on reserved stream",
+                ));
+            }
+            StreamState::HalfClosedRemote | StreamState::Closed => {
+                self.stream_error(stream_id, ErrorCode::StreamClosed);
+            }
+        }
+        Ok(())
+    }
+
+    fn on_push_promise(
+        &mut self,
+        stream_id: u32,
+        promised_stream_id: u32,
+        fields: Vec<(FieldName, FieldValue)>,
+    ) -> Result<(), ConnectionError> {
+        if self.is_local_id(promised_stream_id)
+            || self.stream_state(promised_stream_id) != StreamState::Idle
+        {
+            return Err(ConnectionError::new(
+                ErrorCode::ProtocolError,
+                "invalid promised stream",
+            ));
+        }
+        match self.stream_state(stream_id) {
+            StreamState::Open | StreamState::HalfClosedLocal => {}
+            _ => {
+                return Err(ConnectionError::new(
+                    ErrorCode::ProtocolError,
+                    "PUSH_PROMISE on invalid stream",
+                ));
+            }
+        }
+        self.last_peer_stream_id = promised_stream_id;
+        self.insert_stream(promised_stream_id, StreamState::ReservedRemote, false);
+        let mut r = H2Request::default();
+        let valid = try_add_fields_to_request(fields.clone(), &mut r).is_ok()
+            && message::check_push_request(&r).is_ok();
+        if !valid {
+            self.stream_error(promised_stream_id, ErrorCode::ProtocolError);
+            return Ok(());
+        }
+        self.events.push_back(Event::PushPromise {
+            stream_id,
+            promised_stream_id,
+            fields,
+        });
+        Ok(())
+    }
+
+    ///Sends a SETTINGS frame with the values which differ from the latest settings sent.
+    ///The settings take effect when the peer acknowledges. Returns an error if a value is invalid.
+    pub fn send_settings(&mut self, o: ConnectionSettings) -> Result<(), ConnectionError> {
+        self.local.send(o)?.export(&mut self.output);
+        Ok(())
+    }
+
+    ///Sends a PING frame.
+    pub fn send_ping(&mut self, opaque_data: u64) {
+        Ping::new(false, opaque_data).export(&mut self.output);
+    }
+
+    ///Sends an ALTSVC frame. Returns an error if self is not a server.
+    pub fn send_altsvc(&mut self, o: AltSvc) -> Result<(), &'static str> {
+        if self.role != Role::Server {
+            return Err("not a server");
+        }
+        o.export(&mut self.output);
+        Ok(())
+    }
+
+    ///Sends an ORIGIN frame. Returns an error if self is not a server.
+    pub fn send_origin(&mut self, o: Origin) -> Result<(), &'static str> {
+        if self.role != Role::Server {
+            return Err("not a server");
+        }
+        o.export(&mut self.output);
+        Ok(())
+    }
+
+    ///Sends a request on a new stream. Returns the stream identifier.
+    ///Returns an error if self is not a client, or no more streams can be initiated.
+    pub fn send_request(&mut self, r: &H2Request, end_stream: bool) -> Result<u32, &'static str> {
+        if self.role != Role::Client {
+            return Err("not a client");
+        }
+        if self.goaway_received.is_some() || self.goaway_sent.is_some() || self.error.is_some() {
+            return Err("connection is closing");
+        }
+        let max = self.remote.max_concurrent_streams().unwrap_or(u32::MAX);
+        if self.local_active_streams() >= max as usize {
+            return Err("too many concurrent streams");
+        }
+        let stream_id = self.next_stream_id;
+        if stream_id > MAX_STREAM_ID {
+            return Err("stream identifiers exhausted");
+        }
+        self.next_stream_id += 2;
+        let mut helper = self.headers_helper(stream_id);
+        self.encoder.encode_request_fields(r, &mut helper);
+        self.export_headers(helper, end_stream);
+        let state = if end_stream {
+            StreamState::HalfClosedLocal
+        } else {
+            StreamState::Open
+        };
+        self.insert_stream(stream_id, state, false);
+        Ok(stream_id)
+    }
+
+    ///Sends a PUSH_PROMISE frame on a stream initiated by the peer, which reserves a new stream for the promised request.
+    ///Returns the promised stream identifier, then the response is sent on it by [`Connection::send_response`].
+    ///
+    ///Returns an error if self is not a server, the peer disables server push, the stream is not open,
+    ///or the promised request is not valid, safe and cacheable, or includes content.
+    pub fn send_push_promise(
+        &mut self,
+        stream_id: u32,
+        r: &H2Request,
+    ) -> Result<u32, &'static str> {
+        if self.role != Role::Server {
+            return Err("not a server");
+        }
+        if !self.remote.enable_push() {
+            return Err("server push disabled");
+        }
+        if self.goaway_received.is_some() || self.goaway_sent.is_some() || self.error.is_some() {
+            return Err("connection is closing");
+        }
+        if self.is_local_id(stream_id) {
+            return Err("stream not initiated by the peer");
+        }
+        self.check_send(stream_id)?;
+        let promised_stream_id = self.next_stream_id;
+        if promised_stream_id > MAX_STREAM_ID {
+            return Err("stream identifiers exhausted");
+        }
+        let n = self.remote.max_frame_size() as usize;
+        message::encode_push_promise(
+            stream_id,
+            promised_stream_id,
+            r,
+            &mut self.encoder,
+            n,
+            &mut self.output,
+        )?;
+        self.next_stream_id += 2;
+        self.insert_stream(promised_stream_id, StreamState::ReservedLocal, false);
+        Ok(promised_stream_id)
+    }
+
+    ///Refuses a stream reserved by the peer's PUSH_PROMISE with REFUSED_STREAM, or cancels it with CANCEL.
+    pub fn refuse_push(
+        &mut self,
+        promised_stream_id: u32,
+        cancel: bool,
+    ) -> Result<(), &'static str> {
+        if self.stream_state(promised_stream_id) != StreamState::ReservedRemote {
+            return Err("stream not reserved by the peer");
+        }
+        let error_code = if cancel {
+            ErrorCode::Cancel
+        } else {
+            ErrorCode::RefusedStream
+        };
+        self.send_reset(promised_stream_id, error_code)
+    }
+
+    ///Sends a response, or an interim response.
+    ///Returns an error if the stream is not in a state to send a response.
+    pub fn send_response(
+        &mut self,
+        stream_id: u32,
+        r: &H2Response,
+        end_stream: bool,
+    ) -> Result<(), &'static str> {
+        match self.stream_state(stream_id) {
+            StreamState::Open | StreamState::HalfClosedRemote => {}
+            StreamState::ReservedLocal => {
+                let max = self.remote.max_concurrent_streams().unwrap_or(u32::MAX);
+                if self.local_active_streams() >= max as usize {
+                    return Err("too many concurrent streams");
+                }
+                self.set_state(stream_id, StreamState::HalfClosedRemote);
+            }
+            _ => return Err("invalid stream state"),
+        }
+        let mut helper = self.headers_helper(stream_id);
+        self.encoder.encode_response_fields(r, &mut helper);
+        self.export_headers(helper, end_stream);
+        if end_stream {
+            self.end_local(stream_id);
+        }
+        Ok(())
+    }
+
+    ///Sends a trailer section, which ends the stream.
+    pub fn send_trailers(&mut self, stream_id: u32, r: &Entity) -> Result<(), &'static str> {
+        self.check_send(stream_id)?;
+        let mut helper = self.headers_helper(stream_id);
+        self.encoder.encode_entity_fields(r, &mut helper);
+        self.export_headers(helper, true);
+        self.end_local(stream_id);
+        Ok(())
+    }
+
+    ///Sends message content in one or more DATA frames, limited by the peer's SETTINGS_MAX_FRAME_SIZE and flow control.
+    ///Returns the number of bytes sent, the stream is ended only if all bytes are sent.
+    pub fn send_data(
+        &mut self,
+        stream_id: u32,
+        data: &[u8],
+        end_stream: bool,
+    ) -> Result<usize, &'static str> {
+        self.check_send(stream_id)?;
+        let sent = data.len().min(self.flow.send_capacity(stream_id) as usize);
+        let end_stream = end_stream && sent == data.len();
+        if sent == 0 && !end_stream {
+            return Ok(0);
+        }
+        let _ = self.flow.consume_send(stream_id, sent as u32);
+        let n = self.remote.max_frame_size() as usize;
+        let mut chunks = data[..sent].chunks(n).peekable();
+        if chunks.peek().is_none() {
+            let mut o = Data::new(stream_id, 1);
+            o.set_end_stream(end_stream);
+            o.export(&mut self.output);
+        }
+        while let Some(v) = chunks.next() {
+            let mut o = Data::new(stream_id, v.len());
+            o.data_mut().put_exact(v);
+            o.set_end_stream(end_stream && chunks.peek().is_none());
+            o.export(&mut self.output);
+        }
+        if end_stream {
+            self.end_local(stream_id);
+        }
+        Ok(sent)
+    }
+
+    ///Returns the number of bytes of DATA frames can be sent on a stream now.
+    pub fn send_capacity(&self, stream_id: u32) -> u32 {
+        self.flow.send_capacity(stream_id)
+    }
+
+    ///Releases data processed by the application, WINDOW_UPDATE frames are queued by the window policy.
+    ///
+    ///Received DATA frames consume the windows until released, so the peer stops sending if data is never released.
+    pub fn release_data(&mut self, stream_id: u32, n: u32) {
+        self.release(stream_id, n);
+    }
+
+    ///Sets the policy to send WINDOW_UPDATE frames.
+    pub fn set_window_policy(&mut self, o: WindowPolicy) {
+        self.flow.set_policy(o);
+    }
+
+    ///Sets the window to receive of the connection, a WINDOW_UPDATE frame is queued if the window is larger.
+    pub fn set_connection_window(&mut self, n: u32) {
+        if let Some(o) = self.flow.set_connection_window(n) {
+            o.export(&mut self.output);
+        }
+    }
+
+    ///Sends a RST_STREAM frame, the stream is closed.
+    pub fn send_reset(
+        &mut self,
+        stream_id: u32,
+        error_code: ErrorCode,
+    ) -> Result<(), &'static str> {
+        match self.stream_state(stream_id) {
+            StreamState::Idle => Err("idle stream"),
+            _ => {
+                RstStream::new(stream_id, error_code).export(&mut self.output);
+                self.close(stream_id);
+                Ok(())
+            }
+        }
+    }
+
+    ///Sends a GOAWAY frame with the last stream identifier processed.
+    ///The identifier never increases, streams initiated by the peer after it are ignored.
+    pub fn send_goaway(&mut self, error_code: ErrorCode, additional_debug_data: &[u8]) {
+        self.send_goaway_with(self.last_peer_stream_id, error_code, additional_debug_data);
+    }
+
+    ///Sends a GOAWAY frame with a last stream identifier,
+    ///e.g. [`MAX_LAST_STREAM_ID`](super::shutdown::MAX_LAST_STREAM_ID) to start a graceful shutdown.
+    ///The identifier is limited to the one sent before, no stream is opened after it.
+    pub fn send_goaway_with(
+        &mut self,
+        last_stream_id: u32,
+        error_code: ErrorCode,
+        additional_debug_data: &[u8],
+    ) {
+        let last_stream_id = self
+            .goaway_sent
+            .map_or(last_stream_id, |n| n.min(last_stream_id));
+        let mut o = Goaway::new(additional_debug_data.len());
+        o.set_last_stream_id(last_stream_id);
+        o.set_error_code(error_code);
+        o.additional_debug_data_mut()
+            .put_exact(additional_debug_data);
+        o.export(&mut self.output);
+        self.goaway_sent = Some(last_stream_id);
+    }
+
+    fn check_send(&self, stream_id: u32) -> Result<(), &'static str> {
+        match self.stream_state(stream_id) {
+            StreamState::Open | StreamState::HalfClosedRemote => Ok(()),
+            _ => Err("invalid stream state"),
+        }
+    }
+
+    fn headers_helper(&self, stream_id: u32) -> HeadersHelper {
+        let n = self.remote.max_frame_size() as usize;
+        HeadersHelper::new(stream_id, n, n)
+    }
+
+    fn export_headers(&mut self, helper: HeadersHelper, end_stream: bool) {
+        message::export_headers(helper, end_stream, &mut self.output);
+    }
+
+    fn is_ignored(&self, stream_id: u32) -> bool {
+        !self.is_local_id(stream_id) && self.goaway_sent.is_some_and(|n| stream_id > n)
+    }
+
+    fn is_local_id(&self, stream_id: u32) -> bool {
+        match self.role {
+            Role::Client => stream_id % 2 == 1,
+            Role::Server => stream_id.is_multiple_of(2),
+        }
+    }
+
+    fn insert_stream(&mut self, stream_id: u32, state: StreamState, headers_received: bool) {
+        self.streams.insert(
+            stream_id,
+            Stream {
+                state,
+                headers_received,
+            },
+        );
+        self.flow.open_stream(stream_id);
+    }
+
+    fn set_state(&mut self, stream_id: u32, state: StreamState) {
+        if state == StreamState::Closed {
+            self.close(stream_id);
+        } else if let Some(o) = self.streams.get_mut(&stream_id) {
+            o.state = state;
+        }
+    }
+
+    fn close(&mut self, stream_id: u32) {
+        self.streams.remove(&stream_id);
+        self.flow.close_stream(stream_id);
+    }
+
+    fn release(&mut self, stream_id: u32, n: u32) {
+        for o in self.flow.release(stream_id, n) {
+            o.export(&mut self.output);
+        }
+    }
+
+    fn flow_error(&mut self, e: FlowControlError) -> Result<(), ConnectionError> {
+        if e.is_connection_error() {
+            return Err(connection_error(e));
+        }
+        self.stream_error(e.stream_id(), e.error_code());
+        Ok(())
+    }
+
+    fn end_local(&mut self, stream_id: u32) {
+        match self.stream_state(stream_id) {
+            StreamState::Open => self.set_state(stream_id, StreamState::HalfClosedLocal),
+            StreamState::HalfClosedRemote => self.close(stream_id),
+            _ => {}
+        }
+    }
+
+    fn end_remote(&mut self, stream_id: u32) {
+        match self.stream_state(stream_id) {
+            StreamState::Open => self.set_state(stream_id, StreamState::HalfClosedRemote),
+            StreamState::HalfClosedLocal => self.close(stream_id),
+            _ => {}
+        }
+    }
+
+    fn stream_error(&mut self, stream_id: u32, error_code: ErrorCode) {
+        RstStream::new(stream_id, error_code).export(&mut self.output);
+        self.close(stream_id);
+        self.events.push_back(Event::Reset {
+            stream_id,
+            error_code,
+            remote: false,
+        });
+    }
+
+    fn fail(&mut self, e: ConnectionError) -> ConnectionError {
+        if self.goaway_sent.is_none() {
+            self.send_goaway(e.error_code(), e.reason().as_bytes());
+        }
+        self.error = Some(e.clone());
+        e
+    }
+}
+
+#[inline(always)]
+fn connection_error(e: FlowControlError) -> ConnectionError {
+    ConnectionError::new(e.error_code(), e.reason())
+}
+
+#[inline(always)]
+fn is_active(o: StreamState) -> bool {
+    matches!(
+        o,
+        StreamState::Open | StreamState::HalfClosedLocal | StreamState::HalfClosedRemote
+    )
+}
+
+#[inline(always)]
+fn check_stream_id(stream_id: u32) -> Result<(), ConnectionError> {
+    if stream_id == 0 {
+        Err(ConnectionError::new(
+            ErrorCode::ProtocolError,
+            "stream identifier is 0",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AltSvcValue, Alternative};
+
+    fn pair() -> (Connection, Connection) {
+        let mut client = Connection::new(Role::Client);
+        let mut server = Connection::new(Role::Server);
+        server.receive(&client.take_outbound()).unwrap();
+        client.receive(&server.take_outbound()).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        while client.poll_event().is_some() {}
+        while server.poll_event().is_some() {}
+        (client, server)
+    }
+
+    fn request() -> H2Request {
+        let mut r = H2Request::new("POST");
+        r.set_scheme(Some("https"));
+        r.set_authority(Some("example.org"));
+        r.set_path(Some("/upload"));
+        r
+    }
+
+    #[test]
+    fn exchange() {
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), false).unwrap();
+        let b = client.send_request(&request(), false).unwrap();
+        assert_eq!((a, b), (1, 3));
+        client.send_data(a, &[1; 40000], true).unwrap();
+        assert_eq!(client.stream_state(a), StreamState::HalfClosedLocal);
+
+        server.receive(&client.take_outbound()).unwrap();
+        let mut n = 0;
+        while let Some(e) = server.poll_event() {
+            match e {
+                Event::Headers { stream_id, .. } => assert!(stream_id == a || stream_id == b),
+                Event::Data { data, .. } => n += data.len(),
+                _ => {}
+            }
+        }
+        assert_eq!(n, 40000);
+        assert_eq!(server.stream_state(a), StreamState::HalfClosedRemote);
+        assert_eq!(server.stream_state(b), StreamState::Open);
+
+        server
+            .send_response(a, &H2Response::new("200"), true)
+            .unwrap();
+        assert_eq!(server.stream_state(a), StreamState::Closed);
+        let mut trailers = Entity::default();
+        trailers.add_field("grpc-status", "0");
+        server
+            .send_response(b, &H2Response::new("200"), false)
+            .unwrap();
+        server.send_trailers(b, &trailers).unwrap();
+        assert_eq!(server.stream_state(b), StreamState::HalfClosedLocal);
+
+        client.receive(&server.take_outbound()).unwrap();
+        let mut trailers = 0;
+        while let Some(e) = client.poll_event() {
+            if let Event::Trailers { stream_id, .. } = e {
+                assert_eq!(stream_id, b);
+                trailers += 1;
+            }
+        }
+        assert_eq!(trailers, 1);
+        assert_eq!(client.stream_state(a), StreamState::Closed);
+        assert_eq!(client.stream_state(b), StreamState::HalfClosedRemote);
+    }
+
+    #[test]
+    fn flow_control() {
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), false).unwrap();
+        assert_eq!(client.send_data(a, &[0; 70000], true).unwrap(), 65535);
+        assert_eq!(client.stream_state(a), StreamState::Open);
+        assert_eq!(client.send_data(a, &[0; 10], true).unwrap(), 0);
+
+        server.receive(&client.take_outbound()).unwrap();
+        server.release_data(a, 30000);
+        assert!(!server.has_outbound());
+        server.release_data(a, 35535);
+        client.receive(&server.take_outbound()).unwrap();
+        assert_eq!(client.send_capacity(a), 65535);
+        assert_eq!(client.send_data(a, &[0; 4465], true).unwrap(), 4465);
+        assert_eq!(client.stream_state(a), StreamState::HalfClosedLocal);
+
+        let mut v = Vec::new();
+        WindowUpdate::new(0, MAX_WINDOW_SIZE).export(&mut v);
+        let e = client.receive(&v).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::FlowControlError);
+    }
+
+    #[test]
+    fn settings() {
+        let (mut client, mut server) = pair();
+        let mut o = ConnectionSettings::default();
+        o.set_max_concurrent_streams(Some(1));
+        o.set_initial_window_size(100);
+        server.send_settings(o).unwrap();
+        assert_eq!(server.local().pending_len(), 1);
+        client.receive(&server.take_outbound()).unwrap();
+        assert!(matches!(client.poll_event(), Some(Event::Settings(r)) if r == o));
+        assert_eq!(client.remote().max_concurrent_streams(), Some(1));
+        server.receive(&client.take_outbound()).unwrap();
+        assert!(matches!(server.poll_event(), Some(Event::SettingsAck)));
+        assert_eq!(server.local().acked(), o);
+
+        let a = client.send_request(&request(), false).unwrap();
+        assert_eq!(client.send_data(a, &[0; 200], false).unwrap(), 100);
+        assert!(client.send_request(&request(), true).is_err());
+        server.receive(&client.take_outbound()).unwrap();
+
+        let mut v = Vec::new();
+        let mut f = Settings::new(1);
+        f.push(SETTINGS_MAX_FRAME_SIZE, 100);
+        f.export(&mut v);
+        let e = client.receive(&v).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+
+        let (mut client, mut server) = pair();
+        let mut v = Vec::new();
+        let mut f = Settings::new(1);
+        f.push(SETTINGS_ENABLE_PUSH, 0);
+        f.export(&mut v);
+        client.receive(&v).unwrap();
+        server.receive(&v).unwrap();
+        assert!(!server.remote().enable_push());
+        let mut v = Vec::new();
+        let mut f = Settings::new(1);
+        f.push(SETTINGS_ENABLE_PUSH, 1);
+        f.export(&mut v);
+        server.receive(&v).unwrap();
+        let e = client.receive(&v).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+    }
+
+    #[test]
+    fn header_list_size() {
+        let (mut client, mut server) = pair();
+        let mut o = ConnectionSettings::default();
+        o.set_max_header_list_size(Some(200));
+        server.send_settings(o).unwrap();
+        client.receive(&server.take_outbound()).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        while server.poll_event().is_some() {}
+
+        let mut r = request();
+        r.add_field("x-large", "a".repeat(200));
+        let a = client.send_request(&r, true).unwrap();
+        let b = client.send_request(&request(), true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        assert!(matches!(
+            server.poll_event(),
+            Some(Event::Reset { stream_id, remote: false, .. }) if stream_id == a
+        ));
+        assert!(matches!(
+            server.poll_event(),
+            Some(Event::Headers { stream_id, .. }) if stream_id == b
+        ));
+        assert_eq!(server.last_peer_stream_id(), b);
+    }
+
+    #[test]
+    fn push() {
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        while server.poll_event().is_some() {}
+        let mut r = H2Request::new("GET");
+        r.set_scheme(Some("https"));
+        r.set_authority(Some("example.org"));
+        r.set_path(Some("/style.css"));
+        assert!(server.send_push_promise(a, &request()).is_err());
+        let b = server.send_push_promise(a, &r).unwrap();
+        let c = server.send_push_promise(a, &r).unwrap();
+        assert_eq!((b, c), (2, 4));
+        assert_eq!(server.stream_state(b), StreamState::ReservedLocal);
+        server
+            .send_response(a, &H2Response::new("200"), true)
+            .unwrap();
+        server
+            .send_response(b, &H2Response::new("200"), true)
+            .unwrap();
+
+        client.receive(&server.take_outbound()).unwrap();
+        assert!(matches!(
+            client.poll_event(),
+            Some(Event::PushPromise { promised_stream_id, .. }) if promised_stream_id == b
+        ));
+        assert!(matches!(
+            client.poll_event(),
+            Some(Event::PushPromise { .. })
+        ));
+        assert_eq!(client.stream_state(c), StreamState::ReservedRemote);
+        client.refuse_push(c, false).unwrap();
+        assert!(client.refuse_push(c, false).is_err());
+        while client.poll_event().is_some() {}
+        assert_eq!(client.stream_state(b), StreamState::Closed);
+
+        server.receive(&client.take_outbound()).unwrap();
+        assert!(matches!(
+            server.poll_event(),
+            Some(Event::Reset { stream_id, error_code: ErrorCode::RefusedStream, remote: true }) if stream_id == c
+        ));
+        assert!(
+            server
+                .send_response(c, &H2Response::new("200"), true)
+                .is_err()
+        );
+
+        let mut o = ConnectionSettings::default();
+        o.set_enable_push(false);
+        client.send_settings(o).unwrap();
+        let d = client.send_request(&request(), true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        assert!(server.send_push_promise(d, &r).is_err());
+        assert!(client.send_push_promise(d, &r).is_err());
+    }
+
+    #[test]
+    fn alt_svc() {
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        let value = AltSvcValue::Alternatives(vec![Alternative::new("h3", "", 443)]);
+        server.send_altsvc(AltSvc::new(a, b"", &value)).unwrap();
+        server
+            .send_altsvc(AltSvc::new(0, b"", &AltSvcValue::Clear))
+            .unwrap();
+        let mut o = Origin::new();
+        o.push(b"https://example.org");
+        server.send_origin(o).unwrap();
+        assert!(client.send_origin(Origin::new()).is_err());
+
+        client.receive(&server.take_outbound()).unwrap();
+        let mut v = Vec::new();
+        while let Some(e) = client.poll_event() {
+            v.push(e);
+        }
+        assert_eq!(v.len(), 2);
+        match &v[0] {
+            Event::AltSvc {
+                stream_id,
+                origin,
+                field_value,
+            } => {
+                assert_eq!(*stream_id, a);
+                assert!(origin.is_empty());
+                assert_eq!(AltSvcValue::parse(field_value), Ok(value));
+            }
+            e => panic!("{e:?}"),
+        }
+        assert!(matches!(&v[1], Event::Origin(o) if o[0] == b"https://example.org"));
+
+        let s = [0, 0, 3, 0x0c, 0, 0, 0, 0, 0, 0, 2, b'a'];
+        client.receive(&s).unwrap();
+        server.receive(&s).unwrap();
+        let s = [0, 0, 1, 0x0a, 0, 0, 0, 0, 0, 0];
+        client.receive(&s).unwrap();
+        server.receive(&s).unwrap();
+        assert!(client.poll_event().is_none());
+    }
+
+    #[test]
+    fn priority_update() {
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), false).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        while server.poll_event().is_some() {}
+        let mut v = Vec::new();
+        PriorityUpdate::new(a, &PriorityParams::new(1, true)).export(&mut v);
+        server.receive(&v).unwrap();
+        assert!(matches!(
+            server.poll_event(),
+            Some(Event::PriorityUpdate { prioritized_stream_id, priority })
+                if prioritized_stream_id == a && priority == PriorityParams::new(1, true)
+        ));
+
+        let mut v = Vec::new();
+        PriorityUpdate::new(0, &PriorityParams::default()).export(&mut v);
+        let e = server.receive(&v).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+    }
+
+    #[test]
+    fn errors() {
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        server
+            .send_response(a, &H2Response::new("200"), true)
+            .unwrap();
+        client.receive(&server.take_outbound()).unwrap();
+        while client.poll_event().is_some() {}
+        while server.poll_event().is_some() {}
+
+        let mut v = Vec::new();
+        Data::new(a, 1).export(&mut v);
+        server.receive(&v).unwrap();
+        assert!(matches!(
+            server.poll_event(),
+            Some(Event::Reset {
+                error_code: ErrorCode::StreamClosed,
+                remote: false,
+                ..
+            })
+        ));
+
+        let mut v = Vec::new();
+        Data::new(9, 1).export(&mut v);
+        let e = server.receive(&v).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+        assert!(server.receive(&[]).is_err());
+        client.receive(&server.take_outbound()).unwrap();
+        assert!(matches!(client.poll_event(), Some(Event::Reset { .. })));
+        assert!(matches!(
+            client.poll_event(),
+            Some(Event::Goaway {
+                error_code: ErrorCode::ProtocolError,
+                ..
+            })
+        ));
+        assert!(client.send_request(&request(), true).is_err());
+
+        let mut server = Connection::new(Role::Server);
+        let e = server.receive(b"GET / HTTP/1.1\r\n\r\n").unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+    }
+
+    #[test]
+    fn server_initiated_streams() {
+        let (mut client, _) = pair();
+        let mut v = Vec::new();
+        let mut o = Headers::new(2, 1);
+        o.set_end_headers(true);
+        o.field_block_fragment_mut().put_u8(0x88);
+        o.export(&mut v);
+        let e = client.receive(&v).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+        assert_eq!(client.stream_state(2), StreamState::Idle);
+        assert!(client.poll_event().is_none());
+
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        while server.poll_event().is_some() {}
+        let mut r = H2Request::new("GET");
+        r.set_scheme(Some("https"));
+        r.set_authority(Some("example.org"));
+        r.set_path(Some("/style.css"));
+        let b = server.send_push_promise(a, &r).unwrap();
+        client.receive(&server.take_outbound()).unwrap();
+        assert_eq!(client.stream_state(b), StreamState::ReservedRemote);
+        let mut v = Vec::new();
+        Data::new(b, 1).export(&mut v);
+        let e = client.receive(&v).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+    }
+
+    #[test]
+    fn goaway() {
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        while server.poll_event().is_some() {}
+        server.send_goaway(ErrorCode::NoError, &[]);
+        let b = client.send_request(&request(), false).unwrap();
+        client.send_data(b, &[0; 100], true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        assert!(server.poll_event().is_none());
+        assert_eq!(server.last_peer_stream_id(), a);
+        assert_eq!(server.stream_state(b), StreamState::Idle);
+        server.send_goaway(ErrorCode::NoError, &[]);
+        client.receive(&server.take_outbound()).unwrap();
+        let mut v = Vec::new();
+        while let Some(e) = client.poll_event() {
+            if let Event::Goaway { last_stream_id, .. } = e {
+                v.push(last_stream_id);
+            }
+        }
+        assert_eq!(v, [a, a]);
+
+        let (mut client, mut server) = pair();
+        let a = client.send_request(&request(), false).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        while server.poll_event().is_some() {}
+        let mut r = H2Request::new("GET");
+        r.set_scheme(Some("https"));
+        r.set_authority(Some("example.org"));
+        r.set_path(Some("/style.css"));
+        let b = server.send_push_promise(a, &r).unwrap();
+        client.receive(&server.take_outbound()).unwrap();
+        while client.poll_event().is_some() {}
+        client.send_goaway(ErrorCode::NoError, &[]);
+        let c = server.send_push_promise(a, &r).unwrap();
+        server
+            .send_response(c, &H2Response::new("200"), true)
+            .unwrap();
+        client.receive(&server.take_outbound()).unwrap();
+        assert!(client.poll_event().is_none());
+        assert_eq!(client.last_peer_stream_id(), b);
+        assert_eq!(client.stream_state(c), StreamState::Idle);
+
+        let (mut client, mut server) = pair();
+        server.send_goaway_with(MAX_STREAM_ID, ErrorCode::NoError, &[]);
+        let a = client.send_request(&request(), true).unwrap();
+        server.receive(&client.take_outbound()).unwrap();
+        assert!(matches!(server.poll_event(), Some(Event::Headers { .. })));
+        let mut r = H2Request::new("GET");
+        r.set_scheme(Some("https"));
+        r.set_authority(Some("example.org"));
+        r.set_path(Some("/style.css"));
+        assert_eq!(
+            server.send_push_promise(a, &r),
+            Err("connection is closing")
+        );
+        server.send_goaway(ErrorCode::NoError, &[]);
+        server.send_goaway_with(MAX_STREAM_ID, ErrorCode::NoError, &[]);
+        client.receive(&server.take_outbound()).unwrap();
+        let mut v = Vec::new();
+        while let Some(e) = client.poll_event() {
+            if let Event::Goaway { last_stream_id, .. } = e {
+                v.push(last_stream_id);
+            }
+        }
+        assert_eq!(v, [MAX_STREAM_ID, a, a]);
+        assert!(client.send_request(&request(), true).is_err());
+    }
+}
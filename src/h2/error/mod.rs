@@ -0,0 +1,182 @@
+/*!
+A module for HTTP/2 error codes.
+
+Error codes are used in RST_STREAM and GOAWAY frames to convey the reasons for the stream or connection error.
+
+# Examples
+```
+use httpenergy::h2::error::*;
+
+assert_eq!(ErrorCode::from(0x1), ErrorCode::ProtocolError);
+assert_eq!(u32::from(ErrorCode::Http11Required), 0xd);
+assert_eq!(ErrorCode::FlowControlError.to_string(), "FLOW_CONTROL_ERROR");
+assert_eq!(ErrorCode::from(0xff).to_string(), "UNKNOWN(0xff)");
+```
+*/
+
+use crate::prty::*;
+
+///Represents an error code.
+///
+///Unknown or unsupported error codes must not trigger any special behavior,
+///they may be treated as being equivalent to [`ErrorCode::InternalError`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ErrorCode {
+    ///Not a result of an error.
+    NoError,
+    ///Detects an unspecific protocol error.
+    ProtocolError,
+    ///Encounters an unexpected internal error.
+    InternalError,
+    ///Detects that its peer violated the flow-control protocol.
+    FlowControlError,
+    ///Sends a SETTINGS frame but did not receive a response in a timely manner.
+    SettingsTimeout,
+    ///Receives a frame after a stream is half-closed.
+    StreamClosed,
+    ///Receives a frame with an invalid size.
+    FrameSizeError,
+    ///Refuses the stream prior to performing any application processing.
+    RefusedStream,
+    ///Indicates that the stream is no longer needed.
+    Cancel,
+    ///Is unable to maintain the field section compression context for the connection.
+    CompressionError,
+    ///The connection established in response to a CONNECT request was reset or abnormally closed.
+    ConnectError,
+    ///Detects that its peer is exhibiting a behavior that might be generating excessive load.
+    EnhanceYourCalm,
+    ///The underlying transport has properties that do not meet minimum security requirements.
+    InadequateSecurity,
+    ///Requires that HTTP/1.1 be used instead of HTTP/2.
+    Http11Required,
+    ///An unknown error code.
+    Unknown(u32),
+}
+
+impl ErrorCode {
+    ///Returns the name, or None if unknown.
+    pub fn name(&self) -> Option<&'static str> {
+        let r = match self {
+            Self::NoError => "NO_ERROR",
+            Self::ProtocolError => "PROTOCOL_ERROR",
+            Self::InternalError => "INTERNAL_ERROR",
+            Self::FlowControlError => "FLOW_CONTROL_ERROR",
+            Self::SettingsTimeout => "SETTINGS_TIMEOUT",
+            Self::StreamClosed => "STREAM_CLOSED",
+            Self::FrameSizeError => "FRAME_SIZE_ERROR",
+            Self::RefusedStream => "REFUSED_STREAM",
+            Self::Cancel => "CANCEL",
+            Self::CompressionError => "COMPRESSION_ERROR",
+            Self::ConnectError => "CONNECT_ERROR",
+            Self::EnhanceYourCalm => "ENHANCE_YOUR_CALM",
+            Self::InadequateSecurity => "INADEQUATE_SECURITY",
+            Self::Http11Required => "HTTP_1_1_REQUIRED",
+            Self::Unknown(_) => return None,
+        };
+        Some(r)
+    }
+}
+
+impl From<u32> for ErrorCode {
+    fn from(o: u32) -> Self {
+        match o {
+            0x0 => Self::NoError,
+            0x1 => Self::ProtocolError,
+            0x2 => Self::InternalError,
+            0x3 => Self::FlowControlError,
+            0x4 => Self::SettingsTimeout,
+            0x5 => Self::StreamClosed,
+            0x6 => Self::FrameSizeError,
+            0x7 => Self::RefusedStream,
+            0x8 => Self::Cancel,
+            0x9 => Self::CompressionError,
+            0xa => Self::ConnectError,
+            0xb => Self::EnhanceYourCalm,
+            0xc => Self::InadequateSecurity,
+            0xd => Self::Http11Required,
+            _ => Self::Unknown(o),
+        }
+    }
+}
+
+impl From<ErrorCode> for u32 {
+    fn from(o: ErrorCode) -> Self {
+        match o {
+            ErrorCode::NoError => 0x0,
+            ErrorCode::ProtocolError => 0x1,
+            ErrorCode::InternalError => 0x2,
+            ErrorCode::FlowControlError => 0x3,
+            ErrorCode::SettingsTimeout => 0x4,
+            ErrorCode::StreamClosed => 0x5,
+            ErrorCode::FrameSizeError => 0x6,
+            ErrorCode::RefusedStream => 0x7,
+            ErrorCode::Cancel => 0x8,
+            ErrorCode::CompressionError => 0x9,
+            ErrorCode::ConnectError => 0xa,
+            ErrorCode::EnhanceYourCalm => 0xb,
+            ErrorCode::InadequateSecurity => 0xc,
+            ErrorCode::Http11Required => 0xd,
+            ErrorCode::Unknown(n) => n,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(s) => f.write_str(s),
+            None => write!(f, "UNKNOWN({:#x})", u32::from(*self)),
+        }
+    }
+}
+
+///Represents a connection error, the connection is closed by a GOAWAY frame with the error code.
+#[derive(Clone, CopyGetters, Debug)]
+#[getset(get_copy = "pub")]
+pub struct ConnectionError {
+    error_code: ErrorCode,
+    reason: &'static str,
+}
+
+impl ConnectionError {
+    ///Creates.
+    pub fn new(error_code: ErrorCode, reason: &'static str) -> Self {
+        Self { error_code, reason }
+    }
+}
+
+impl std::fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "connection error {}: {}", self.error_code, self.reason)
+    }
+}
+
+impl std::error::Error for ConnectionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_code() {
+        for n in 0..=0xd {
+            let o = ErrorCode::from(n);
+            assert!(!matches!(o, ErrorCode::Unknown(_)));
+            assert_eq!(u32::from(o), n);
+            assert_eq!(ErrorCode::from(u32::from(o)), o);
+            assert!(o.name().is_some_and(|s| o.to_string() == s));
+        }
+        let o = ErrorCode::from(0xe);
+        assert_eq!(o, ErrorCode::Unknown(0xe));
+        assert_eq!(u32::from(o), 0xe);
+        assert_eq!(o.name(), None);
+        assert_eq!(o.to_string(), "UNKNOWN(0xe)");
+        assert_eq!(ErrorCode::NoError.to_string(), "NO_ERROR");
+        assert_eq!(ErrorCode::Http11Required.to_string(), "HTTP_1_1_REQUIRED");
+        assert_eq!(
+            ConnectionError::new(ErrorCode::Cancel, "reason").to_string(),
+            "connection error CANCEL: reason"
+        );
+    }
+}
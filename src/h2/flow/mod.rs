@@ -0,0 +1,471 @@
+/*!
+A module for HTTP/2 flow control.
+
+Both the connection and each stream have a window to send and a window to receive.
+[`FlowControl`] tracks these windows, tells how many bytes of DATA frames can be sent now,
+and builds WINDOW_UPDATE frames by [`WindowPolicy`] after received data is released.
+
+# Examples
+```
+use httpenergy::h2::flow::*;
+
+let mut f = FlowControl::default();
+f.open_stream(1);
+assert_eq!(f.send_capacity(1), 65535);
+f.consume_send(1, 65535).unwrap();
+assert_eq!(f.send_capacity(1), 0);
+f.on_window_update(0, 100).unwrap();
+f.on_window_update(1, 10).unwrap();
+assert_eq!(f.send_capacity(1), 10);
+
+f.on_data(1, 40000).unwrap();
+let v = f.release(1, 40000);
+assert_eq!(v.len(), 2);
+```
+*/
+
+use super::error::ErrorCode;
+use super::frame::WindowUpdate;
+use crate::prty::*;
+use std::collections::HashMap;
+
+///The initial window size of the connection and streams.
+pub const DEFAULT_WINDOW_SIZE: u32 = 65535;
+///The maximum window size, 2^31-1.
+pub const MAX_WINDOW_SIZE: u32 = 0x7fff_ffff;
+
+///Represents a flow-control error. The stream identifier is 0 if it is a connection error.
+#[derive(Clone, Copy, CopyGetters, Debug, Eq, PartialEq)]
+#[getset(get_copy = "pub")]
+pub struct FlowControlError {
+    stream_id: u32,
+    error_code: ErrorCode,
+    reason: &'static str,
+}
+
+impl FlowControlError {
+    ///Creates.
+    pub fn new(stream_id: u32, error_code: ErrorCode, reason: &'static str) -> Self {
+        Self {
+            stream_id,
+            error_code,
+            reason,
+        }
+    }
+
+    ///Returns true if it is a connection error.
+    pub fn is_connection_error(&self) -> bool {
+        self.stream_id == 0
+    }
+}
+
+impl std::fmt::Display for FlowControlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "flow-control error {} on stream {}: {}",
+            self.error_code, self.stream_id, self.reason
+        )
+    }
+}
+
+impl std::error::Error for FlowControlError {}
+
+///Represents a window to send. It can be negative after SETTINGS_INITIAL_WINDOW_SIZE decreases.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SendWindow(i64);
+
+impl Default for SendWindow {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW_SIZE)
+    }
+}
+
+impl SendWindow {
+    ///Creates.
+    pub fn new(size: u32) -> Self {
+        Self(size as i64)
+    }
+
+    ///Returns the window size, which can be negative.
+    pub fn size(&self) -> i64 {
+        self.0
+    }
+
+    ///Returns the number of bytes can be sent now.
+    pub fn available(&self) -> u32 {
+        self.0.max(0) as u32
+    }
+
+    ///Consumes the window. Returns an error if the window is exceeded.
+    pub fn consume(&mut self, n: u32) -> Result<(), &'static str> {
+        if n as i64 > self.0 {
+            return Err("window exceeded");
+        }
+        self.0 -= n as i64;
+        Ok(())
+    }
+
+    ///Increases the window by a WINDOW_UPDATE frame. Returns an error if the window exceeds 2^31-1.
+    pub fn increase(&mut self, n: u32) -> Result<(), &'static str> {
+        self.adjust(n as i64)
+    }
+
+    ///Adjusts the window by the change of SETTINGS_INITIAL_WINDOW_SIZE. Returns an error if the window exceeds 2^31-1.
+    pub fn adjust(&mut self, delta: i64) -> Result<(), &'static str> {
+        let n = self.0 + delta;
+        if n > MAX_WINDOW_SIZE as i64 {
+            return Err("window overflow");
+        }
+        self.0 = n;
+        Ok(())
+    }
+}
+
+///The policy to send WINDOW_UPDATE frames.
+///
+///A WINDOW_UPDATE frame is sent when the released bytes reach the threshold percent of the target window.
+///If auto-tuning is enabled, the target window is doubled up to the max window,
+///when the peer has used three quarters of the window before the update.
+#[derive(Clone, Copy, CopyGetters, Debug, Setters)]
+#[getset(get_copy = "pub", set = "pub")]
+pub struct WindowPolicy {
+    threshold_percent: u8,
+    auto_tune: bool,
+    max_window: u32,
+}
+
+impl Default for WindowPolicy {
+    fn default() -> Self {
+        Self {
+            threshold_percent: 50,
+            auto_tune: false,
+            max_window: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl WindowPolicy {
+    ///Creates.
+    pub fn new(threshold_percent: u8, auto_tune: bool, max_window: u32) -> Self {
+        Self {
+            threshold_percent: threshold_percent.min(100),
+            auto_tune,
+            max_window: max_window.min(MAX_WINDOW_SIZE),
+        }
+    }
+
+    #[inline(always)]
+    fn threshold(&self, target: u32) -> u32 {
+        (target as u64 * self.threshold_percent as u64 / 100) as u32
+    }
+}
+
+///Represents a window to receive.
+#[derive(Clone, Copy, CopyGetters, Debug, Eq, PartialEq)]
+#[getset(get_copy = "pub")]
+pub struct RecvWindow {
+    size: i64,
+    target: u32,
+    released: u32,
+}
+
+impl Default for RecvWindow {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW_SIZE)
+    }
+}
+
+impl RecvWindow {
+    ///Creates.
+    pub fn new(size: u32) -> Self {
+        Self {
+            size: size as i64,
+            target: size,
+            released: 0,
+        }
+    }
+
+    ///Consumes the window by received data. Returns an error if the window is exceeded.
+    pub fn receive(&mut self, n: u32) -> Result<(), &'static str> {
+        if n as i64 > self.size {
+            return Err("window exceeded");
+        }
+        self.size -= n as i64;
+        Ok(())
+    }
+
+    ///Releases processed data. Returns the window size increment to send, or None if not needed yet.
+    pub fn release(&mut self, n: u32, policy: &WindowPolicy) -> Option<u32> {
+        let unreleased = (self.target as i64 - self.size - self.released as i64).max(0) as u32;
+        self.released += n.min(unreleased);
+        if self.released == 0 || self.released < policy.threshold(self.target) {
+            return None;
+        }
+        let mut increment = self.released;
+        if policy.auto_tune && self.size <= (self.target / 4) as i64 {
+            let target = self.target.saturating_mul(2).min(policy.max_window);
+            if target > self.target {
+                increment += target - self.target;
+                self.target = target;
+            }
+        }
+        self.size += increment as i64;
+        self.released = 0;
+        Some(increment)
+    }
+
+    ///Sets the target window. Returns the window size increment to send, or None if the target is not larger.
+    pub fn set_target(&mut self, n: u32) -> Option<u32> {
+        let n = n.min(MAX_WINDOW_SIZE);
+        if n <= self.target {
+            return None;
+        }
+        let increment = n - self.target;
+        self.target = n;
+        self.size += increment as i64;
+        Some(increment)
+    }
+
+    ///Adjusts the window by the change of SETTINGS_INITIAL_WINDOW_SIZE.
+    pub fn adjust(&mut self, delta: i64) {
+        self.size += delta;
+        self.target = (self.target as i64 + delta).clamp(0, MAX_WINDOW_SIZE as i64) as u32;
+    }
+}
+
+///Tracks the windows of the connection and streams.
+#[derive(Debug, CopyGetters)]
+pub struct FlowControl {
+    #[getset(get_copy = "pub")]
+    policy: WindowPolicy,
+    #[getset(get_copy = "pub")]
+    local_initial_window_size: u32,
+    #[getset(get_copy = "pub")]
+    remote_initial_window_size: u32,
+    #[getset(get_copy = "pub")]
+    send: SendWindow,
+    #[getset(get_copy = "pub")]
+    recv: RecvWindow,
+    streams: HashMap<u32, (SendWindow, RecvWindow)>,
+}
+
+impl Default for FlowControl {
+    fn default() -> Self {
+        Self::new(WindowPolicy::default())
+    }
+}
+
+impl FlowControl {
+    ///Creates.
+    pub fn new(policy: WindowPolicy) -> Self {
+        Self {
+            policy,
+            local_initial_window_size: DEFAULT_WINDOW_SIZE,
+            remote_initial_window_size: DEFAULT_WINDOW_SIZE,
+            send: SendWindow::default(),
+            recv: RecvWindow::default(),
+            streams: HashMap::new(),
+        }
+    }
+
+    ///Sets the policy.
+    pub fn set_policy(&mut self, o: WindowPolicy) {
+        self.policy = o;
+    }
+
+    ///Creates the windows of a stream with the initial window sizes.
+    pub fn open_stream(&mut self, stream_id: u32) {
+        self.streams.entry(stream_id).or_insert((
+            SendWindow::new(self.remote_initial_window_size),
+            RecvWindow::new(self.local_initial_window_size),
+        ));
+    }
+
+    ///Removes the windows of a stream.
+    pub fn close_stream(&mut self, stream_id: u32) {
+        self.streams.remove(&stream_id);
+    }
+
+    ///Returns the windows of a stream, or None if not open.
+    pub fn stream(&self, stream_id: u32) -> Option<&(SendWindow, RecvWindow)> {
+        self.streams.get(&stream_id)
+    }
+
+    ///Returns the number of bytes of DATA frames can be sent on a stream now.
+    pub fn send_capacity(&self, stream_id: u32) -> u32 {
+        match self.streams.get(&stream_id) {
+            Some((o, _)) => o.available().min(self.send.available()),
+            None => 0,
+        }
+    }
+
+    ///Consumes the windows to send a DATA frame, n is the frame payload length.
+    pub fn consume_send(&mut self, stream_id: u32, n: u32) -> Result<(), FlowControlError> {
+        if n > self.send_capacity(stream_id) {
+            return Err(FlowControlError::new(
+                stream_id,
+                ErrorCode::FlowControlError,
+                "window exceeded",
+            ));
+        }
+        if let Some((o, _)) = self.streams.get_mut(&stream_id) {
+            let _ = o.consume(n);
+        }
+        let _ = self.send.consume(n);
+        Ok(())
+    }
+
+    ///Handles a received WINDOW_UPDATE frame. Increments to streams not open are ignored.
+    pub fn on_window_update(
+        &mut self,
+        stream_id: u32,
+        increment: u32,
+    ) -> Result<(), FlowControlError> {
+        if increment == 0 {
+            return Err(FlowControlError::new(
+                stream_id,
+                ErrorCode::ProtocolError,
+                "window size increment is 0",
+            ));
+        }
+        let r = if stream_id == 0 {
+            self.send.increase(increment)
+        } else if let Some((o, _)) = self.streams.get_mut(&stream_id) {
+            o.increase(increment)
+        } else {
+            Ok(())
+        };
+        r.map_err(|e| FlowControlError::new(stream_id, ErrorCode::FlowControlError, e))
+    }
+
+    ///Handles the peer's SETTINGS_INITIAL_WINDOW_SIZE, adjusts the windows to send of all streams.
+    ///Returns a connection error if the value or a window exceeds 2^31-1.
+    pub fn set_remote_initial_window_size(&mut self, n: u32) -> Result<(), FlowControlError> {
+        if n > MAX_WINDOW_SIZE {
+            return Err(FlowControlError::new(
+                0,
+                ErrorCode::FlowControlError,
+                "invalid SETTINGS_INITIAL_WINDOW_SIZE",
+            ));
+        }
+        let delta = n as i64 - self.remote_initial_window_size as i64;
+        for (o, _) in self.streams.values_mut() {
+            o.adjust(delta)
+                .map_err(|e| FlowControlError::new(0, ErrorCode::FlowControlError, e))?;
+        }
+        self.remote_initial_window_size = n;
+        Ok(())
+    }
+
+    ///Applies self's SETTINGS_INITIAL_WINDOW_SIZE acknowledged by the peer, adjusts the windows to receive of all streams.
+    pub fn set_local_initial_window_size(&mut self, n: u32) -> Result<(), FlowControlError> {
+        if n > MAX_WINDOW_SIZE {
+            return Err(FlowControlError::new(
+                0,
+                ErrorCode::FlowControlError,
+                "invalid SETTINGS_INITIAL_WINDOW_SIZE",
+            ));
+        }
+        let delta = n as i64 - self.local_initial_window_size as i64;
+        for (_, o) in self.streams.values_mut() {
+            o.adjust(delta);
+        }
+        self.local_initial_window_size = n;
+        Ok(())
+    }
+
+    ///Sets the target window to receive of the connection.
+    ///Returns a WINDOW_UPDATE frame to send, or None if the target is not larger.
+    pub fn set_connection_window(&mut self, n: u32) -> Option<WindowUpdate> {
+        self.recv.set_target(n).map(|o| WindowUpdate::new(0, o))
+    }
+
+    ///Handles a received DATA frame, n is the frame payload length including padding.
+    ///Only the connection window is consumed if the stream is not open.
+    pub fn on_data(&mut self, stream_id: u32, n: u32) -> Result<(), FlowControlError> {
+        self.recv
+            .receive(n)
+            .map_err(|e| FlowControlError::new(0, ErrorCode::FlowControlError, e))?;
+        if let Some((_, o)) = self.streams.get_mut(&stream_id) {
+            o.receive(n)
+                .map_err(|e| FlowControlError::new(stream_id, ErrorCode::FlowControlError, e))?;
+        }
+        Ok(())
+    }
+
+    ///Releases processed data. Returns WINDOW_UPDATE frames to send by the policy.
+    pub fn release(&mut self, stream_id: u32, n: u32) -> Vec<WindowUpdate> {
+        let mut v = Vec::new();
+        if let Some((_, o)) = self.streams.get_mut(&stream_id)
+            && let Some(i) = o.release(n, &self.policy)
+        {
+            v.push(WindowUpdate::new(stream_id, i));
+        }
+        if let Some(i) = self.recv.release(n, &self.policy) {
+            v.push(WindowUpdate::new(0, i));
+        }
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows() {
+        let mut f = FlowControl::default();
+        f.open_stream(1);
+        f.consume_send(1, 60000).unwrap();
+        f.set_remote_initial_window_size(1000).unwrap();
+        assert_eq!(f.stream(1).unwrap().0.size(), 1000 - 60000);
+        assert_eq!(f.send_capacity(1), 0);
+        assert!(f.consume_send(1, 1).is_err());
+        f.on_window_update(1, 60000).unwrap();
+        assert_eq!(f.send_capacity(1), 1000);
+
+        let e = f.on_window_update(1, MAX_WINDOW_SIZE).unwrap_err();
+        assert_eq!(
+            (e.stream_id(), e.error_code()),
+            (1, ErrorCode::FlowControlError)
+        );
+        let e = f.on_window_update(0, MAX_WINDOW_SIZE).unwrap_err();
+        assert!(e.is_connection_error());
+        let e = f.on_window_update(1, 0).unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+        f.on_window_update(1, 1).unwrap();
+        assert!(f.set_remote_initial_window_size(MAX_WINDOW_SIZE).is_err());
+
+        let e = f.on_data(1, 65536).unwrap_err();
+        assert!(e.is_connection_error());
+        f.set_local_initial_window_size(100).unwrap();
+        let e = f.on_data(1, 101).unwrap_err();
+        assert_eq!(e.stream_id(), 1);
+    }
+
+    #[test]
+    fn policy() {
+        let mut f = FlowControl::default();
+        f.open_stream(1);
+        f.on_data(1, 30000).unwrap();
+        assert!(f.release(1, 30000).is_empty());
+        f.on_data(1, 3000).unwrap();
+        let v = f.release(1, 3000);
+        assert_eq!(v.len(), 2);
+        assert_eq!(v[0].window_size_increment(), 33000);
+        assert_eq!(f.recv().size(), 65535);
+
+        f.set_policy(WindowPolicy::new(50, true, 1 << 20));
+        f.on_data(1, 60000).unwrap();
+        let v = f.release(1, 60000);
+        assert_eq!(v[1].window_size_increment(), 60000 + 65535);
+        assert_eq!(f.recv().target(), 131070);
+        assert_eq!(
+            f.set_connection_window(1 << 20)
+                .unwrap()
+                .window_size_increment(),
+            (1 << 20) - 131070
+        );
+    }
+}
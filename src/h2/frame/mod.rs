@@ -6,14 +6,21 @@ This module provides several types and functions for working with frames.
 Each frame type can be created, then use export method.
 
 To parse a frame, you can use [`get_frame`], returns a specific frame type.
+A parsed frame only holds indexes into the source, use [`Frame::from_result`] to turn it into an owned frame.
 */
 
+use super::error::ErrorCode;
 use super::*;
+use crate::AltSvcValue;
+use crate::priority::PriorityParams;
 use derive_more::{Debug, From};
+use std::any::Any;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
 
 const FRAME_HEADER_LENGTH: usize = 9;
 const MAX_FRAME_LENGTH: usize = 16777215;
+const MIN_MAX_FRAME_SIZE: u32 = 16384;
 
 const DATA_FRAME_TYPE: u8 = 0x00;
 const HEADERS_FRAME_TYPE: u8 = 0x01;
@@ -25,6 +32,9 @@ const PING_FRAME_TYPE: u8 = 0x06;
 const GOAWAY_FRAME_TYPE: u8 = 0x07;
 const WINDOW_UPDATE_FRAME_TYPE: u8 = 0x08;
 const CONTINUATION_FRAME_TYPE: u8 = 0x09;
+const ALTSVC_FRAME_TYPE: u8 = 0x0a;
+const ORIGIN_FRAME_TYPE: u8 = 0x0c;
+const PRIORITY_UPDATE_FRAME_TYPE: u8 = 0x10;
 
 const UNUSED_FLAGS: u8 = 0b0000_0000;
 const PADDED_FLAG: u8 = 0b0000_1000;
@@ -38,6 +48,23 @@ const STREAM_IDENTIFIER_ZERO: u32 = 0;
 
 const EXCLUSIVE: u8 = 0b1000_0000;
 
+///The maximum size of the header compression table used to decode field blocks.
+pub const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x01;
+///Allows server push, the value is 0 or 1.
+pub const SETTINGS_ENABLE_PUSH: u16 = 0x02;
+///The maximum number of concurrent streams that the sender will allow.
+pub const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x03;
+///The initial window size for stream-level flow control.
+pub const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x04;
+///The size of the largest frame payload that the sender is willing to receive.
+pub const SETTINGS_MAX_FRAME_SIZE: u16 = 0x05;
+///The maximum size of field section that the sender is prepared to accept.
+pub const SETTINGS_MAX_HEADER_LIST_SIZE: u16 = 0x06;
+///Allows the use of the extended CONNECT, the value is 0 or 1.
+pub const SETTINGS_ENABLE_CONNECT_PROTOCOL: u16 = 0x08;
+///Indicates the RFC 7540 priority signals are not used, the value is 0 or 1.
+pub const SETTINGS_NO_RFC7540_PRIORITIES: u16 = 0x09;
+
 #[inline(always)]
 fn check_capacity(capacity: usize) -> NonZeroUsize {
     let n = match capacity {
@@ -103,6 +130,8 @@ pub struct Data {
     #[getset(skip)]
     #[getset(get_copy = "pub")]
     stream_identifier: u32,
+    ///The flags byte, the defined flags are replaced by the fields when exported.
+    flags: u8,
     padded: bool,
     end_stream: bool,
     pad_length: u8,
@@ -117,6 +146,7 @@ impl Data {
     pub fn new(stream_identifier: u32, capacity: usize) -> Self {
         Self {
             stream_identifier,
+            flags: UNUSED_FLAGS,
             padded: false,
             end_stream: false,
             pad_length: 0,
@@ -125,8 +155,8 @@ impl Data {
     }
 
     #[inline(always)]
-    fn flags(&self) -> u8 {
-        let mut o = UNUSED_FLAGS;
+    fn export_flags(&self) -> u8 {
+        let mut o = self.flags & !(PADDED_FLAG | END_STREAM_FLAG);
         if self.padded {
             o |= PADDED_FLAG;
         }
@@ -141,7 +171,7 @@ impl Data {
         if self.padded && self.data.len() >= MAX_FRAME_LENGTH {
             self.padded = false;
         }
-        let flags = self.flags();
+        let flags = self.export_flags();
         let stream = self.stream_identifier;
         if self.padded {
             let (length, pad_length) = pad_length(1 + self.data.len(), self.pad_length);
@@ -164,6 +194,8 @@ pub struct Headers {
     #[getset(skip)]
     #[getset(get_copy = "pub")]
     stream_identifier: u32,
+    ///The flags byte, the defined flags are replaced by the fields when exported.
+    flags: u8,
     priority: bool,
     padded: bool,
     end_headers: bool,
@@ -183,6 +215,7 @@ impl Headers {
     pub fn new(stream_identifier: u32, capacity: usize) -> Self {
         Self {
             stream_identifier,
+            flags: UNUSED_FLAGS,
             priority: false,
             padded: false,
             end_headers: false,
@@ -196,8 +229,9 @@ impl Headers {
     }
 
     #[inline(always)]
-    fn flags(&self) -> u8 {
-        let mut o = UNUSED_FLAGS;
+    fn export_flags(&self) -> u8 {
+        let mut o =
+            self.flags & !(PRIORITY_FLAG | PADDED_FLAG | END_HEADERS_FLAG | END_STREAM_FLAG);
         if self.priority {
             o |= PRIORITY_FLAG;
         }
@@ -221,7 +255,7 @@ impl Headers {
             if self.padded && n >= MAX_FRAME_LENGTH {
                 self.padded = false;
             }
-            let flags = self.flags();
+            let flags = self.export_flags();
             if self.padded {
                 let (length, pad_length) = pad_length(1 + n, self.pad_length);
                 fill_header(length, HEADERS_FRAME_TYPE, flags, stream, o);
@@ -240,7 +274,7 @@ impl Headers {
             if self.padded && n >= MAX_FRAME_LENGTH {
                 self.padded = false;
             }
-            let flags = self.flags();
+            let flags = self.export_flags();
             if self.padded {
                 let (length, pad_length) = pad_length(1 + n, self.pad_length);
                 fill_header(length, HEADERS_FRAME_TYPE, flags, stream, o);
@@ -265,6 +299,8 @@ pub struct Priority {
     #[getset(skip)]
     #[getset(get_copy = "pub")]
     stream_identifier: u32,
+    ///The flags byte, which is exported as is.
+    flags: u8,
     exclusive: bool,
     stream_dependency: u32,
     weight: u8,
@@ -275,6 +311,7 @@ impl Priority {
     pub fn new(stream_identifier: u32) -> Self {
         Self {
             stream_identifier,
+            flags: UNUSED_FLAGS,
             exclusive: false,
             stream_dependency: 0,
             weight: 0,
@@ -286,7 +323,7 @@ impl Priority {
         fill_header(
             PRIORITY_LENGTH,
             PRIORITY_FRAME_TYPE,
-            UNUSED_FLAGS,
+            self.flags,
             self.stream_identifier,
             o,
         );
@@ -301,25 +338,38 @@ const RST_STREAM_LENGTH: u32 = 0x04;
 #[getset(get_copy = "pub")]
 pub struct RstStream {
     stream_identifier: u32,
+    ///The flags byte, which is exported as is.
     #[getset(set = "pub")]
+    flags: u8,
     error_code: u32,
 }
 
 impl RstStream {
     ///Creates.
-    pub fn new(stream_identifier: u32, error_code: u32) -> Self {
+    pub fn new(stream_identifier: u32, error_code: impl Into<u32>) -> Self {
         Self {
             stream_identifier,
-            error_code,
+            flags: UNUSED_FLAGS,
+            error_code: error_code.into(),
         }
     }
 
+    ///Sets error code.
+    pub fn set_error_code(&mut self, o: impl Into<u32>) {
+        self.error_code = o.into();
+    }
+
+    ///Returns the typed error code.
+    pub fn error(&self) -> ErrorCode {
+        self.error_code.into()
+    }
+
     ///Exports self into [`PutU8`].
     pub fn export(self, o: &mut dyn PutU8) {
         fill_header(
             RST_STREAM_LENGTH,
             RST_STREAM_FRAME_TYPE,
-            UNUSED_FLAGS,
+            self.flags,
             self.stream_identifier,
             o,
         );
@@ -332,6 +382,9 @@ impl RstStream {
 pub struct Settings {
     #[getset(get_copy = "pub", set = "pub")]
     ack: bool,
+    ///The flags byte, the defined flags are replaced by the fields when exported.
+    #[getset(get_copy = "pub", set = "pub")]
+    flags: u8,
     #[debug("{}", setting.len())]
     #[getset(get = "pub", get_mut = "pub")]
     setting: Vec<(u16, u32)>,
@@ -342,13 +395,15 @@ impl Settings {
     pub fn new(capacity: usize) -> Self {
         Self {
             ack: false,
+            flags: UNUSED_FLAGS,
             setting: Vec::with_capacity(capacity),
         }
     }
 
     #[inline(always)]
-    fn flags(&self) -> u8 {
-        if self.ack { ACK_FLAG } else { UNUSED_FLAGS }
+    fn export_flags(&self) -> u8 {
+        let o = self.flags & !ACK_FLAG;
+        if self.ack { o | ACK_FLAG } else { o }
     }
 
     ///Add identifier and value.
@@ -360,10 +415,20 @@ impl Settings {
         r
     }
 
+    ///Add SETTINGS_ENABLE_CONNECT_PROTOCOL.
+    pub fn push_enable_connect_protocol(&mut self, o: bool) -> bool {
+        self.push(SETTINGS_ENABLE_CONNECT_PROTOCOL, o as u32)
+    }
+
+    ///Add SETTINGS_NO_RFC7540_PRIORITIES.
+    pub fn push_no_rfc7540_priorities(&mut self, o: bool) -> bool {
+        self.push(SETTINGS_NO_RFC7540_PRIORITIES, o as u32)
+    }
+
     ///Exports self into [`PutU8`].
     pub fn export(self, o: &mut dyn PutU8) {
-        let flags = self.flags();
-        let length = self.setting.len() as u32;
+        let flags = self.export_flags();
+        let length = 6 * self.setting.len() as u32;
         fill_header(
             length,
             SETTINGS_FRAME_TYPE,
@@ -385,6 +450,8 @@ pub struct PushPromise {
     #[getset(skip)]
     #[getset(get_copy = "pub")]
     stream_identifier: u32,
+    ///The flags byte, the defined flags are replaced by the fields when exported.
+    flags: u8,
     padded: bool,
     end_headers: bool,
     pad_length: u8,
@@ -400,6 +467,7 @@ impl PushPromise {
     pub fn new(stream_identifier: u32, capacity: usize) -> Self {
         Self {
             stream_identifier,
+            flags: UNUSED_FLAGS,
             padded: false,
             end_headers: false,
             pad_length: 0,
@@ -409,8 +477,8 @@ impl PushPromise {
     }
 
     #[inline(always)]
-    fn flags(&self) -> u8 {
-        let mut o = UNUSED_FLAGS;
+    fn export_flags(&self) -> u8 {
+        let mut o = self.flags & !(PADDED_FLAG | END_HEADERS_FLAG);
         if self.padded {
             o |= PADDED_FLAG;
         }
@@ -426,7 +494,7 @@ impl PushPromise {
         if self.padded && n >= MAX_FRAME_LENGTH {
             self.padded = false;
         }
-        let flags = self.flags();
+        let flags = self.export_flags();
         let stream = self.stream_identifier;
         if self.padded {
             let (length, pad_length) = pad_length(1 + n, self.pad_length);
@@ -451,6 +519,9 @@ const PING_LENGTH: u32 = 0x08;
 #[getset(get_copy = "pub")]
 pub struct Ping {
     stream_identifier: u32,
+    ///The flags byte, the defined flags are replaced by the fields when exported.
+    #[getset(set = "pub")]
+    flags: u8,
     #[getset(set = "pub")]
     ack: bool,
     #[getset(set = "pub")]
@@ -462,19 +533,21 @@ impl Ping {
     pub fn new(ack: bool, opaque_data: u64) -> Self {
         Self {
             stream_identifier: STREAM_IDENTIFIER_ZERO,
+            flags: UNUSED_FLAGS,
             ack,
             opaque_data,
         }
     }
 
     #[inline(always)]
-    fn flags(&self) -> u8 {
-        if self.ack { ACK_FLAG } else { UNUSED_FLAGS }
+    fn export_flags(&self) -> u8 {
+        let o = self.flags & !ACK_FLAG;
+        if self.ack { o | ACK_FLAG } else { o }
     }
 
     ///Exports self into [`PutU8`].
     pub fn export(self, o: &mut dyn PutU8) {
-        let flags = self.flags();
+        let flags = self.export_flags();
         let stream = self.stream_identifier;
         fill_header(PING_LENGTH, PING_FRAME_TYPE, flags, stream, o);
         u64_to(self.opaque_data, o);
@@ -485,7 +558,11 @@ impl Ping {
 #[derive(CopyGetters, Debug, Getters, MutGetters, Setters)]
 #[getset(get_copy = "pub", set = "pub")]
 pub struct Goaway {
+    ///The flags byte, which is exported as is.
+    flags: u8,
     last_stream_id: u32,
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
     error_code: u32,
     #[debug("{}", additional_debug_data.len())]
     #[getset(skip)]
@@ -497,19 +574,30 @@ impl Goaway {
     ///Creates.
     pub fn new(capacity: usize) -> Self {
         Self {
+            flags: UNUSED_FLAGS,
             last_stream_id: 0,
             error_code: 0,
             additional_debug_data: check_capacity(capacity).into(),
         }
     }
 
+    ///Sets error code.
+    pub fn set_error_code(&mut self, o: impl Into<u32>) {
+        self.error_code = o.into();
+    }
+
+    ///Returns the typed error code.
+    pub fn error(&self) -> ErrorCode {
+        self.error_code.into()
+    }
+
     ///Exports self into [`PutU8`].
     pub fn export(self, o: &mut dyn PutU8) {
         let length = 8 + self.additional_debug_data.len() as u32;
         fill_header(
             length,
             GOAWAY_FRAME_TYPE,
-            UNUSED_FLAGS,
+            self.flags,
             STREAM_IDENTIFIER_ZERO,
             o,
         );
@@ -526,6 +614,9 @@ const WINDOW_UPDATE_LENGTH: u32 = 0x04;
 #[getset(get_copy = "pub")]
 pub struct WindowUpdate {
     stream_identifier: u32,
+    ///The flags byte, which is exported as is.
+    #[getset(set = "pub")]
+    flags: u8,
     #[getset(set = "pub")]
     window_size_increment: u32,
 }
@@ -535,6 +626,7 @@ impl WindowUpdate {
     pub fn new(stream_identifier: u32, window_size_increment: u32) -> Self {
         Self {
             stream_identifier,
+            flags: UNUSED_FLAGS,
             window_size_increment,
         }
     }
@@ -544,7 +636,7 @@ impl WindowUpdate {
         fill_header(
             WINDOW_UPDATE_LENGTH,
             WINDOW_UPDATE_FRAME_TYPE,
-            UNUSED_FLAGS,
+            self.flags,
             self.stream_identifier,
             o,
         );
@@ -557,6 +649,9 @@ impl WindowUpdate {
 pub struct Continuation {
     #[getset(get_copy = "pub")]
     stream_identifier: u32,
+    ///The flags byte, the defined flags are replaced by the fields when exported.
+    #[getset(get_copy = "pub", set = "pub")]
+    flags: u8,
     #[getset(get_copy = "pub", set = "pub")]
     end_headers: bool,
     #[debug("{}", field_block_fragment.len())]
@@ -569,23 +664,25 @@ impl Continuation {
     pub fn new(stream_identifier: u32, capacity: usize) -> Self {
         Self {
             stream_identifier,
+            flags: UNUSED_FLAGS,
             end_headers: false,
             field_block_fragment: check_capacity(capacity).into(),
         }
     }
 
     #[inline(always)]
-    fn flags(&self) -> u8 {
+    fn export_flags(&self) -> u8 {
+        let o = self.flags & !END_HEADERS_FLAG;
         if self.end_headers {
-            END_HEADERS_FLAG
+            o | END_HEADERS_FLAG
         } else {
-            UNUSED_FLAGS
+            o
         }
     }
 
     ///Exports self into [`PutU8`].
     pub fn export(self, o: &mut dyn PutU8) {
-        let flags = self.flags();
+        let flags = self.export_flags();
         let length = self.field_block_fragment.len() as u32;
         let stream = self.stream_identifier;
         fill_header(length, CONTINUATION_FRAME_TYPE, flags, stream, o);
@@ -593,6 +690,275 @@ impl Continuation {
     }
 }
 
+///Represents a PRIORITY_UPDATE frame, which signals the priority of a stream.
+#[derive(CopyGetters, Debug, Getters, MutGetters, Setters)]
+pub struct PriorityUpdate {
+    ///The flags byte, which is exported as is.
+    #[getset(get_copy = "pub", set = "pub")]
+    flags: u8,
+    #[getset(get_copy = "pub", set = "pub")]
+    prioritized_stream_id: u32,
+    #[getset(get = "pub", get_mut = "pub")]
+    priority_field_value: Vec<u8>,
+}
+
+impl PriorityUpdate {
+    ///Creates.
+    pub fn new(prioritized_stream_id: u32, priority: &PriorityParams) -> Self {
+        let mut priority_field_value = Vec::new();
+        priority.export(&mut priority_field_value);
+        Self {
+            flags: UNUSED_FLAGS,
+            prioritized_stream_id,
+            priority_field_value,
+        }
+    }
+
+    ///Exports self into [`PutU8`].
+    pub fn export(self, o: &mut dyn PutU8) {
+        let length = 4 + self.priority_field_value.len() as u32;
+        fill_header(
+            length,
+            PRIORITY_UPDATE_FRAME_TYPE,
+            self.flags,
+            STREAM_IDENTIFIER_ZERO,
+            o,
+        );
+        fill_stream_id(self.prioritized_stream_id, o);
+        o.put_exact(&self.priority_field_value);
+    }
+}
+
+///Represents an ALTSVC frame, which advertises alternative services.
+///
+///On stream 0, the origin is the one the alternative services apply to.
+///On other streams, the origin is empty, and the alternative services apply to the origin of the stream.
+#[derive(CopyGetters, Debug, Getters, MutGetters, Setters)]
+pub struct AltSvc {
+    #[getset(get_copy = "pub", set = "pub")]
+    stream_identifier: u32,
+    ///The flags byte, which is exported as is.
+    #[getset(get_copy = "pub", set = "pub")]
+    flags: u8,
+    #[getset(get = "pub", get_mut = "pub")]
+    origin: Vec<u8>,
+    #[getset(get = "pub", get_mut = "pub")]
+    alt_svc_field_value: Vec<u8>,
+}
+
+impl AltSvc {
+    ///Creates.
+    pub fn new(stream_identifier: u32, origin: &[u8], value: &AltSvcValue) -> Self {
+        let mut alt_svc_field_value = Vec::new();
+        value.export(&mut alt_svc_field_value);
+        Self {
+            stream_identifier,
+            flags: UNUSED_FLAGS,
+            origin: origin.to_vec(),
+            alt_svc_field_value,
+        }
+    }
+
+    ///Exports self into [`PutU8`].
+    pub fn export(self, o: &mut dyn PutU8) {
+        let length = 2 + self.origin.len() as u32 + self.alt_svc_field_value.len() as u32;
+        let stream = self.stream_identifier;
+        fill_header(length, ALTSVC_FRAME_TYPE, self.flags, stream, o);
+        u16_to(self.origin.len() as u16, o);
+        o.put_exact(&self.origin);
+        o.put_exact(&self.alt_svc_field_value);
+    }
+}
+
+///Represents an ORIGIN frame, which lists the origins the server is authoritative for. It is sent on stream 0.
+#[derive(CopyGetters, Debug, Default, Getters, MutGetters, Setters)]
+pub struct Origin {
+    ///The flags byte, which is exported as is.
+    #[getset(get_copy = "pub", set = "pub")]
+    flags: u8,
+    #[getset(get = "pub", get_mut = "pub")]
+    origins: Vec<Vec<u8>>,
+}
+
+impl Origin {
+    ///Creates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Adds an origin, e.g. "https://www.example.com". Returns false if it is too long.
+    pub fn push(&mut self, o: &[u8]) -> bool {
+        let r = o.len() <= u16::MAX as usize;
+        if r {
+            self.origins.push(o.to_vec());
+        }
+        r
+    }
+
+    ///Exports self into [`PutU8`].
+    pub fn export(self, o: &mut dyn PutU8) {
+        let length = self.origins.iter().map(|o| 2 + o.len() as u32).sum();
+        fill_header(
+            length,
+            ORIGIN_FRAME_TYPE,
+            self.flags,
+            STREAM_IDENTIFIER_ZERO,
+            o,
+        );
+        for v in self.origins {
+            u16_to(v.len() as u16, o);
+            o.put_exact(&v);
+        }
+    }
+}
+
+///Represents a frame of unknown type, which is kept as is.
+#[derive(CopyGetters, Debug, Getters, MutGetters, Setters)]
+#[getset(get_copy = "pub", set = "pub")]
+pub struct Unknown {
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    ty: u8,
+    flags: u8,
+    stream_identifier: u32,
+    #[debug("{}", payload.len())]
+    #[getset(skip)]
+    #[getset(get = "pub", get_mut = "pub")]
+    payload: FiniteVec,
+}
+
+impl Unknown {
+    ///Creates.
+    pub fn new(ty: u8, stream_identifier: u32, capacity: usize) -> Self {
+        Self {
+            ty,
+            flags: UNUSED_FLAGS,
+            stream_identifier,
+            payload: check_capacity(capacity).into(),
+        }
+    }
+
+    ///Exports self into [`PutU8`].
+    pub fn export(self, o: &mut dyn PutU8) {
+        let length = self.payload.len() as u32;
+        fill_header(length, self.ty, self.flags, self.stream_identifier, o);
+        o.put_exact(&self.payload);
+    }
+}
+
+///Represents an owned frame of any type.
+///
+///A parsed frame is turned into an owned frame by [`Frame::from_result`], which copies the payload out of the source,
+///and keeps the flags byte, including undefined flags, the padding length and priority. Exporting it produces the same bytes as the source,
+///provided that the padding octets and reserved bits are zero, as senders are required to do.
+#[derive(Debug, From)]
+pub enum Frame {
+    Data(Data),
+    Headers(Headers),
+    Priority(Priority),
+    RstStream(RstStream),
+    Settings(Settings),
+    PushPromise(PushPromise),
+    Ping(Ping),
+    Goaway(Goaway),
+    WindowUpdate(WindowUpdate),
+    Continuation(Continuation),
+    PriorityUpdate(PriorityUpdate),
+    AltSvc(AltSvc),
+    Origin(Origin),
+    Unknown(Unknown),
+}
+
+impl Frame {
+    ///Creates from a parsed frame, `o` is the source it was parsed from.
+    ///Returns None if the source doesn't hold the payload, or the frame is decoded by an extension decoder.
+    pub fn from_result(r: FrameResult, o: &mut dyn GetU8) -> Option<Self> {
+        let r = match r {
+            FrameResult::Data(r) => r.into_frame(o)?.into(),
+            FrameResult::Headers(r) => r.into_frame(o)?.into(),
+            FrameResult::Priority(r) => r.into(),
+            FrameResult::RstStream(r) => r.into(),
+            FrameResult::Settings(r) => r.into_frame().into(),
+            FrameResult::PushPromise(r) => r.into_frame(o)?.into(),
+            FrameResult::Ping(r) => r.into(),
+            FrameResult::Goaway(r) => r.into_frame(o)?.into(),
+            FrameResult::WindowUpdate(r) => r.into(),
+            FrameResult::Continuation(r) => r.into_frame(o)?.into(),
+            FrameResult::PriorityUpdate(r) => r.into_frame(o)?.into(),
+            FrameResult::AltSvc(r) => r.into_frame(o)?.into(),
+            FrameResult::Origin(r) => r.into_frame().into(),
+            FrameResult::Unknown(r) => r.into_frame(o)?.into(),
+            FrameResult::Extension(_) => return None,
+        };
+        Some(r)
+    }
+
+    ///Returns the frame type.
+    pub fn ty(&self) -> u8 {
+        match self {
+            Self::Data(_) => DATA_FRAME_TYPE,
+            Self::Headers(_) => HEADERS_FRAME_TYPE,
+            Self::Priority(_) => PRIORITY_FRAME_TYPE,
+            Self::RstStream(_) => RST_STREAM_FRAME_TYPE,
+            Self::Settings(_) => SETTINGS_FRAME_TYPE,
+            Self::PushPromise(_) => PUSH_PROMISE_FRAME_TYPE,
+            Self::Ping(_) => PING_FRAME_TYPE,
+            Self::Goaway(_) => GOAWAY_FRAME_TYPE,
+            Self::WindowUpdate(_) => WINDOW_UPDATE_FRAME_TYPE,
+            Self::Continuation(_) => CONTINUATION_FRAME_TYPE,
+            Self::PriorityUpdate(_) => PRIORITY_UPDATE_FRAME_TYPE,
+            Self::AltSvc(_) => ALTSVC_FRAME_TYPE,
+            Self::Origin(_) => ORIGIN_FRAME_TYPE,
+            Self::Unknown(o) => o.ty,
+        }
+    }
+
+    ///Returns the stream identifier.
+    pub fn stream_identifier(&self) -> u32 {
+        match self {
+            Self::Data(o) => o.stream_identifier,
+            Self::Headers(o) => o.stream_identifier,
+            Self::Priority(o) => o.stream_identifier,
+            Self::RstStream(o) => o.stream_identifier,
+            Self::PushPromise(o) => o.stream_identifier,
+            Self::Ping(o) => o.stream_identifier,
+            Self::WindowUpdate(o) => o.stream_identifier,
+            Self::Continuation(o) => o.stream_identifier,
+            Self::AltSvc(o) => o.stream_identifier,
+            Self::Unknown(o) => o.stream_identifier,
+            Self::Settings(_) | Self::Goaway(_) | Self::PriorityUpdate(_) | Self::Origin(_) => {
+                STREAM_IDENTIFIER_ZERO
+            }
+        }
+    }
+
+    ///Exports self into [`PutU8`].
+    pub fn export(self, o: &mut dyn PutU8) {
+        match self {
+            Self::Data(r) => r.export(o),
+            Self::Headers(r) => r.export(o),
+            Self::Priority(r) => r.export(o),
+            Self::RstStream(r) => r.export(o),
+            Self::Settings(r) => r.export(o),
+            Self::PushPromise(r) => r.export(o),
+            Self::Ping(r) => r.export(o),
+            Self::Goaway(r) => r.export(o),
+            Self::WindowUpdate(r) => r.export(o),
+            Self::Continuation(r) => r.export(o),
+            Self::PriorityUpdate(r) => r.export(o),
+            Self::AltSvc(r) => r.export(o),
+            Self::Origin(r) => r.export(o),
+            Self::Unknown(r) => r.export(o),
+        }
+    }
+}
+
+#[inline(always)]
+fn copy_to(o: &mut dyn GetU8) -> FiniteVec {
+    let r = o.get_surplus().to_vec();
+    (check_capacity(r.len()), r).into()
+}
+
 #[inline(always)]
 fn get_31_uint(o: &[u8]) -> u32 {
     u32::from_be_bytes([o[0] & RESERVED, o[1], o[2], o[3]])
@@ -606,12 +972,127 @@ fn get_priority(o: &[u8]) -> (bool, u32, u8) {
 }
 
 #[inline(always)]
-fn check_return<'a>(
-    o: bool,
-    s: &'a str,
-    h: &FrameHeader,
-) -> Result<(), (&'a str, Option<FrameHeader>)> {
-    if o { Err((s, Some(*h))) } else { Ok(()) }
+fn check_return(o: bool, s: &'static str, h: &FrameHeader) -> Result<(), ParseError> {
+    if o {
+        Err(ParseError::Protocol(*h, s))
+    } else {
+        Ok(())
+    }
+}
+
+#[inline(always)]
+fn check_length(o: bool, h: &FrameHeader) -> Result<(), ParseError> {
+    if o { Err(invalid_length(h)) } else { Ok(()) }
+}
+
+#[inline(always)]
+fn invalid_length(h: &FrameHeader) -> ParseError {
+    ParseError::FrameSize(*h, "invalid length")
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ParseError {
+    Incomplete(usize, Option<FrameHeader>),
+    FrameSize(FrameHeader, &'static str),
+    Protocol(FrameHeader, &'static str),
+}
+
+impl From<ParseError> for (&'static str, Option<FrameHeader>) {
+    fn from(o: ParseError) -> Self {
+        match o {
+            ParseError::Incomplete(_, None) => ("header shortage", None),
+            ParseError::Incomplete(_, h) => ("shortage", h),
+            ParseError::FrameSize(h, s) | ParseError::Protocol(h, s) => (s, Some(h)),
+        }
+    }
+}
+
+///Represents an error of parsing frame.
+#[derive(Clone, Copy, Debug)]
+pub enum FrameError {
+    ///Needs n more bytes to parse a frame, the input is not consumed.
+    Incomplete(usize),
+    ///A frame exceeds the max frame size or has an invalid length, treated as FRAME_SIZE_ERROR.
+    FrameSize(FrameHeader, &'static str),
+    ///A frame violates the protocol, treated as PROTOCOL_ERROR.
+    Protocol(FrameHeader, &'static str),
+}
+
+impl From<ParseError> for FrameError {
+    fn from(o: ParseError) -> Self {
+        match o {
+            ParseError::Incomplete(n, _) => Self::Incomplete(n),
+            ParseError::FrameSize(h, s) => Self::FrameSize(h, s),
+            ParseError::Protocol(h, s) => Self::Protocol(h, s),
+        }
+    }
+}
+
+impl FrameError {
+    ///Returns the error code to signal, or None if incomplete.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Incomplete(_) => None,
+            Self::FrameSize(..) => Some(ErrorCode::FrameSizeError),
+            Self::Protocol(..) => Some(ErrorCode::ProtocolError),
+        }
+    }
+
+    ///Returns the reason, or None if incomplete.
+    pub fn reason(&self) -> Option<&'static str> {
+        match self {
+            Self::Incomplete(_) => None,
+            Self::FrameSize(_, s) | Self::Protocol(_, s) => Some(s),
+        }
+    }
+}
+
+///A parser of frames with the max frame size and registered extension frame types.
+#[derive(Debug, CopyGetters, Getters, MutGetters)]
+pub struct FrameParser {
+    #[getset(get_copy = "pub")]
+    max_frame_size: u32,
+    #[getset(get = "pub", get_mut = "pub")]
+    extensions: FrameExtensions,
+}
+
+impl Default for FrameParser {
+    fn default() -> Self {
+        Self {
+            max_frame_size: MIN_MAX_FRAME_SIZE,
+            extensions: FrameExtensions::default(),
+        }
+    }
+}
+
+impl FrameParser {
+    ///Sets the max frame size, which is limited between 2^14 and 2^24-1.
+    pub fn set_max_frame_size(&mut self, n: u32) {
+        self.max_frame_size = n.clamp(MIN_MAX_FRAME_SIZE, MAX_FRAME_LENGTH as u32);
+    }
+
+    ///Parses bytes. Returns a frame, or error.
+    ///
+    ///Returns [`FrameError::Incomplete`] with the number of bytes needed if the frame is truncated, and the input is not consumed.
+    pub fn parse(&self, o: &mut dyn GetU8) -> Result<FrameResult, FrameError> {
+        let n = o.surplus();
+        if n < FRAME_HEADER_LENGTH {
+            return Err(FrameError::Incomplete(FRAME_HEADER_LENGTH - n));
+        }
+        let i = o.index();
+        let h = o.get_exact(FRAME_HEADER_LENGTH).map(FrameHeader::parse);
+        o.set_index(i);
+        if let Some(h) = h {
+            if h.length > self.max_frame_size {
+                return Err(FrameError::FrameSize(h, "frame too large"));
+            }
+            let m = FRAME_HEADER_LENGTH + h.length as usize;
+            if n < m {
+                return Err(FrameError::Incomplete(m - n));
+            }
+        }
+        get_frame_inner(o, Some(&self.extensions)).map_err(FrameError::from)
+    }
 }
 
 ///Represents a parsed frame header.
@@ -625,6 +1106,33 @@ pub struct FrameHeader {
 }
 
 impl FrameHeader {
+    #[inline(always)]
+    fn parse(o: &[u8]) -> Self {
+        Self {
+            length: u32::from_be_bytes([0, o[0], o[1], o[2]]),
+            ty: o[3],
+            flags: o[4],
+            stream_identifier: get_31_uint(&o[5..9]),
+        }
+    }
+
+    #[inline(always)]
+    fn check_stream_identifier(&self) -> bool {
+        match self.ty {
+            DATA_FRAME_TYPE
+            | HEADERS_FRAME_TYPE
+            | PRIORITY_FRAME_TYPE
+            | RST_STREAM_FRAME_TYPE
+            | PUSH_PROMISE_FRAME_TYPE
+            | CONTINUATION_FRAME_TYPE => self.stream_identifier != 0,
+            SETTINGS_FRAME_TYPE
+            | PING_FRAME_TYPE
+            | GOAWAY_FRAME_TYPE
+            | PRIORITY_UPDATE_FRAME_TYPE => self.stream_identifier == 0,
+            _ => true,
+        }
+    }
+
     #[inline(always)]
     fn padded_flag(&self) -> bool {
         bit_eq(self.flags, PADDED_FLAG)
@@ -652,7 +1160,7 @@ impl FrameHeader {
 }
 
 ///Represents a result of parsing frame.
-#[derive(From)]
+#[derive(Debug, From)]
 #[repr(u8)]
 pub enum FrameResult {
     Data(DataResult),
@@ -665,30 +1173,61 @@ pub enum FrameResult {
     Goaway(GoawayResult),
     WindowUpdate(WindowUpdate),
     Continuation(ContinuationResult),
+    PriorityUpdate(PriorityUpdateResult),
+    AltSvc(AltSvcResult),
+    Origin(OriginResult),
+    Unknown(UnknownResult),
+    Extension(ExtensionResult),
 }
 
-///Parses bytes. Returns a frame, or error.
+///Parses bytes. Returns a frame, or error. A frame of unknown type is returned as [`FrameResult::Unknown`].
 pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHeader>)> {
+    get_frame_inner(o, None).map_err(Into::into)
+}
+
+///Parses bytes with registered extension frame types. Returns a frame, or error.
+///
+///A frame of registered type is decoded by the decoder and returned as [`FrameResult::Extension`],
+///other unknown types are returned as [`FrameResult::Unknown`].
+pub fn get_frame_with<'a>(
+    o: &'a mut dyn GetU8,
+    extensions: &FrameExtensions,
+) -> Result<FrameResult, (&'a str, Option<FrameHeader>)> {
+    get_frame_inner(o, Some(extensions)).map_err(Into::into)
+}
+
+fn get_frame_inner(
+    o: &mut dyn GetU8,
+    extensions: Option<&FrameExtensions>,
+) -> Result<FrameResult, ParseError> {
+    let a = o.index();
+    let n = o.surplus();
     let h = o
         .get_exact(FRAME_HEADER_LENGTH)
-        .ok_or_else(|| ("header shortage", None))?;
-    let h = FrameHeader {
-        length: u32::from_be_bytes([0, h[0], h[1], h[2]]),
-        ty: h[3],
-        flags: h[4],
-        stream_identifier: get_31_uint(&h[5..9]),
-    };
+        .map(FrameHeader::parse)
+        .ok_or_else(|| ParseError::Incomplete(FRAME_HEADER_LENGTH - n, None))?;
+    let n = o.surplus();
+    if n < h.length as usize {
+        o.set_index(a);
+        return Err(ParseError::Incomplete(h.length as usize - n, Some(h)));
+    }
+    check_return(
+        !h.check_stream_identifier(),
+        "invalid stream identifier",
+        &h,
+    )?;
     let i = o.index();
     let length = h.length;
     let stream_identifier = h.stream_identifier;
-    let mut temp = TempIndex(i, i + length as usize);
+    let end = i + length as usize;
+    let mut temp = TempIndex(i, end);
     let r = match h.ty {
         DATA_FRAME_TYPE => {
             let padded = h.padded_flag();
             let mut pad_length = 0;
             if padded {
-                check_return(length == 0, "invalid length", &h)?;
-                pad_length = o.get_u8().ok_or_else(|| ("shortage", Some(h)))?;
+                check_length(length == 0, &h)?;
+                pad_length = o.get_u8().ok_or_else(|| invalid_length(&h))?;
                 temp.0 += 1;
                 check_return(
                     !temp.sub_pad_length(length, pad_length),
@@ -696,10 +1235,11 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
                     &h,
                 )?;
             }
-            o.set_index(temp.1);
+            o.set_index(end);
             DataResult {
                 length,
                 stream_identifier,
+                flags: h.flags,
                 padded,
                 end_stream: h.end_stream_flag(),
                 pad_length,
@@ -711,8 +1251,8 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             let padded = h.padded_flag();
             let mut pad_length = 0;
             if padded {
-                check_return(length == 0, "invalid length", &h)?;
-                pad_length = o.get_u8().ok_or_else(|| ("shortage", Some(h)))?;
+                check_length(length == 0, &h)?;
+                pad_length = o.get_u8().ok_or_else(|| invalid_length(&h))?;
                 temp.0 += 1;
                 check_return(
                     !temp.sub_pad_length(length, pad_length),
@@ -725,15 +1265,17 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             let mut stream_dependency = 0;
             let mut weight = 0;
             if priority {
-                check_return(length < 5, "invalid length", &h)?;
-                let i = o.get_exact(5).ok_or_else(|| ("shortage", Some(h)))?;
+                check_length(length < 5 + padded as u32, &h)?;
+                check_return(temp.0 + 5 > temp.1, "protocol error", &h)?;
+                let i = o.get_exact(5).ok_or_else(|| invalid_length(&h))?;
                 (exclusive, stream_dependency, weight) = get_priority(i);
                 temp.0 += 5;
             }
-            o.set_index(temp.1);
+            o.set_index(end);
             HeadersResult {
                 length,
                 stream_identifier,
+                flags: h.flags,
                 priority,
                 padded,
                 end_headers: h.end_headers_flag(),
@@ -747,13 +1289,14 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             .into()
         }
         PRIORITY_FRAME_TYPE => {
-            check_return(length != PRIORITY_LENGTH, "invalid length", &h)?;
+            check_length(length != PRIORITY_LENGTH, &h)?;
             let i = o
                 .get_exact(PRIORITY_LENGTH as usize)
-                .ok_or_else(|| ("shortage", Some(h)))?;
+                .ok_or_else(|| invalid_length(&h))?;
             let (exclusive, stream_dependency, weight) = get_priority(i);
             Priority {
                 stream_identifier,
+                flags: h.flags,
                 exclusive,
                 stream_dependency,
                 weight,
@@ -761,20 +1304,23 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             .into()
         }
         RST_STREAM_FRAME_TYPE => {
-            check_return(length != RST_STREAM_LENGTH, "invalid length", &h)?;
-            let error_code = to_u32(o).ok_or_else(|| ("shortage", Some(h)))?;
+            check_length(length != RST_STREAM_LENGTH, &h)?;
+            let error_code = to_u32(o).ok_or_else(|| invalid_length(&h))?;
             RstStream {
                 stream_identifier,
+                flags: h.flags,
                 error_code,
             }
             .into()
         }
         SETTINGS_FRAME_TYPE => {
+            check_length(length % 6 != 0, &h)?;
+            check_length(h.ack_flag() && length != 0, &h)?;
             let mut setting = Vec::new();
             if length > 0 {
                 let mut k = o
                     .get_exact_to(length as usize)
-                    .ok_or_else(|| ("shortage", Some(h)))?;
+                    .ok_or_else(|| invalid_length(&h))?;
                 while let Some(v) = k.get_exact(6) {
                     let a = u16::from_be_bytes([v[0], v[1]]);
                     let b = u32::from_be_bytes([v[2], v[3], v[4], v[5]]);
@@ -785,6 +1331,7 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             SettingsResult {
                 length,
                 stream_identifier,
+                flags: h.flags,
                 ack: h.ack_flag(),
                 setting,
             }
@@ -794,8 +1341,8 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             let padded = h.padded_flag();
             let mut pad_length = 0;
             if padded {
-                check_return(length == 0, "invalid length", &h)?;
-                pad_length = o.get_u8().ok_or_else(|| ("shortage", Some(h)))?;
+                check_length(length == 0, &h)?;
+                pad_length = o.get_u8().ok_or_else(|| invalid_length(&h))?;
                 temp.0 += 1;
                 check_return(
                     !temp.sub_pad_length(length, pad_length),
@@ -803,13 +1350,16 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
                     &h,
                 )?;
             }
-            let i = o.get_exact(4).ok_or_else(|| ("shortage", Some(h)))?;
+            check_length(length < 4 + padded as u32, &h)?;
+            check_return(temp.0 + 4 > temp.1, "protocol error", &h)?;
+            let i = o.get_exact(4).ok_or_else(|| invalid_length(&h))?;
             let promised_stream_id = get_31_uint(i);
             temp.0 += 4;
-            o.set_index(temp.1);
+            o.set_index(end);
             PushPromiseResult {
                 length,
                 stream_identifier,
+                flags: h.flags,
                 padded,
                 end_headers: h.end_headers_flag(),
                 pad_length,
@@ -819,23 +1369,27 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             .into()
         }
         PING_FRAME_TYPE => {
-            check_return(length != PING_LENGTH, "invalid length", &h)?;
-            let opaque_data = to_u64(o).ok_or_else(|| ("shortage", Some(h)))?;
+            check_length(length != PING_LENGTH, &h)?;
+            let opaque_data = to_u64(o).ok_or_else(|| invalid_length(&h))?;
             Ping {
                 stream_identifier,
+                flags: h.flags,
                 ack: h.ack_flag(),
                 opaque_data,
             }
             .into()
         }
         GOAWAY_FRAME_TYPE => {
-            let i = o.get_exact(4).ok_or_else(|| ("shortage", Some(h)))?;
+            check_length(length < 8, &h)?;
+            let i = o.get_exact(4).ok_or_else(|| invalid_length(&h))?;
             let last_stream_id = get_31_uint(i);
-            let error_code = to_u32(o).ok_or_else(|| ("shortage", Some(h)))?;
+            let error_code = to_u32(o).ok_or_else(|| invalid_length(&h))?;
+            temp.0 += 8;
             o.set_index(temp.1);
             GoawayResult {
                 length,
                 stream_identifier,
+                flags: h.flags,
                 last_stream_id,
                 error_code,
                 temp,
@@ -843,13 +1397,14 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             .into()
         }
         WINDOW_UPDATE_FRAME_TYPE => {
-            check_return(length != WINDOW_UPDATE_LENGTH, "invalid length", &h)?;
+            check_length(length != WINDOW_UPDATE_LENGTH, &h)?;
             let i = o
                 .get_exact(WINDOW_UPDATE_LENGTH as usize)
-                .ok_or_else(|| ("shortage", Some(h)))?;
+                .ok_or_else(|| invalid_length(&h))?;
             let window_size_increment = get_31_uint(i);
             WindowUpdate {
                 stream_identifier,
+                flags: h.flags,
                 window_size_increment,
             }
             .into()
@@ -859,16 +1414,156 @@ pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, (&str, Option<FrameHe
             ContinuationResult {
                 length,
                 stream_identifier,
+                flags: h.flags,
                 end_headers: h.end_headers_flag(),
                 temp,
             }
             .into()
         }
-        _ => return Err(("invalid type", Some(h))),
+        PRIORITY_UPDATE_FRAME_TYPE => {
+            check_length(length < 4, &h)?;
+            let i = o.get_exact(4).ok_or_else(|| invalid_length(&h))?;
+            let prioritized_stream_id = get_31_uint(i);
+            temp.0 += 4;
+            o.set_index(temp.1);
+            PriorityUpdateResult {
+                length,
+                stream_identifier,
+                flags: h.flags,
+                prioritized_stream_id,
+                temp,
+            }
+            .into()
+        }
+        ALTSVC_FRAME_TYPE => {
+            let mut origin_length = 0;
+            let mut valid = false;
+            if length >= 2 {
+                let i = o.get_exact(2).ok_or_else(|| invalid_length(&h))?;
+                origin_length = u16::from_be_bytes([i[0], i[1]]);
+                valid = 2 + origin_length as u32 <= length;
+                temp.0 += 2;
+            }
+            o.set_index(temp.1);
+            AltSvcResult {
+                length,
+                stream_identifier,
+                flags: h.flags,
+                origin_length,
+                valid,
+                temp,
+            }
+            .into()
+        }
+        ORIGIN_FRAME_TYPE => {
+            let mut origins = Vec::new();
+            let mut valid = true;
+            if length > 0 {
+                let mut k = o
+                    .get_exact_to(length as usize)
+                    .ok_or_else(|| invalid_length(&h))?;
+                while let Some(v) = k.get_exact(2) {
+                    let n = u16::from_be_bytes([v[0], v[1]]) as usize;
+                    let Some(v) = k.get_exact(n) else {
+                        break;
+                    };
+                    origins.push(v.to_vec());
+                }
+                if k.is_surplus() {
+                    origins.clear();
+                    valid = false;
+                }
+            }
+            o.set_index(temp.1);
+            OriginResult {
+                length,
+                stream_identifier,
+                flags: h.flags,
+                valid,
+                origins,
+            }
+            .into()
+        }
+        ty => match extensions.and_then(|e| e.inner.get(&ty)) {
+            Some(f) => {
+                let v = o
+                    .get_exact(length as usize)
+                    .ok_or_else(|| invalid_length(&h))?;
+                let value = f(&h, v).map_err(|e| ParseError::Protocol(h, e))?;
+                ExtensionResult { header: h, value }.into()
+            }
+            None => {
+                o.set_index(temp.1);
+                UnknownResult {
+                    length,
+                    ty,
+                    flags: h.flags,
+                    stream_identifier,
+                    temp,
+                }
+                .into()
+            }
+        },
     };
     Ok(r)
 }
 
+#[inline(always)]
+fn is_defined_type(ty: u8) -> bool {
+    matches!(
+        ty,
+        DATA_FRAME_TYPE
+            | HEADERS_FRAME_TYPE
+            | PRIORITY_FRAME_TYPE
+            | RST_STREAM_FRAME_TYPE
+            | SETTINGS_FRAME_TYPE
+            | PUSH_PROMISE_FRAME_TYPE
+            | PING_FRAME_TYPE
+            | GOAWAY_FRAME_TYPE
+            | WINDOW_UPDATE_FRAME_TYPE
+            | CONTINUATION_FRAME_TYPE
+            | PRIORITY_UPDATE_FRAME_TYPE
+            | ALTSVC_FRAME_TYPE
+            | ORIGIN_FRAME_TYPE
+    )
+}
+
+///A decoder of an extension frame, decodes the frame payload into a value of any type.
+pub type ExtensionDecoder = Box<dyn Fn(&FrameHeader, &[u8]) -> Result<Box<dyn Any>, &'static str>>;
+
+///Registered decoders of extension frame types, used by [`get_frame_with`].
+#[derive(Debug, Default)]
+pub struct FrameExtensions {
+    #[debug("{:?}", inner.keys())]
+    inner: HashMap<u8, ExtensionDecoder>,
+}
+
+impl FrameExtensions {
+    ///Registers a decoder of a frame type.
+    ///Returns false if the frame type is defined by this module, and the decoder is not registered.
+    pub fn register(
+        &mut self,
+        ty: u8,
+        f: impl Fn(&FrameHeader, &[u8]) -> Result<Box<dyn Any>, &'static str> + 'static,
+    ) -> bool {
+        let r = !is_defined_type(ty);
+        if r {
+            self.inner.insert(ty, Box::new(f));
+        }
+        r
+    }
+
+    ///Removes the decoder of a frame type.
+    pub fn unregister(&mut self, ty: u8) -> bool {
+        self.inner.remove(&ty).is_some()
+    }
+
+    ///Returns true if a decoder of the frame type is registered.
+    pub fn contains(&self, ty: u8) -> bool {
+        self.inner.contains_key(&ty)
+    }
+}
+
 struct TempIndex(usize, usize);
 
 impl TempIndex {
@@ -890,6 +1585,7 @@ impl TempIndex {
 pub struct DataResult {
     length: u32,
     stream_identifier: u32,
+    flags: u8,
     padded: bool,
     end_stream: bool,
     pad_length: u8,
@@ -903,6 +1599,24 @@ impl DataResult {
     pub fn data<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
         o.sub_to(self.temp.0, self.temp.1)
     }
+
+    ///Returns the length of data, which excludes the padding.
+    pub fn data_length(&self) -> usize {
+        self.temp.1 - self.temp.0
+    }
+
+    ///Turns self into an owned frame, `o` is the source it was parsed from.
+    pub fn into_frame(self, o: &mut dyn GetU8) -> Option<Data> {
+        let data = copy_to(self.data(o)?.as_mut());
+        Some(Data {
+            stream_identifier: self.stream_identifier,
+            flags: self.flags,
+            padded: self.padded,
+            end_stream: self.end_stream,
+            pad_length: self.pad_length,
+            data,
+        })
+    }
 }
 
 ///Represents a parsed HEADERS frame.
@@ -911,6 +1625,7 @@ impl DataResult {
 pub struct HeadersResult {
     length: u32,
     stream_identifier: u32,
+    flags: u8,
     priority: bool,
     padded: bool,
     end_headers: bool,
@@ -929,6 +1644,29 @@ impl HeadersResult {
     pub fn field_block_fragment<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
         o.sub_to(self.temp.0, self.temp.1)
     }
+
+    ///Returns the length of field block fragment.
+    pub fn field_block_fragment_length(&self) -> usize {
+        self.temp.1 - self.temp.0
+    }
+
+    ///Turns self into an owned frame, `o` is the source it was parsed from.
+    pub fn into_frame(self, o: &mut dyn GetU8) -> Option<Headers> {
+        let field_block_fragment = copy_to(self.field_block_fragment(o)?.as_mut());
+        Some(Headers {
+            stream_identifier: self.stream_identifier,
+            flags: self.flags,
+            priority: self.priority,
+            padded: self.padded,
+            end_headers: self.end_headers,
+            end_stream: self.end_stream,
+            pad_length: self.pad_length,
+            exclusive: self.exclusive,
+            stream_dependency: self.stream_dependency,
+            weight: self.weight,
+            field_block_fragment,
+        })
+    }
 }
 
 ///Represents a parsed SETTINGS frame.
@@ -937,18 +1675,68 @@ impl HeadersResult {
 pub struct SettingsResult {
     length: u32,
     stream_identifier: u32,
+    flags: u8,
     ack: bool,
     #[getset(skip)]
     #[getset(get = "pub")]
     setting: Vec<(u16, u32)>,
 }
 
+impl SettingsResult {
+    ///Returns the last value of SETTINGS_ENABLE_CONNECT_PROTOCOL, or None if absent.
+    ///Returns an error if the value is other than 0 or 1.
+    pub fn enable_connect_protocol(&self) -> Result<Option<bool>, &'static str> {
+        self.bool_value(
+            SETTINGS_ENABLE_CONNECT_PROTOCOL,
+            "invalid SETTINGS_ENABLE_CONNECT_PROTOCOL",
+        )
+    }
+
+    ///Returns the last value of SETTINGS_NO_RFC7540_PRIORITIES, or None if absent.
+    ///Returns an error if the value is other than 0 or 1.
+    pub fn no_rfc7540_priorities(&self) -> Result<Option<bool>, &'static str> {
+        self.bool_value(
+            SETTINGS_NO_RFC7540_PRIORITIES,
+            "invalid SETTINGS_NO_RFC7540_PRIORITIES",
+        )
+    }
+
+    #[inline(always)]
+    fn bool_value(&self, identifier: u16, e: &'static str) -> Result<Option<bool>, &'static str> {
+        match self.last_value(identifier) {
+            Some(0) => Ok(Some(false)),
+            Some(1) => Ok(Some(true)),
+            Some(_) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    #[inline(always)]
+    fn last_value(&self, identifier: u16) -> Option<u32> {
+        self.setting
+            .iter()
+            .rev()
+            .find(|(a, _)| *a == identifier)
+            .map(|(_, b)| *b)
+    }
+
+    ///Turns self into an owned frame.
+    pub fn into_frame(self) -> Settings {
+        Settings {
+            ack: self.ack,
+            flags: self.flags,
+            setting: self.setting,
+        }
+    }
+}
+
 ///Represents a parsed PUSH_PROMISE frame.
 #[derive(CopyGetters, Debug, Getters, MutGetters)]
 #[getset(get_copy = "pub")]
 pub struct PushPromiseResult {
     length: u32,
     stream_identifier: u32,
+    flags: u8,
     padded: bool,
     end_headers: bool,
     pad_length: u8,
@@ -963,6 +1751,20 @@ impl PushPromiseResult {
     pub fn field_block_fragment<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
         o.sub_to(self.temp.0, self.temp.1)
     }
+
+    ///Turns self into an owned frame, `o` is the source it was parsed from.
+    pub fn into_frame(self, o: &mut dyn GetU8) -> Option<PushPromise> {
+        let field_block_fragment = copy_to(self.field_block_fragment(o)?.as_mut());
+        Some(PushPromise {
+            stream_identifier: self.stream_identifier,
+            flags: self.flags,
+            padded: self.padded,
+            end_headers: self.end_headers,
+            pad_length: self.pad_length,
+            promised_stream_id: self.promised_stream_id,
+            field_block_fragment,
+        })
+    }
 }
 
 ///Represents a parsed GOAWAY frame.
@@ -971,6 +1773,7 @@ impl PushPromiseResult {
 pub struct GoawayResult {
     length: u32,
     stream_identifier: u32,
+    flags: u8,
     last_stream_id: u32,
     error_code: u32,
     #[debug(ignore)]
@@ -979,10 +1782,26 @@ pub struct GoawayResult {
 }
 
 impl GoawayResult {
+    ///Returns the typed error code.
+    pub fn error(&self) -> ErrorCode {
+        self.error_code.into()
+    }
+
     ///Returns additional debug data.
     pub fn additional_debug_data<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
         o.sub_to(self.temp.0, self.temp.1)
     }
+
+    ///Turns self into an owned frame, `o` is the source it was parsed from.
+    pub fn into_frame(self, o: &mut dyn GetU8) -> Option<Goaway> {
+        let additional_debug_data = copy_to(self.additional_debug_data(o)?.as_mut());
+        Some(Goaway {
+            flags: self.flags,
+            last_stream_id: self.last_stream_id,
+            error_code: self.error_code,
+            additional_debug_data,
+        })
+    }
 }
 
 ///Represents a parsed CONTINUATION frame.
@@ -991,6 +1810,7 @@ impl GoawayResult {
 pub struct ContinuationResult {
     length: u32,
     stream_identifier: u32,
+    flags: u8,
     end_headers: bool,
     #[debug(ignore)]
     #[getset(skip)]
@@ -1002,11 +1822,206 @@ impl ContinuationResult {
     pub fn field_block_fragment<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
         o.sub_to(self.temp.0, self.temp.1)
     }
+
+    ///Returns the length of field block fragment.
+    pub fn field_block_fragment_length(&self) -> usize {
+        self.temp.1 - self.temp.0
+    }
+
+    ///Turns self into an owned frame, `o` is the source it was parsed from.
+    pub fn into_frame(self, o: &mut dyn GetU8) -> Option<Continuation> {
+        let field_block_fragment = copy_to(self.field_block_fragment(o)?.as_mut());
+        Some(Continuation {
+            stream_identifier: self.stream_identifier,
+            flags: self.flags,
+            end_headers: self.end_headers,
+            field_block_fragment,
+        })
+    }
+}
+
+///Represents a parsed PRIORITY_UPDATE frame.
+#[derive(CopyGetters, Debug, Getters, MutGetters)]
+#[getset(get_copy = "pub")]
+pub struct PriorityUpdateResult {
+    length: u32,
+    stream_identifier: u32,
+    flags: u8,
+    prioritized_stream_id: u32,
+    #[debug(ignore)]
+    #[getset(skip)]
+    temp: TempIndex,
+}
+
+impl PriorityUpdateResult {
+    ///Returns priority field value.
+    pub fn priority_field_value<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
+        o.sub_to(self.temp.0, self.temp.1)
+    }
+
+    ///Parses priority field value.
+    ///Returns an error if the field value can't be parsed, then the field value should be ignored.
+    pub fn priority(&self, o: &mut dyn GetU8) -> Result<PriorityParams, &'static str> {
+        let mut r = self.priority_field_value(o).ok_or(READ_BYTE_ERROR)?;
+        PriorityParams::parse(r.get_surplus())
+    }
+
+    ///Turns self into an owned frame, `o` is the source it was parsed from.
+    pub fn into_frame(self, o: &mut dyn GetU8) -> Option<PriorityUpdate> {
+        let priority_field_value = self.priority_field_value(o)?.get_surplus().to_vec();
+        Some(PriorityUpdate {
+            flags: self.flags,
+            prioritized_stream_id: self.prioritized_stream_id,
+            priority_field_value,
+        })
+    }
+}
+
+///Represents a parsed ALTSVC frame.
+#[derive(CopyGetters, Debug, Getters, MutGetters)]
+#[getset(get_copy = "pub")]
+pub struct AltSvcResult {
+    length: u32,
+    stream_identifier: u32,
+    flags: u8,
+    origin_length: u16,
+    #[getset(skip)]
+    valid: bool,
+    #[debug(ignore)]
+    #[getset(skip)]
+    temp: TempIndex,
+}
+
+impl AltSvcResult {
+    ///Returns true if the frame should be processed, otherwise it should be ignored.
+    ///An origin is required on stream 0, and is not allowed on other streams.
+    ///A frame too short for its origin is invalid.
+    pub fn is_valid(&self) -> bool {
+        self.valid && (self.stream_identifier == 0) != (self.origin_length == 0)
+    }
+
+    ///Returns origin, or None if the frame is too short for it.
+    pub fn origin<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
+        if !self.valid {
+            return None;
+        }
+        o.sub_to(self.temp.0, self.temp.0 + self.origin_length as usize)
+    }
+
+    ///Returns Alt-Svc field value, or None if the frame is too short for the origin.
+    pub fn alt_svc_field_value<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
+        if !self.valid {
+            return None;
+        }
+        o.sub_to(self.temp.0 + self.origin_length as usize, self.temp.1)
+    }
+
+    ///Parses Alt-Svc field value.
+    ///Returns an error if the field value can't be parsed, then the field value should be ignored.
+    pub fn alt_svc(&self, o: &mut dyn GetU8) -> Result<AltSvcValue, &'static str> {
+        let mut r = self.alt_svc_field_value(o).ok_or(READ_BYTE_ERROR)?;
+        AltSvcValue::parse(r.get_surplus())
+    }
+
+    ///Turns self into an owned frame, `o` is the source it was parsed from.
+    pub fn into_frame(self, o: &mut dyn GetU8) -> Option<AltSvc> {
+        let origin = self.origin(o)?.get_surplus().to_vec();
+        let alt_svc_field_value = self.alt_svc_field_value(o)?.get_surplus().to_vec();
+        Some(AltSvc {
+            stream_identifier: self.stream_identifier,
+            flags: self.flags,
+            origin,
+            alt_svc_field_value,
+        })
+    }
+}
+
+///Represents a parsed ORIGIN frame, which should be ignored if it is not valid.
+#[derive(CopyGetters, Debug, Getters, MutGetters)]
+#[getset(get_copy = "pub")]
+pub struct OriginResult {
+    length: u32,
+    stream_identifier: u32,
+    flags: u8,
+    #[getset(skip)]
+    valid: bool,
+    #[getset(skip)]
+    #[getset(get = "pub")]
+    origins: Vec<Vec<u8>>,
+}
+
+impl OriginResult {
+    ///Returns true if the frame should be processed, otherwise it should be ignored.
+    ///It must be on stream 0, and a truncated Origin-Entry makes it invalid, then no origin is returned.
+    pub fn is_valid(&self) -> bool {
+        self.valid && self.stream_identifier == 0
+    }
+
+    ///Turns self into an owned frame.
+    pub fn into_frame(self) -> Origin {
+        Origin {
+            flags: self.flags,
+            origins: self.origins,
+        }
+    }
+}
+
+///Represents a parsed frame of unknown type, which should be ignored.
+#[derive(CopyGetters, Debug)]
+#[getset(get_copy = "pub")]
+pub struct UnknownResult {
+    length: u32,
+    ty: u8,
+    flags: u8,
+    stream_identifier: u32,
+    #[debug(ignore)]
+    #[getset(skip)]
+    temp: TempIndex,
+}
+
+impl UnknownResult {
+    ///Returns frame payload.
+    pub fn payload<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
+        o.sub_to(self.temp.0, self.temp.1)
+    }
+
+    ///Turns self into an owned frame, `o` is the source it was parsed from.
+    pub fn into_frame(self, o: &mut dyn GetU8) -> Option<Unknown> {
+        let payload = copy_to(self.payload(o)?.as_mut());
+        Some(Unknown {
+            ty: self.ty,
+            flags: self.flags,
+            stream_identifier: self.stream_identifier,
+            payload,
+        })
+    }
+}
+
+///Represents a frame decoded by a registered decoder.
+#[derive(CopyGetters, Debug)]
+pub struct ExtensionResult {
+    #[getset(get_copy = "pub")]
+    header: FrameHeader,
+    #[debug(ignore)]
+    value: Box<dyn Any>,
+}
+
+impl ExtensionResult {
+    ///Returns the decoded value if it is of type T.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+
+    ///Takes the decoded value out of self.
+    pub fn into_value(self) -> Box<dyn Any> {
+        self.value
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Alternative;
 
     #[test]
     fn data() {
@@ -1035,4 +2050,335 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn settings() {
+        let mut o = Settings::new(2);
+        o.push(0x01, 0);
+        o.push_enable_connect_protocol(true);
+        let mut v = Vec::new();
+        o.export(&mut v);
+        assert_eq!(v.len(), 9 + 12);
+
+        let mut v = v.into_get();
+        match get_frame(&mut v) {
+            Ok(FrameResult::Settings(o)) => {
+                assert_eq!(o.setting().len(), 2);
+                assert_eq!(o.enable_connect_protocol(), Ok(Some(true)));
+            }
+            _ => panic!("settings"),
+        }
+    }
+
+    #[test]
+    fn settings_length() {
+        let mut o = Settings::new(3);
+        o.push(0x01, 0);
+        o.push(0x03, 100);
+        o.push(0x04, 1000);
+        let mut v = Vec::new();
+        o.export(&mut v);
+        Ping::new(false, 1).export(&mut v);
+        assert_eq!(&v[..3], &[0, 0, 18]);
+
+        let mut g = v.into_get();
+        match get_frame(&mut g) {
+            Ok(FrameResult::Settings(o)) => assert_eq!(o.setting().len(), 3),
+            _ => panic!("settings"),
+        }
+        assert!(matches!(get_frame(&mut g), Ok(FrameResult::Ping(_))));
+    }
+
+    #[test]
+    fn goaway() {
+        let mut o = Goaway::new(5);
+        o.set_last_stream_id(7);
+        o.set_error_code(ErrorCode::EnhanceYourCalm);
+        o.additional_debug_data_mut().put_exact(b"calm!");
+        let mut v = Vec::new();
+        o.export(&mut v);
+
+        let mut g = v.into_get();
+        match get_frame(&mut g) {
+            Ok(FrameResult::Goaway(o)) => {
+                assert_eq!(o.last_stream_id(), 7);
+                assert_eq!(o.error(), ErrorCode::EnhanceYourCalm);
+                let mut r = o.additional_debug_data(&mut g).unwrap();
+                assert_eq!(r.get_surplus(), b"calm!");
+            }
+            _ => panic!("goaway"),
+        }
+    }
+
+    #[test]
+    fn priority_update() {
+        let o = PriorityUpdate::new(5, &PriorityParams::new(1, true));
+        let mut v = Vec::new();
+        o.export(&mut v);
+
+        let mut v = v.into_get();
+        match get_frame(&mut v) {
+            Ok(FrameResult::PriorityUpdate(o)) => {
+                assert_eq!(o.stream_identifier(), 0);
+                assert_eq!(o.prioritized_stream_id(), 5);
+                assert_eq!(o.priority(&mut v), Ok(PriorityParams::new(1, true)));
+            }
+            _ => panic!("priority update"),
+        }
+    }
+
+    #[test]
+    fn unknown() {
+        let mut v = vec![0, 0, 3, 0xb, 0xff, 0, 0, 0, 1, b'a', b'b', b'c'];
+        Ping::new(false, 7).export(&mut v);
+
+        let mut g = v.as_slice().into_get();
+        match get_frame(&mut g) {
+            Ok(FrameResult::Unknown(o)) => {
+                assert_eq!((o.ty(), o.flags(), o.stream_identifier()), (0xb, 0xff, 1));
+                assert_eq!(o.payload(&mut g).unwrap().get_surplus(), b"abc");
+            }
+            _ => panic!("unknown"),
+        }
+        assert!(matches!(get_frame(&mut g), Ok(FrameResult::Ping(_))));
+
+        let mut e = FrameExtensions::default();
+        assert!(!e.register(PING_FRAME_TYPE, |_, _| Ok(Box::new(()))));
+        assert!(e.register(0xb, |h, o| {
+            if h.stream_identifier() == 0 {
+                return Err("invalid stream");
+            }
+            Ok(Box::new(o.len()))
+        }));
+        let mut g = v.as_slice().into_get();
+        match get_frame_with(&mut g, &e) {
+            Ok(FrameResult::Extension(o)) => {
+                assert_eq!(o.header().ty(), 0xb);
+                assert_eq!(o.downcast_ref::<usize>(), Some(&3));
+            }
+            _ => panic!("extension"),
+        }
+        assert!(matches!(
+            get_frame_with(&mut g, &e),
+            Ok(FrameResult::Ping(_))
+        ));
+    }
+
+    #[test]
+    fn parser() {
+        let mut o = Data::new(1, 100);
+        o.data_mut().put_exact(&[1; 100]);
+        let mut v = Vec::new();
+        o.export(&mut v);
+
+        let p = FrameParser::default();
+        let mut g = v[..50].into_get();
+        assert!(matches!(p.parse(&mut g), Err(FrameError::Incomplete(59))));
+        assert_eq!(g.index(), 0);
+        assert!(matches!(get_frame(&mut g), Err(("shortage", Some(_)))));
+        assert_eq!(g.index(), 0);
+        let mut g = v[..5].into_get();
+        assert!(matches!(p.parse(&mut g), Err(FrameError::Incomplete(4))));
+        let mut g = v.as_slice().into_get();
+        assert!(matches!(p.parse(&mut g), Ok(FrameResult::Data(_))));
+
+        let mut v = Vec::new();
+        Data::new(1, 20000).export(&mut v);
+        v[0..3].copy_from_slice(&[0, 0x4e, 0x20]);
+        v.resize(9 + 20000, 0);
+        let e = p.parse(&mut v.as_slice().into_get()).unwrap_err();
+        assert_eq!(e.error_code(), Some(ErrorCode::FrameSizeError));
+        let mut p = FrameParser::default();
+        p.set_max_frame_size(20000);
+        assert!(p.parse(&mut v.as_slice().into_get()).is_ok());
+
+        let mut v = Vec::new();
+        Ping::new(false, 1).export(&mut v);
+        v[8] = 1;
+        let e = p.parse(&mut v.as_slice().into_get()).unwrap_err();
+        assert_eq!(e.error_code(), Some(ErrorCode::ProtocolError));
+        let mut v = Vec::new();
+        Ping::new(false, 1).export(&mut v);
+        v[2] = 7;
+        v.pop();
+        let e = p.parse(&mut v.as_slice().into_get()).unwrap_err();
+        assert!(matches!(e, FrameError::FrameSize(_, "invalid length")));
+        let s = [0, 0, 1, DATA_FRAME_TYPE, PADDED_FLAG, 0, 0, 0, 1, 1];
+        let e = p.parse(&mut s.as_slice().into_get()).unwrap_err();
+        assert!(matches!(e, FrameError::Protocol(..)));
+        let mut v = Vec::new();
+        RstStream::new(0, ErrorCode::Cancel).export(&mut v);
+        assert!(get_frame(&mut v.into_get()).is_err());
+    }
+
+    #[test]
+    fn alt_svc() {
+        let mut s = Vec::new();
+        let value = AltSvcValue::Alternatives(vec![Alternative::new("h3", "", 443)]);
+        AltSvc::new(1, b"", &value).export(&mut s);
+        AltSvc::new(1, b"https://example.org", &value).export(&mut s);
+        let mut o = Origin::new();
+        o.push(b"https://example.org");
+        o.export(&mut s);
+
+        let mut g = s.as_slice().into_get();
+        let Ok(FrameResult::AltSvc(r)) = get_frame(&mut g) else {
+            panic!("ALTSVC");
+        };
+        let i = g.index();
+        assert!(r.is_valid());
+        assert_eq!(r.alt_svc(&mut g), Ok(value));
+        g.set_index(i);
+        let Ok(FrameResult::AltSvc(r)) = get_frame(&mut g) else {
+            panic!("ALTSVC");
+        };
+        assert!(!r.is_valid());
+        let Ok(FrameResult::Origin(r)) = get_frame(&mut g) else {
+            panic!("ORIGIN");
+        };
+        assert_eq!(r.origins(), &[b"https://example.org".to_vec()]);
+
+        assert!(r.is_valid());
+
+        let mut s = vec![0, 0, 3, ORIGIN_FRAME_TYPE, 0, 0, 0, 0, 0, 0, 2, b'a'];
+        s.extend_from_slice(&[0, 0, 2, ALTSVC_FRAME_TYPE, 0, 0, 0, 0, 0, 0, 1]);
+        s.extend_from_slice(&[0, 0, 1, ALTSVC_FRAME_TYPE, 0, 0, 0, 0, 1, 0]);
+        let mut g = s.as_slice().into_get();
+        let Ok(FrameResult::Origin(r)) = get_frame(&mut g) else {
+            panic!("ORIGIN");
+        };
+        assert!(!r.is_valid());
+        assert!(r.origins().is_empty());
+        for _ in 0..2 {
+            let Ok(FrameResult::AltSvc(r)) = get_frame(&mut g) else {
+                panic!("ALTSVC");
+            };
+            assert!(!r.is_valid());
+            assert!(r.origin(&mut g).is_none());
+        }
+        assert!(!g.is_surplus());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut s = Vec::new();
+        let mut o = Data::new(1, 4);
+        o.set_padded(true);
+        o.set_pad_length(3);
+        o.set_end_stream(true);
+        o.data_mut().put_exact(b"data");
+        o.export(&mut s);
+        let mut o = Headers::new(3, 4);
+        o.set_priority(true);
+        o.set_padded(true);
+        o.set_pad_length(2);
+        o.set_exclusive(true);
+        o.set_stream_dependency(1);
+        o.set_weight(42);
+        o.field_block_fragment_mut().put_exact(b"\x82\x86");
+        o.export(&mut s);
+        let mut o = Continuation::new(3, 1);
+        o.set_end_headers(true);
+        o.field_block_fragment_mut().put_exact(b"\x84");
+        o.export(&mut s);
+        let mut o = PushPromise::new(3, 1);
+        o.set_padded(true);
+        o.set_pad_length(1);
+        o.set_promised_stream_id(2);
+        o.field_block_fragment_mut().put_exact(b"\x82");
+        o.export(&mut s);
+        let mut o = Goaway::new(5);
+        o.set_last_stream_id(3);
+        o.set_error_code(ErrorCode::EnhanceYourCalm);
+        o.additional_debug_data_mut().put_exact(b"debug");
+        o.export(&mut s);
+        let mut o = Settings::new(1);
+        o.push(SETTINGS_MAX_FRAME_SIZE, 32768);
+        o.export(&mut s);
+        Ping::new(true, 7).export(&mut s);
+        RstStream::new(3, ErrorCode::Cancel).export(&mut s);
+        PriorityUpdate::new(3, &PriorityParams::new(2, false)).export(&mut s);
+        AltSvc::new(0, b"https://example.org", &AltSvcValue::Clear).export(&mut s);
+        let mut o = Origin::new();
+        o.push(b"https://example.org");
+        o.push(b"https://www.example.org");
+        o.export(&mut s);
+        let mut o = Unknown::new(0xfa, 9, 2);
+        o.set_flags(0xff);
+        o.payload_mut().put_exact(b"xy");
+        o.export(&mut s);
+
+        let mut g = s.as_slice().into_get();
+        let mut v = Vec::new();
+        while let Ok(r) = get_frame(&mut g) {
+            let i = g.index();
+            v.push(Frame::from_result(r, &mut g).unwrap());
+            g.set_index(i);
+        }
+        assert_eq!(v.len(), 12);
+        assert_eq!(v[1].ty(), HEADERS_FRAME_TYPE);
+        assert_eq!(v[11].stream_identifier(), 9);
+        let mut a = Vec::new();
+        for o in v {
+            o.export(&mut a);
+        }
+        assert_eq!(a, s);
+    }
+
+    #[test]
+    fn undefined_flags() {
+        let mut v: Vec<Vec<u8>> = vec![Vec::new(); 13];
+        let mut o = Data::new(1, 1);
+        o.set_end_stream(true);
+        o.data_mut().put_u8(1);
+        o.export(&mut v[0]);
+        let mut o = Headers::new(3, 1);
+        o.set_end_headers(true);
+        o.field_block_fragment_mut().put_u8(0x82);
+        o.export(&mut v[1]);
+        Priority::new(3).export(&mut v[2]);
+        RstStream::new(3, ErrorCode::Cancel).export(&mut v[3]);
+        Settings::new(0).export(&mut v[4]);
+        let mut o = PushPromise::new(3, 1);
+        o.set_promised_stream_id(2);
+        o.field_block_fragment_mut().put_u8(0x82);
+        o.export(&mut v[5]);
+        Ping::new(false, 7).export(&mut v[6]);
+        Goaway::new(0).export(&mut v[7]);
+        WindowUpdate::new(0, 1).export(&mut v[8]);
+        Continuation::new(3, 0).export(&mut v[9]);
+        PriorityUpdate::new(3, &PriorityParams::default()).export(&mut v[10]);
+        AltSvc::new(1, b"", &AltSvcValue::Clear).export(&mut v[11]);
+        Origin::new().export(&mut v[12]);
+        let mut s = Vec::new();
+        for mut o in v {
+            o[4] |= 0b1101_0010;
+            s.extend_from_slice(&o);
+        }
+
+        let mut g = s.as_slice().into_get();
+        let mut v = Vec::new();
+        while let Ok(r) = get_frame(&mut g) {
+            let i = g.index();
+            v.push(Frame::from_result(r, &mut g).unwrap());
+            g.set_index(i);
+        }
+        assert_eq!(v.len(), 13);
+        let mut a = Vec::new();
+        for o in v {
+            o.export(&mut a);
+        }
+        assert_eq!(a, s);
+
+        let mut g = s.as_slice().into_get();
+        let Ok(FrameResult::Data(r)) = get_frame(&mut g) else {
+            panic!("data");
+        };
+        assert_eq!(r.flags(), 0b1101_0011);
+        let mut o = r.into_frame(&mut g).unwrap();
+        o.set_end_stream(false);
+        let mut a = Vec::new();
+        o.export(&mut a);
+        assert_eq!(a[4], 0b1101_0010);
+    }
 }
@@ -0,0 +1,265 @@
+use super::super::error::ErrorCode;
+use super::super::settings::DEFAULT_HEADER_TABLE_SIZE;
+use super::*;
+use crate::prty::*;
+use crate::table::*;
+use crate::validate::*;
+use derive_more::Debug;
+
+///Represents an error of decoding a field block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HpackDecodeError {
+    ///The field block can't be decoded, the compression context is broken. It is a connection error of COMPRESSION_ERROR.
+    Compression(&'static str),
+    ///The header list exceeds SETTINGS_MAX_HEADER_LIST_SIZE. The field block is decoded, the compression context is kept.
+    HeaderListTooLarge(usize),
+    ///The message is malformed. The field block is decoded, the compression context is kept.
+    Malformed(MalformedError),
+}
+
+impl HpackDecodeError {
+    ///Returns true if it is a connection error.
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, Self::Compression(_))
+    }
+
+    ///Returns the error code.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::Compression(_) => ErrorCode::CompressionError,
+            Self::HeaderListTooLarge(_) | Self::Malformed(_) => ErrorCode::ProtocolError,
+        }
+    }
+}
+
+impl std::fmt::Display for HpackDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Compression(s) => write!(f, "COMPRESSION_ERROR: {s}"),
+            Self::HeaderListTooLarge(n) => write!(f, "header list too large: {n}"),
+            Self::Malformed(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for HpackDecodeError {}
+
+impl From<MalformedError> for HpackDecodeError {
+    fn from(o: MalformedError) -> Self {
+        Self::Malformed(o)
+    }
+}
+
+///A stateful HPACK decoder.
+///
+///A dynamic table size update is accepted only at the beginning of a field block, and must not exceed the limit,
+///which is self's SETTINGS_HEADER_TABLE_SIZE. After the limit is reduced below the size in use,
+///the next field block must start with a dynamic table size update.
+///An invalid index is a COMPRESSION_ERROR. The header list size is accounted against SETTINGS_MAX_HEADER_LIST_SIZE.
+#[derive(Debug, CopyGetters)]
+pub struct HpackDecoder {
+    #[getset(get_copy = "pub")]
+    max_table_size: usize,
+    #[getset(get_copy = "pub")]
+    max_header_list_size: Option<usize>,
+    table: FieldTable,
+    update_required: bool,
+}
+
+impl Default for HpackDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_HEADER_TABLE_SIZE as usize)
+    }
+}
+
+impl HpackDecoder {
+    ///Creates with the limit of the dynamic table size.
+    pub fn new(max_table_size: usize) -> Self {
+        let mut o = Self {
+            max_table_size: DEFAULT_HEADER_TABLE_SIZE as usize,
+            max_header_list_size: None,
+            table: FieldTable::new(DEFAULT_HEADER_TABLE_SIZE as usize),
+            update_required: false,
+        };
+        o.set_max_table_size(max_table_size);
+        o
+    }
+
+    ///Sets the limit of the dynamic table size, which is self's SETTINGS_HEADER_TABLE_SIZE acknowledged by the peer.
+    pub fn set_max_table_size(&mut self, n: usize) {
+        self.max_table_size = n;
+        if self.table.capacity() > n {
+            self.update_required = true;
+        }
+    }
+
+    ///Sets the limit of the header list size, None means unlimited.
+    pub fn set_max_header_list_size(&mut self, n: Option<usize>) {
+        self.max_header_list_size = n;
+    }
+
+    ///Returns the dynamic table.
+    pub fn table(&self) -> &FieldTable {
+        &self.table
+    }
+
+    ///Decodes a field block into fields.
+    pub fn decode(
+        &mut self,
+        block: &[u8],
+    ) -> Result<Vec<(FieldName, FieldValue)>, HpackDecodeError> {
+        let mut g = block.into_get();
+        let mut v = Vec::new();
+        let mut list_size = 0;
+        let mut start = true;
+        while g.is_surplus() {
+            let r = get_one_hfri(&mut g).map_err(HpackDecodeError::Compression)?;
+            if let HfriResult::DynamicTableSizeUpdate(n) = r {
+                if !start {
+                    return Err(HpackDecodeError::Compression(
+                        "dynamic table size update after field",
+                    ));
+                }
+                if n > self.max_table_size {
+                    return Err(HpackDecodeError::Compression(
+                        "dynamic table size update exceeds limit",
+                    ));
+                }
+                self.table.set_capacity(n);
+                self.update_required = false;
+                continue;
+            }
+            if std::mem::take(&mut start) && self.update_required {
+                return Err(HpackDecodeError::Compression(
+                    "dynamic table size update required",
+                ));
+            }
+            let (name, value) = self.field(r)?;
+            list_size += name.len() + value.len() + 32;
+            v.push((name, value));
+        }
+        if start && self.update_required {
+            return Err(HpackDecodeError::Compression(
+                "dynamic table size update required",
+            ));
+        }
+        match self.max_header_list_size {
+            Some(n) if list_size > n => Err(HpackDecodeError::HeaderListTooLarge(list_size)),
+            _ => Ok(v),
+        }
+    }
+
+    ///Decodes a field block into a request, which is validated.
+    pub fn decode_request(&mut self, block: &[u8]) -> Result<H2Request, HpackDecodeError> {
+        let v = self.decode(block)?;
+        let mut r = H2Request::default();
+        try_add_fields_to_request(v, &mut r)?;
+        Ok(r)
+    }
+
+    ///Decodes a field block into a response, which is validated.
+    pub fn decode_response(&mut self, block: &[u8]) -> Result<H2Response, HpackDecodeError> {
+        let v = self.decode(block)?;
+        let mut r = H2Response::default();
+        try_add_fields_to_response(v, &mut r)?;
+        Ok(r)
+    }
+
+    fn field(&mut self, r: HfriResult) -> Result<(FieldName, FieldValue), HpackDecodeError> {
+        let r = match r {
+            HfriResult::Indexed(n) => self.entry(n)?,
+            HfriResult::IncrementalIndexingIndexedName(n, value) => {
+                let name = self.name(n)?;
+                self.table.insert(name.clone(), value.clone());
+                (name, value)
+            }
+            HfriResult::IncrementalIndexingNewName(name, value) => {
+                self.table.insert(name.clone(), value.clone());
+                (name, value)
+            }
+            HfriResult::WithoutIndexingIndexedName(n, value)
+            | HfriResult::NeverIndexedIndexedName(n, value) => (self.name(n)?, value),
+            HfriResult::WithoutIndexingNewName(name, value)
+            | HfriResult::NeverIndexedNewName(name, value) => (name, value),
+            HfriResult::DynamicTableSizeUpdate(_) => unreachable!(),
+        };
+        Ok(r)
+    }
+
+    #[inline(always)]
+    fn entry(&self, n: usize) -> Result<(FieldName, FieldValue), HpackDecodeError> {
+        match static_table_get_entry(n) {
+            Ok(o) => Ok(o.clone()),
+            Err(e) => e
+                .and_then(|n| self.table.get(n))
+                .cloned()
+                .ok_or(HpackDecodeError::Compression("invalid index")),
+        }
+    }
+
+    #[inline(always)]
+    fn name(&self, n: usize) -> Result<FieldName, HpackDecodeError> {
+        match static_table_get_name(n) {
+            Ok(o) => Ok(o.clone()),
+            Err(e) => e
+                .and_then(|n| self.table.get(n))
+                .map(|(o, _)| o.clone())
+                .ok_or(HpackDecodeError::Compression("invalid index")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let mut d = HpackDecoder::default();
+        let a: &[u8] = &[
+            0x82, 0x86, 0x84, 0x41, 0x0f, 0x77, 0x77, 0x77, 0x2e, 0x65, 0x78, 0x61, 0x6d, 0x70,
+            0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d,
+        ];
+        let r = d.decode_request(a).unwrap();
+        assert_eq!(
+            r.authority().as_ref().unwrap().as_bytes(),
+            b"www.example.com"
+        );
+        assert_eq!(d.table().size(), 57);
+        let a: &[u8] = &[
+            0x82, 0x86, 0x84, 0xbe, 0x58, 0x08, 0x6e, 0x6f, 0x2d, 0x63, 0x61, 0x63, 0x68, 0x65,
+        ];
+        d.decode_request(a).unwrap();
+        assert_eq!(d.table().size(), 110);
+
+        assert!(d.decode(&[0xc0]).unwrap_err().is_connection_error());
+        assert!(d.decode(&[0x80]).is_err());
+        assert_eq!(
+            d.decode(&[0x82, 0x20]).unwrap_err(),
+            HpackDecodeError::Compression("dynamic table size update after field")
+        );
+        assert!(d.decode(&[0x3f, 0xe2, 0x1f]).is_err());
+
+        d.set_max_header_list_size(Some(60));
+        let e = d.decode(&[0x82, 0xbe]).unwrap_err();
+        assert_eq!(e, HpackDecodeError::HeaderListTooLarge(42 + 53));
+        assert!(!e.is_connection_error());
+        assert!(matches!(
+            d.decode_request(&[0x82]),
+            Err(HpackDecodeError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn table_size() {
+        let mut d = HpackDecoder::default();
+        d.decode(&[0x40, 0x01, b'a', 0x01, b'b']).unwrap();
+        assert_eq!(d.table().len(), 1);
+        d.set_max_table_size(0);
+        assert!(d.decode(&[0x82]).is_err());
+        assert!(d.decode(&[0x21, 0x82]).is_err());
+        d.decode(&[0x20, 0x82]).unwrap();
+        assert!(d.table().is_empty());
+        d.decode(&[0x82]).unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+use super::super::huffman::encoded_len;
+use super::super::settings::DEFAULT_HEADER_TABLE_SIZE;
+use super::*;
+use crate::prty::*;
+use crate::table::*;
+use derive_more::Debug;
+use std::collections::HashSet;
+
+///The names of fields never indexed by default, which are sensitive.
+pub const DEFAULT_SENSITIVE_NAMES: [&[u8]; 3] = [b"authorization", b"cookie", b"set-cookie"];
+
+///A stateful HPACK encoder.
+///
+///Looks up full and name-only matches in both the static table and the dynamic table, never indexes sensitive fields,
+///and chooses Huffman encoding only when it is shorter.
+///The size of the dynamic table is limited by the peer's SETTINGS_HEADER_TABLE_SIZE and self's budget,
+///changes are signaled by dynamic table size updates at the beginning of the next field block.
+#[derive(Debug, CopyGetters)]
+pub struct HpackEncoder {
+    #[getset(get_copy = "pub")]
+    max_table_size: usize,
+    #[getset(get_copy = "pub")]
+    header_table_size: usize,
+    table: FieldTable,
+    pending: Option<(usize, usize)>,
+    sensitive: HashSet<Vec<u8>>,
+    #[getset(get_copy = "pub")]
+    huffman: bool,
+}
+
+impl Default for HpackEncoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_HEADER_TABLE_SIZE as usize)
+    }
+}
+
+impl HpackEncoder {
+    ///Creates with the budget of the dynamic table size.
+    pub fn new(max_table_size: usize) -> Self {
+        let mut o = Self {
+            max_table_size,
+            header_table_size: DEFAULT_HEADER_TABLE_SIZE as usize,
+            table: FieldTable::new(DEFAULT_HEADER_TABLE_SIZE as usize),
+            pending: None,
+            sensitive: DEFAULT_SENSITIVE_NAMES.iter().map(|o| o.to_vec()).collect(),
+            huffman: true,
+        };
+        o.update_capacity();
+        o
+    }
+
+    ///Sets the peer's SETTINGS_HEADER_TABLE_SIZE, which is the maximum size of the dynamic table.
+    pub fn set_header_table_size(&mut self, n: usize) {
+        self.header_table_size = n;
+        self.update_capacity();
+    }
+
+    ///Sets the budget of the dynamic table size.
+    pub fn set_max_table_size(&mut self, n: usize) {
+        self.max_table_size = n;
+        self.update_capacity();
+    }
+
+    ///Sets whether or not Huffman encoding is allowed.
+    pub fn set_huffman(&mut self, o: bool) {
+        self.huffman = o;
+    }
+
+    ///Adds a sensitive field name, which is never indexed.
+    pub fn add_sensitive(&mut self, name: &[u8]) {
+        self.sensitive.insert(name.to_ascii_lowercase());
+    }
+
+    ///Removes a sensitive field name.
+    pub fn remove_sensitive(&mut self, name: &[u8]) {
+        self.sensitive.remove(&name.to_ascii_lowercase());
+    }
+
+    ///Returns true if the field name is sensitive, the name is case-insensitive.
+    pub fn is_sensitive(&self, name: &[u8]) -> bool {
+        if name.iter().any(u8::is_ascii_uppercase) {
+            self.sensitive.contains(&name.to_ascii_lowercase())
+        } else {
+            self.sensitive.contains(name)
+        }
+    }
+
+    ///Returns the dynamic table.
+    pub fn table(&self) -> &FieldTable {
+        &self.table
+    }
+
+    ///Encodes some fields into a field block, starts with the pending dynamic table size updates.
+    pub fn encode<N: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        v: impl IntoIterator<Item = (N, V)>,
+        o: &mut dyn PutU8,
+    ) {
+        if let Some((a, b)) = self.pending.take() {
+            if a < b {
+                build_dynamic_table_size_update(a, o);
+            }
+            build_dynamic_table_size_update(b, o);
+        }
+        for (name, value) in v {
+            self.encode_field(name.as_ref(), value.as_ref(), o);
+        }
+    }
+
+    ///Encodes the pseudo-header fields and the fields of a request into a field block.
+    pub fn encode_request_fields(&mut self, r: &H2Request, o: &mut dyn PutU8) {
+        let mut v = vec![(PSEUDO_METHOD, r.method().as_bytes())];
+        let pseudo = [
+            (PSEUDO_SCHEME, r.scheme()),
+            (PSEUDO_AUTHORITY, r.authority()),
+            (PSEUDO_PATH, r.path()),
+            (PSEUDO_PROTOCOL, r.protocol()),
+        ];
+        for (a, b) in pseudo {
+            if let Some(b) = b {
+                v.push((a, b.as_bytes()));
+            }
+        }
+        push_fields(r, &mut v);
+        self.encode(v, o);
+    }
+
+    ///Encodes the pseudo-header fields and the fields of a response into a field block.
+    pub fn encode_response_fields(&mut self, r: &H2Response, o: &mut dyn PutU8) {
+        let mut v = vec![(PSEUDO_STATUS, r.status().as_bytes())];
+        push_fields(r, &mut v);
+        self.encode(v, o);
+    }
+
+    ///Encodes the fields, e.g. a trailer section, into a field block.
+    pub fn encode_entity_fields(&mut self, r: &Entity, o: &mut dyn PutU8) {
+        let mut v = Vec::new();
+        push_fields(r, &mut v);
+        self.encode(v, o);
+    }
+
+    ///Encodes the trailer section of a message into a field block.
+    pub fn encode_trailer_fields(&mut self, r: &Entity, o: &mut dyn PutU8) {
+        let mut v = Vec::new();
+        push_fields(r.trailers(), &mut v);
+        self.encode(v, o);
+    }
+
+    fn encode_field(&mut self, name: &[u8], value: &[u8], o: &mut dyn PutU8) {
+        let never = self.is_sensitive(name);
+        if !never {
+            if let Some(n) = static_table_get_entry_index(name, value) {
+                return build_indexed(n, o);
+            }
+            if let Some(n) = self.table.find(name, value) {
+                return build_indexed(STATIC_TABLE_LEN + n, o);
+            }
+        }
+        let n = static_table_get_name_index(name)
+            .or_else(|| self.table.find_name(name).map(|n| STATIC_TABLE_LEN + n));
+        let v = self.literal(value);
+        if never {
+            match n {
+                Some(n) => build_never_indexed_indexed_name(n, v, o),
+                None => build_never_indexed_new_name(self.literal(name), v, o),
+            }
+        } else if entry_size(name, value) <= self.table.capacity() {
+            match n {
+                Some(n) => build_incremental_indexing_indexed_name(n, v, o),
+                None => build_incremental_indexing_new_name(self.literal(name), v, o),
+            }
+            self.table
+                .insert(name.to_vec().into(), value.to_vec().into());
+        } else {
+            match n {
+                Some(n) => build_without_indexing_indexed_name(n, v, o),
+                None => build_without_indexing_new_name(self.literal(name), v, o),
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn literal<'a>(&self, s: &'a [u8]) -> OctetsRef<'a> {
+        OctetsRef::new(s, self.huffman && encoded_len(s) < s.len())
+    }
+
+    fn update_capacity(&mut self) {
+        let n = self.header_table_size.min(self.max_table_size);
+        if n == self.table.capacity() && self.pending.is_none() {
+            return;
+        }
+        self.table.set_capacity(n);
+        self.pending = Some(match self.pending {
+            Some((a, _)) => (a.min(n), n),
+            None => (n, n),
+        });
+    }
+}
+
+#[inline(always)]
+fn push_fields<'a>(r: &'a Fields, v: &mut Vec<(&'a [u8], &'a [u8])>) {
+    for (a, b) in r.iter() {
+        v.push((a.as_bytes(), b.one().as_bytes()));
+        for o in b.rest() {
+            v.push((a.as_bytes(), o.as_bytes()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(s: &[u8], t: &mut DynamicTable) -> Vec<(FieldName, FieldValue)> {
+        let v = get_hfris_to_vec(&mut s.into_get()).unwrap();
+        update_dynamic_table_to_vec(v, t)
+    }
+
+    #[test]
+    fn encode() {
+        let mut e = HpackEncoder::default();
+        let mut t = DynamicTable::default();
+        let v: [(&[u8], &[u8]); 4] = [
+            (b":method", b"GET"),
+            (b":authority", b"www.example.com"),
+            (b"cookie", b"a=b"),
+            (b"x-custom", b"value"),
+        ];
+        let mut s = Vec::new();
+        e.encode(v, &mut s);
+        assert_eq!(s[0], 0x82);
+        assert_eq!(e.table().len(), 2);
+        assert_eq!(decode(&s, &mut t).len(), 4);
+        assert_eq!(t.size(), e.table().size());
+
+        let mut s = Vec::new();
+        e.encode(v, &mut s);
+        assert_eq!(s[..2], [0x82, 0xbf]);
+        assert_eq!(s[2..4], [0x1f, 0x11]);
+        assert_eq!(*s.last().unwrap(), 0xbe);
+        let r = decode(&s, &mut t);
+        assert_eq!(r[1].1.as_bytes(), b"www.example.com");
+        assert_eq!(r[3].1.as_bytes(), b"value");
+    }
+
+    #[test]
+    fn sensitive() {
+        let mut e = HpackEncoder::default();
+        e.add_sensitive(b"X-Token");
+        assert!(e.is_sensitive(b"x-token"));
+        assert!(e.is_sensitive(b"X-TOKEN"));
+        assert!(e.is_sensitive(b"Authorization"));
+        e.remove_sensitive(b"x-TOKEN");
+        assert!(!e.is_sensitive(b"X-Token"));
+    }
+
+    #[test]
+    fn table_size() {
+        let mut e = HpackEncoder::new(100);
+        let mut t = DynamicTable::default();
+        let mut s = Vec::new();
+        e.encode([(b"x-a", b"1")], &mut s);
+        assert_eq!(s[..2], [0x3f, 0x45]);
+        decode(&s, &mut t);
+        assert_eq!(t.capacity(), 100);
+
+        e.set_header_table_size(0);
+        e.set_header_table_size(50);
+        assert!(e.table().is_empty());
+        let mut s = Vec::new();
+        e.encode(
+            [(b"x-b", b"0123456789012345678901234567890123456789")],
+            &mut s,
+        );
+        assert_eq!(s[..2], [0x20, 0x3f]);
+        decode(&s, &mut t);
+        assert_eq!(t.capacity(), 50);
+        assert!(e.table().is_empty());
+        assert_eq!(t.size(), 0);
+
+        let mut s = Vec::new();
+        e.set_huffman(false);
+        e.encode([(b"x-c", b"aaaa")], &mut s);
+        assert_eq!(&s[1..], b"\x03x-c\x04aaaa");
+    }
+}
@@ -81,7 +81,7 @@ static VALUE_MAP: LazyLock<HashMap<&[u8], &FieldValue>> = LazyLock::new(|| {
     v
 });
 
-const STATIC_TABLE_LEN: usize = 61;
+pub(crate) const STATIC_TABLE_LEN: usize = 61;
 const STATIC_TABLE: [(&[u8], &[u8]); STATIC_TABLE_LEN] = [
     (b":authority", b""),
     (b":method", b"GET"),
@@ -12,14 +12,22 @@ Field section decompression is the process of decoding a field block into a set
 The get_* functions can be used to parse a field block and the dynamic table size update instruction.
 The [`HfriResult`] represent different parsing result.
 
+[`HpackDecoder`] is a stateful decoder, which maintains the dynamic table with the error semantics of RFC 7541.
+
+[`HpackEncoder`] is a stateful encoder, which chooses the representations and maintains the dynamic table.
+
 # Index
 This module provides static_table_* functions for working with static table.
 */
 
+mod decoder;
+mod encoder;
 mod index;
 
 use super::prty::*;
 use super::*;
+pub use decoder::*;
+pub use encoder::*;
 pub use index::*;
 
 ///Builds field representation.
@@ -1,11 +1,45 @@
+/*!
+A module for the Huffman code used by HPACK and QPACK string literals.
+
+[`encoded_len`] returns the length after encoded, which is used to choose Huffman only when it is shorter.
+[`decode_huffman`] and [`HuffmanDecoder`] treat an EOS symbol, padding longer than 7 bits,
+or padding not corresponding to the most significant bits of EOS as a decoding error.
+
+# Examples
+```
+use httpenergy::h2::huffman::*;
+
+let s = b"www.example.com";
+let mut v = Vec::new();
+encode_huffman(s, &mut v);
+assert_eq!(v.len(), encoded_len(s));
+assert_eq!(v, [0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff]);
+
+let mut r = Vec::new();
+decode_huffman(&v, &mut r).unwrap();
+assert_eq!(r, s);
+
+let mut d = HuffmanDecoder::new();
+let mut r = Vec::new();
+for o in v.chunks(5) {
+    d.decode(o, &mut r).unwrap();
+}
+d.finish().unwrap();
+assert_eq!(r, s);
+assert!(decode_huffman(&[0xff, 0xff, 0xff, 0xff], &mut r).is_err());
+```
+*/
+
 mod assist;
 
 use super::*;
 use assist::*;
+use derive_more::Debug;
 use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
 
 ///Encodes a slice into huffman encoded.
-pub(crate) fn encode_huffman(s: &[u8], o: &mut dyn PutU8) {
+pub fn encode_huffman(s: &[u8], o: &mut dyn PutU8) {
     let mut buffer: u64 = 0;
     let mut count = 40;
     for &i in s {
@@ -26,32 +60,92 @@ pub(crate) fn encode_huffman(s: &[u8], o: &mut dyn PutU8) {
     }
 }
 
+///Returns the length of a slice after huffman encoded.
+pub fn encoded_len(s: &[u8]) -> usize {
+    let n: usize = s.iter().map(|&i| HUFFMAN_CODE[i as usize].1 as usize).sum();
+    n.div_ceil(8)
+}
+
 ///Decodes a huffman encoded slice.
-pub(crate) fn decode_huffman(s: &[u8], o: &mut dyn PutU8) {
-    let mut x = 0;
-    for &i in s {
-        let a = DECODE_STATE_ARRAY[x as usize];
-        let y = (i >> 4) as usize;
-        x = a[y].0;
-        let n = a[y].1;
-        if n >= 0 && n < 256 {
-            o.put_u8(n as u8);
-        } else if n == 256 {
-            return;
+///Returns an error if it contains EOS, or the padding is invalid.
+pub fn decode_huffman(s: &[u8], o: &mut dyn PutU8) -> Result<(), &'static str> {
+    let mut d = HuffmanDecoder::new();
+    d.decode(s, o)?;
+    d.finish()
+}
+
+///A streaming huffman decoder, which decodes a string literal given in several slices.
+#[derive(Debug, Default)]
+pub struct HuffmanDecoder {
+    state: u8,
+}
+
+impl HuffmanDecoder {
+    ///Creates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Decodes a part of a huffman encoded string literal.
+    ///Returns an error if it contains EOS, then self should not be used.
+    pub fn decode(&mut self, s: &[u8], o: &mut dyn PutU8) -> Result<(), &'static str> {
+        for &i in s {
+            self.step(i >> 4, o)?;
+            self.step(i & 0x0f, o)?;
         }
+        Ok(())
+    }
+
+    ///Ends the string literal, and resets self. Returns an error if the padding is invalid.
+    pub fn finish(&mut self) -> Result<(), &'static str> {
+        let (n, ones) = STATE_PADDING[std::mem::take(&mut self.state) as usize];
+        if n > 7 {
+            Err("huffman padding longer than 7 bits")
+        } else if !ones {
+            Err("huffman padding not all ones")
+        } else {
+            Ok(())
+        }
+    }
 
-        let a = DECODE_STATE_ARRAY[x as usize];
-        let y = (i & 0x0f) as usize;
-        x = a[y].0;
-        let n = a[y].1;
-        if n >= 0 && n < 256 {
-            o.put_u8(n as u8);
-        } else if n == 256 {
-            return;
+    #[inline(always)]
+    fn step(&mut self, y: u8, o: &mut dyn PutU8) -> Result<(), &'static str> {
+        let (x, n) = DECODE_STATE_ARRAY[self.state as usize][y as usize];
+        self.state = x;
+        match n {
+            0..256 => {
+                o.put_u8(n as u8);
+            }
+            256 => return Err("huffman EOS symbol"),
+            _ => {}
         }
+        Ok(())
     }
 }
 
+///The number of bits pending in each decode state, and whether they are all ones.
+static STATE_PADDING: LazyLock<Vec<(u32, bool)>> = LazyLock::new(|| {
+    let mut r = vec![(u32::MAX, false); DECODE_STATE_ARRAY.len()];
+    r[0] = (0, true);
+    let mut v = vec![(0, 0u64, 0)];
+    while let Some((x, bits, len)) = v.pop() {
+        for (y, &(next, n)) in DECODE_STATE_ARRAY[x].iter().enumerate() {
+            let mut b = (bits << 4) | y as u64;
+            let mut m = len + 4;
+            if n >= 0 {
+                m -= HUFFMAN_CODE[n as usize].1 as u32;
+                b &= (1 << m) - 1;
+            }
+            let next = next as usize;
+            if r[next].0 == u32::MAX {
+                r[next] = (m, b == (1 << m) - 1);
+                v.push((next, b, m));
+            }
+        }
+    }
+    r
+});
+
 const NONE: &str = "_";
 const SUFFIX: [&str; 16] = [
     "0000", "0001", "0010", "0011", "0100", "0101", "0110", "0111", "1000", "1001", "1010", "1011",
@@ -193,6 +287,24 @@ fn to_state_array(
 mod tests {
     use super::*;
 
+    #[test]
+    fn decode() {
+        let mut v = Vec::new();
+        assert_eq!(decode_huffman(&[0x1f], &mut v), Ok(()));
+        assert_eq!(v, b"a");
+        assert!(decode_huffman(&[0x18], &mut v).is_err());
+        assert!(decode_huffman(&[0x1f, 0xff], &mut v).is_err());
+        assert!(decode_huffman(&[0xff], &mut v).is_err());
+
+        let s: Vec<u8> = (0..=255).collect();
+        let mut v = Vec::new();
+        encode_huffman(&s, &mut v);
+        assert_eq!(v.len(), encoded_len(&s));
+        let mut r = Vec::new();
+        decode_huffman(&v, &mut r).unwrap();
+        assert_eq!(r, s);
+    }
+
     #[test]
     fn build() {
         let v = build_decode_state_array();
@@ -0,0 +1,153 @@
+/*!
+A module for PING keepalive and round-trip time.
+
+[`PingTracker`] creates PING frames with unique opaque data, matches acknowledgements to them, and measures the round-trip time.
+A peer is unresponsive if a PING is not acknowledged within the timeout.
+PING frames are sent when the connection is idle for the interval, which keeps the connection alive through middleboxes.
+
+# Examples
+```
+use httpenergy::h2::keepalive::*;
+use std::time::{Duration, Instant};
+
+let mut t = PingTracker::default();
+let now = Instant::now();
+assert!(t.should_ping(now));
+let o = t.ping(now);
+assert!(!t.should_ping(now));
+
+let later = now + Duration::from_millis(30);
+assert_eq!(t.on_ack(o.opaque_data(), later), Some(Duration::from_millis(30)));
+assert_eq!(t.smoothed_rtt(), Some(Duration::from_millis(30)));
+assert!(!t.is_unresponsive(later));
+```
+*/
+
+use super::frame::*;
+use crate::prty::*;
+use derive_more::Debug;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+///The default idle time after which a PING is sent.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+///The default time in which a PING must be acknowledged.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(20);
+
+///Tracks PING frames sent, and measures the round-trip time.
+#[derive(Debug, CopyGetters, Setters)]
+pub struct PingTracker {
+    #[getset(get_copy = "pub", set = "pub")]
+    interval: Duration,
+    #[getset(get_copy = "pub", set = "pub")]
+    timeout: Duration,
+    next_opaque_data: u64,
+    outstanding: VecDeque<(u64, Instant)>,
+    last_activity: Option<Instant>,
+    #[getset(get_copy = "pub")]
+    latest_rtt: Option<Duration>,
+    #[getset(get_copy = "pub")]
+    smoothed_rtt: Option<Duration>,
+    #[getset(get_copy = "pub")]
+    min_rtt: Option<Duration>,
+}
+
+impl Default for PingTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTERVAL, DEFAULT_TIMEOUT)
+    }
+}
+
+impl PingTracker {
+    ///Creates.
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        Self {
+            interval,
+            timeout,
+            next_opaque_data: 1,
+            outstanding: VecDeque::new(),
+            last_activity: None,
+            latest_rtt: None,
+            smoothed_rtt: None,
+            min_rtt: None,
+        }
+    }
+
+    ///Returns the number of PING frames not acknowledged.
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    ///Creates a PING frame sent at `now`, its opaque data is unique.
+    pub fn ping(&mut self, now: Instant) -> Ping {
+        let opaque_data = self.next_opaque_data;
+        self.next_opaque_data = self.next_opaque_data.wrapping_add(1);
+        self.outstanding.push_back((opaque_data, now));
+        self.last_activity = Some(now);
+        Ping::new(false, opaque_data)
+    }
+
+    ///Handles a PING acknowledged at `now`. Returns the round-trip time, or None if the opaque data is unknown.
+    pub fn on_ack(&mut self, opaque_data: u64, now: Instant) -> Option<Duration> {
+        let i = self
+            .outstanding
+            .iter()
+            .position(|(a, _)| *a == opaque_data)?;
+        let (_, sent) = self.outstanding.remove(i)?;
+        let rtt = now.saturating_duration_since(sent);
+        self.latest_rtt = Some(rtt);
+        self.min_rtt = Some(self.min_rtt.map_or(rtt, |o| o.min(rtt)));
+        self.smoothed_rtt = Some(self.smoothed_rtt.map_or(rtt, |o| (o * 7 + rtt) / 8));
+        self.last_activity = Some(now);
+        Some(rtt)
+    }
+
+    ///Reports a frame is received from the peer at `now`, which defers the next keepalive PING.
+    pub fn on_receive(&mut self, now: Instant) {
+        self.last_activity = Some(now);
+    }
+
+    ///Returns true if a keepalive PING should be sent, no PING is outstanding and the connection is idle for the interval.
+    pub fn should_ping(&self, now: Instant) -> bool {
+        self.outstanding.is_empty()
+            && self
+                .last_activity
+                .is_none_or(|o| now.saturating_duration_since(o) >= self.interval)
+    }
+
+    ///Returns true if a PING is not acknowledged within the timeout, then the connection should be closed.
+    pub fn is_unresponsive(&self, now: Instant) -> bool {
+        self.outstanding
+            .front()
+            .is_some_and(|(_, o)| now.saturating_duration_since(*o) >= self.timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ping() {
+        let ms = Duration::from_millis;
+        let mut t = PingTracker::new(ms(100), ms(50));
+        let now = Instant::now();
+        let a = t.ping(now).opaque_data();
+        let b = t.ping(now + ms(10)).opaque_data();
+        assert_ne!(a, b);
+        assert_eq!(t.outstanding(), 2);
+        assert_eq!(t.on_ack(b, now + ms(20)), Some(ms(10)));
+        assert_eq!(t.on_ack(b, now + ms(20)), None);
+        assert!(!t.is_unresponsive(now + ms(49)));
+        assert!(t.is_unresponsive(now + ms(50)));
+        assert_eq!(t.on_ack(a, now + ms(90)), Some(ms(90)));
+        assert_eq!(t.min_rtt(), Some(ms(10)));
+        assert_eq!(t.smoothed_rtt(), Some((ms(10) * 7 + ms(90)) / 8));
+        assert!(!t.is_unresponsive(now + ms(1000)));
+
+        assert!(!t.should_ping(now + ms(150)));
+        t.on_receive(now + ms(150));
+        assert!(!t.should_ping(now + ms(200)));
+        assert!(t.should_ping(now + ms(250)));
+    }
+}
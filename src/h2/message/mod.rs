@@ -0,0 +1,677 @@
+/*!
+A module for converting messages to and from frames.
+
+[`encode_request`] and [`encode_response`] emit a HEADERS frame, followed by CONTINUATION frames if the field block
+doesn't fit in a frame, then DATA frames carrying the body, and a HEADERS frame carrying the trailer section if any.
+END_HEADERS and END_STREAM are set on the right frames.
+
+[`encode_push_promise`] promises a request of server push, which is checked by [`check_push_request`].
+
+[`MessageDecoder`] accumulates the frames on each stream into a complete message, including the body and the trailer section.
+
+# Examples
+```
+use httpenergy::h2::frame::*;
+use httpenergy::h2::hpack::*;
+use httpenergy::h2::message::*;
+use httpenergy::h2::*;
+use httpenergy::*;
+
+let mut r = H2Request::new("POST");
+r.set_scheme(Some("https"));
+r.set_authority(Some("example.org"));
+r.set_path(Some("/upload"));
+r.body_mut().put_exact(b"hello");
+r.trailers_mut().add_field("checksum", "5d41402a");
+
+let mut encoder = HpackEncoder::default();
+let mut s = Vec::new();
+encode_request(1, &r, &mut encoder, 16384, &mut s);
+
+let mut decoder = HpackDecoder::default();
+let mut m = RequestDecoder::default();
+let mut g = s.as_slice().into_get();
+let mut done = None;
+while let Ok(f) = get_frame(&mut g) {
+    if let Some(o) = m.push(f, &mut g, &mut decoder).unwrap() {
+        done = Some(o);
+    }
+}
+let (stream_id, req) = done.unwrap();
+assert_eq!(stream_id, 1);
+assert_eq!(req.body(), b"hello");
+assert!(req.trailers().contains_key(b"checksum".as_slice()));
+```
+*/
+
+use super::assembler::*;
+use super::error::ConnectionError;
+use super::error::ErrorCode;
+use super::frame::*;
+use super::hpack::*;
+use super::*;
+use crate::validate::*;
+use derive_more::Debug;
+use std::collections::{HashMap, HashSet};
+
+///A message carried by the frames on a stream, which is [`H2Request`] or [`H2Response`].
+pub trait H2Message: std::ops::DerefMut<Target = Entity> + Sized {
+    ///Encodes the pseudo-header fields and the fields into a field block.
+    fn encode_fields(&self, encoder: &mut HpackEncoder, o: &mut dyn PutU8);
+
+    ///Decodes a field block into a message, which is validated.
+    fn decode_fields(decoder: &mut HpackDecoder, block: &[u8]) -> Result<Self, HpackDecodeError>;
+
+    ///Returns true if self is an interim response, which is followed by another message on the stream.
+    fn is_interim(&self) -> bool {
+        false
+    }
+
+    ///Returns the length of the content declared by the content-length field, which the DATA received must match.
+    fn content_length(&self) -> Option<u64> {
+        parse_content_length(self)
+    }
+}
+
+impl H2Message for H2Request {
+    fn encode_fields(&self, encoder: &mut HpackEncoder, o: &mut dyn PutU8) {
+        encoder.encode_request_fields(self, o);
+    }
+
+    fn decode_fields(decoder: &mut HpackDecoder, block: &[u8]) -> Result<Self, HpackDecodeError> {
+        decoder.decode_request(block)
+    }
+}
+
+impl H2Message for H2Response {
+    fn encode_fields(&self, encoder: &mut HpackEncoder, o: &mut dyn PutU8) {
+        encoder.encode_response_fields(self, o);
+    }
+
+    fn decode_fields(decoder: &mut HpackDecoder, block: &[u8]) -> Result<Self, HpackDecodeError> {
+        decoder.decode_response(block)
+    }
+
+    fn is_interim(&self) -> bool {
+        self.status().as_bytes().starts_with(b"1")
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        if self.status().as_bytes() == b"304" {
+            return None;
+        }
+        parse_content_length(self)
+    }
+}
+
+#[inline(always)]
+fn parse_content_length(o: &Entity) -> Option<u64> {
+    let v = o.get(b"content-length".as_slice())?;
+    std::str::from_utf8(v.one().as_bytes()).ok()?.parse().ok()
+}
+
+///Exports a HEADERS frame and the following CONTINUATION frames, the last one has END_HEADERS.
+pub(crate) fn export_headers(helper: HeadersHelper, end_stream: bool, o: &mut dyn PutU8) {
+    let (mut headers, mut v) = helper.take();
+    headers.set_end_stream(end_stream);
+    match v.last_mut() {
+        Some(r) => {
+            r.set_end_headers(true);
+        }
+        None => {
+            headers.set_end_headers(true);
+        }
+    }
+    headers.export(o);
+    for r in v {
+        r.export(o);
+    }
+}
+
+///Encodes a message into frames, each frame payload is limited by max_frame_size.
+pub fn encode_message<T: H2Message>(
+    stream_id: u32,
+    r: &T,
+    encoder: &mut HpackEncoder,
+    max_frame_size: usize,
+    o: &mut dyn PutU8,
+) {
+    let has_body = !r.body().is_empty();
+    let has_trailers = !r.trailers().is_empty();
+    let mut helper = HeadersHelper::new(stream_id, max_frame_size, max_frame_size);
+    r.encode_fields(encoder, &mut helper);
+    export_headers(helper, !has_body && !has_trailers && !r.is_interim(), o);
+    if has_body {
+        let mut helper = DataHelper::new(stream_id, max_frame_size);
+        helper.put_exact(r.body());
+        let mut v = helper.take();
+        if let Some(d) = v.last_mut() {
+            d.set_end_stream(!has_trailers);
+        }
+        for d in v {
+            d.export(o);
+        }
+    }
+    if has_trailers {
+        let mut helper = HeadersHelper::new(stream_id, max_frame_size, max_frame_size);
+        encoder.encode_trailer_fields(r, &mut helper);
+        export_headers(helper, true, o);
+    }
+}
+
+///Encodes a request into frames, each frame payload is limited by max_frame_size.
+pub fn encode_request(
+    stream_id: u32,
+    r: &H2Request,
+    encoder: &mut HpackEncoder,
+    max_frame_size: usize,
+    o: &mut dyn PutU8,
+) {
+    encode_message(stream_id, r, encoder, max_frame_size, o);
+}
+
+///Encodes a response into frames, each frame payload is limited by max_frame_size.
+pub fn encode_response(
+    stream_id: u32,
+    r: &H2Response,
+    encoder: &mut HpackEncoder,
+    max_frame_size: usize,
+    o: &mut dyn PutU8,
+) {
+    encode_message(stream_id, r, encoder, max_frame_size, o);
+}
+
+///Checks a promised request of server push, which must be valid, safe, cacheable, and must not include content.
+///":authority" is required, for which the server is authoritative.
+pub fn check_push_request(r: &H2Request) -> Result<(), &'static str> {
+    r.validate().map_err(|e| e.reason())?;
+    if !matches!(r.method().as_bytes(), b"GET" | b"HEAD") {
+        return Err("promised request is not safe and cacheable");
+    }
+    if r.authority().is_none() {
+        return Err("promised request without :authority");
+    }
+    let content_length = r.get(b"content-length".as_slice());
+    if !r.body().is_empty() || content_length.is_some_and(|o| o.one().as_bytes() != b"0") {
+        return Err("promised request with content");
+    }
+    Ok(())
+}
+
+///Encodes a PUSH_PROMISE frame and the following CONTINUATION frames, which promise a request on promised_stream_id.
+///Each frame payload is limited by max_frame_size. Returns an error if the promised request can't be pushed.
+pub fn encode_push_promise(
+    stream_id: u32,
+    promised_stream_id: u32,
+    r: &H2Request,
+    encoder: &mut HpackEncoder,
+    max_frame_size: usize,
+    o: &mut dyn PutU8,
+) -> Result<(), &'static str> {
+    check_push_request(r)?;
+    if promised_stream_id == 0 || !promised_stream_id.is_multiple_of(2) {
+        return Err("promised stream identifier is not even");
+    }
+    let mut helper =
+        PushPromiseHelper::new(stream_id, max_frame_size.saturating_sub(4), max_frame_size);
+    encoder.encode_request_fields(r, &mut helper);
+    let (mut push_promise, mut v) = helper.take();
+    push_promise.set_promised_stream_id(promised_stream_id);
+    match v.last_mut() {
+        Some(r) => {
+            r.set_end_headers(true);
+        }
+        None => {
+            push_promise.set_end_headers(true);
+        }
+    }
+    push_promise.export(o);
+    for r in v {
+        r.export(o);
+    }
+    Ok(())
+}
+
+///Represents an error of decoding a message.
+#[derive(Clone, Debug)]
+pub enum MessageError {
+    ///A connection error, the connection should be closed.
+    Connection(ConnectionError),
+    ///A field block on the stream is rejected, the stream is discarded and should be reset.
+    Fields(u32, HpackDecodeError),
+    ///A stream error, the stream is discarded and should be reset.
+    Stream(u32, ErrorCode, &'static str),
+}
+
+impl MessageError {
+    ///Returns the stream identifier, or None if it is a connection error.
+    pub fn stream_id(&self) -> Option<u32> {
+        match self {
+            Self::Connection(_) => None,
+            Self::Fields(n, _) | Self::Stream(n, ..) => Some(*n),
+        }
+    }
+
+    ///Returns the error code.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::Connection(e) => e.error_code(),
+            Self::Fields(_, e) => e.error_code(),
+            Self::Stream(_, e, _) => *e,
+        }
+    }
+}
+
+impl std::fmt::Display for MessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connection(e) => e.fmt(f),
+            Self::Fields(n, e) => write!(f, "stream error on {n}: {e}"),
+            Self::Stream(n, e, s) => write!(f, "stream error {e} on {n}: {s}"),
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+impl From<ConnectionError> for MessageError {
+    fn from(o: ConnectionError) -> Self {
+        Self::Connection(o)
+    }
+}
+
+///Accumulates the frames on each stream into a complete message.
+///
+///The first field block on a stream is the message header, interim responses are skipped.
+///DATA frames are appended to the body, a second field block is the trailer section, which must end the stream.
+///A message is returned when the stream is ended. Field blocks of PUSH_PROMISE frames are decoded to keep
+///the compression context, and discarded. A stream reset by RST_STREAM is discarded.
+///After an error on a stream, the following frames on it are ignored until it ends,
+///the flow-control window of their DATA should still be released.
+///The content-length field must match the length of the DATA received, except for a stream passed to
+///[`MessageDecoder::skip_content_length`], e.g. the response to a HEAD request.
+#[derive(Debug, CopyGetters, Setters)]
+pub struct MessageDecoder<T> {
+    assembler: FieldBlockAssembler,
+    #[getset(get_copy = "pub", set = "pub")]
+    max_body_size: Option<usize>,
+    #[debug("{:?}", streams.keys())]
+    streams: HashMap<u32, T>,
+    discarded: HashSet<u32>,
+    lengths: HashMap<u32, u64>,
+    unchecked: HashSet<u32>,
+}
+
+///A decoder of requests.
+pub type RequestDecoder = MessageDecoder<H2Request>;
+///A decoder of responses.
+pub type ResponseDecoder = MessageDecoder<H2Response>;
+
+impl<T> Default for MessageDecoder<T> {
+    fn default() -> Self {
+        Self::new(FieldBlockAssembler::default())
+    }
+}
+
+impl<T> MessageDecoder<T> {
+    ///Creates with a field block assembler, the body size is unlimited.
+    pub fn new(assembler: FieldBlockAssembler) -> Self {
+        Self {
+            assembler,
+            max_body_size: None,
+            streams: HashMap::new(),
+            discarded: HashSet::new(),
+            lengths: HashMap::new(),
+            unchecked: HashSet::new(),
+        }
+    }
+
+    ///Returns true if a message is started but not ended on the stream.
+    pub fn is_pending(&self, stream_id: u32) -> bool {
+        self.streams.contains_key(&stream_id)
+    }
+
+    ///Discards the message on the stream.
+    pub fn remove(&mut self, stream_id: u32) -> Option<T> {
+        self.discarded.remove(&stream_id);
+        self.finish(stream_id)
+    }
+
+    ///Skips the content-length check of the next message on the stream, which has no content whatever it declares.
+    pub fn skip_content_length(&mut self, stream_id: u32) {
+        self.unchecked.insert(stream_id);
+    }
+
+    #[inline(always)]
+    fn finish(&mut self, stream_id: u32) -> Option<T> {
+        self.lengths.remove(&stream_id);
+        self.unchecked.remove(&stream_id);
+        self.streams.remove(&stream_id)
+    }
+
+    #[inline(always)]
+    fn check_length(
+        &mut self,
+        stream_id: u32,
+        n: usize,
+        end_stream: bool,
+    ) -> Result<(), MessageError> {
+        let n = n as u64;
+        match self.lengths.get(&stream_id) {
+            Some(&i) if n > i || (end_stream && n != i) => {
+                self.discard(stream_id, end_stream);
+                Err(MessageError::Stream(
+                    stream_id,
+                    ErrorCode::ProtocolError,
+                    "content-length mismatch",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    #[inline(always)]
+    fn discard(&mut self, stream_id: u32, end_stream: bool) {
+        self.finish(stream_id);
+        if !end_stream {
+            self.discarded.insert(stream_id);
+        }
+    }
+}
+
+impl<T: H2Message> MessageDecoder<T> {
+    ///Handles a parsed frame, `o` is the source it was parsed from, `decoder` decodes the field blocks of the connection.
+    ///Returns the stream identifier and the message when the stream is ended, or None.
+    pub fn push(
+        &mut self,
+        r: FrameResult,
+        o: &mut dyn GetU8,
+        decoder: &mut HpackDecoder,
+    ) -> Result<Option<(u32, T)>, MessageError> {
+        if let Some(b) = self.assembler.push(&r, o)? {
+            return self.on_field_block(b, decoder);
+        }
+        match r {
+            FrameResult::Data(r) => {
+                let stream_id = r.stream_identifier();
+                if self.discarded.contains(&stream_id) {
+                    if r.end_stream() {
+                        self.discarded.remove(&stream_id);
+                    }
+                    return Ok(None);
+                }
+                let Some(m) = self.streams.get_mut(&stream_id) else {
+                    self.discard(stream_id, r.end_stream());
+                    return Err(MessageError::Stream(
+                        stream_id,
+                        ErrorCode::ProtocolError,
+                        "DATA before HEADERS",
+                    ));
+                };
+                if let Some(mut d) = r.data(o) {
+                    let d = d.get_surplus();
+                    if self
+                        .max_body_size
+                        .is_some_and(|n| m.body().len() + d.len() > n)
+                    {
+                        self.discard(stream_id, r.end_stream());
+                        return Err(MessageError::Stream(
+                            stream_id,
+                            ErrorCode::Cancel,
+                            "body too large",
+                        ));
+                    }
+                    m.body_mut().extend_from_slice(d);
+                }
+                let n = m.body().len();
+                self.check_length(stream_id, n, r.end_stream())?;
+                if r.end_stream() {
+                    return Ok(self.finish(stream_id).map(|m| (stream_id, m)));
+                }
+            }
+            FrameResult::RstStream(r) => {
+                self.remove(r.stream_identifier());
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn on_field_block(
+        &mut self,
+        b: FieldBlock,
+        decoder: &mut HpackDecoder,
+    ) -> Result<Option<(u32, T)>, MessageError> {
+        let stream_id = b.stream_identifier();
+        let decode_error = |e: HpackDecodeError| match e {
+            HpackDecodeError::Compression(s) => {
+                MessageError::Connection(ConnectionError::new(ErrorCode::CompressionError, s))
+            }
+            e => MessageError::Fields(stream_id, e),
+        };
+        if b.promised_stream_id().is_some() {
+            decoder.decode(b.field_block()).map_err(decode_error)?;
+            return Ok(None);
+        }
+        let end_stream = b.end_stream();
+        if self.discarded.contains(&stream_id) {
+            decoder.decode(b.field_block()).map_err(decode_error)?;
+            if end_stream {
+                self.discarded.remove(&stream_id);
+            }
+            return Ok(None);
+        }
+        if self.streams.contains_key(&stream_id) {
+            let v = decoder.decode(b.field_block());
+            let v = v.map_err(|e| {
+                self.discard(stream_id, end_stream);
+                decode_error(e)
+            })?;
+            if !end_stream {
+                self.discard(stream_id, end_stream);
+                return Err(MessageError::Stream(
+                    stream_id,
+                    ErrorCode::ProtocolError,
+                    "trailers without END_STREAM",
+                ));
+            }
+            if let Err(e) = check_trailers(&v) {
+                self.discard(stream_id, end_stream);
+                return Err(MessageError::Fields(stream_id, e.into()));
+            }
+            let n = self.streams[&stream_id].body().len();
+            self.check_length(stream_id, n, end_stream)?;
+            let mut m = self.finish(stream_id).unwrap();
+            for (name, value) in v {
+                m.trailers_mut().add_field(name, value);
+            }
+            return Ok(Some((stream_id, m)));
+        }
+        let m = T::decode_fields(decoder, b.field_block()).map_err(|e| {
+            self.discard(stream_id, end_stream);
+            decode_error(e)
+        })?;
+        if m.is_interim() {
+            if end_stream {
+                return Err(MessageError::Stream(
+                    stream_id,
+                    ErrorCode::ProtocolError,
+                    "interim response with END_STREAM",
+                ));
+            }
+            return Ok(None);
+        }
+        let length = if self.unchecked.remove(&stream_id) {
+            None
+        } else {
+            m.content_length()
+        };
+        if end_stream {
+            if length.is_some_and(|n| n != 0) {
+                return Err(MessageError::Stream(
+                    stream_id,
+                    ErrorCode::ProtocolError,
+                    "content-length mismatch",
+                ));
+            }
+            return Ok(Some((stream_id, m)));
+        }
+        if let Some(n) = length {
+            self.lengths.insert(stream_id, n);
+        }
+        self.streams.insert(stream_id, m);
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode<T: H2Message>(
+        s: &[u8],
+        m: &mut MessageDecoder<T>,
+    ) -> Vec<Result<(u32, T), MessageError>> {
+        let mut decoder = HpackDecoder::default();
+        let mut g = s.into_get();
+        let mut v = Vec::new();
+        while let Ok(f) = get_frame(&mut g) {
+            match m.push(f, &mut g, &mut decoder) {
+                Ok(Some(o)) => v.push(Ok(o)),
+                Ok(None) => {}
+                Err(e) => v.push(Err(e)),
+            }
+        }
+        v
+    }
+
+    #[test]
+    fn message() {
+        let mut r = H2Response::new("200");
+        r.add_field("x-long", "a".repeat(100));
+        r.body_mut().put_repeat(250, b'b');
+        let mut e = HpackEncoder::default();
+        let mut s = Vec::new();
+        encode_response(3, &H2Response::new("103"), &mut e, 50, &mut s);
+        encode_response(3, &r, &mut e, 50, &mut s);
+
+        let mut g = s.as_slice().into_get();
+        let mut types = Vec::new();
+        while let Ok(f) = get_frame(&mut g) {
+            let f = Frame::from_result(f, &mut g).unwrap();
+            types.push(f.ty());
+        }
+        assert_eq!(types, [1, 1, 9, 0, 0, 0, 0, 0]);
+
+        let mut m = ResponseDecoder::default();
+        let mut v = decode(&s, &mut m);
+        assert_eq!(v.len(), 1);
+        let (stream_id, o) = v.pop().unwrap().unwrap();
+        assert_eq!(stream_id, 3);
+        assert_eq!(o.status().as_bytes(), b"200");
+        assert_eq!(o.body().len(), 250);
+        assert!(o.trailers().is_empty());
+        assert!(!m.is_pending(3));
+
+        m.set_max_body_size(Some(100));
+        let v = decode(&s, &mut m);
+        assert_eq!(v[0].as_ref().unwrap_err().stream_id(), Some(3));
+    }
+
+    #[test]
+    fn trailers() {
+        let mut r = H2Request::new("GET");
+        r.set_scheme(Some("https"));
+        r.set_path(Some("/"));
+        r.set_authority(Some("example.org"));
+        r.trailers_mut().add_field("grpc-status", "0");
+        let mut s = Vec::new();
+        encode_request(1, &r, &mut HpackEncoder::default(), 16384, &mut s);
+        let v = decode(&s, &mut RequestDecoder::default());
+        let (_, o) = v[0].as_ref().unwrap();
+        assert!(o.body().is_empty());
+        assert_eq!(o.trailers().len(), 1);
+
+        let mut s = Vec::new();
+        let mut e = HpackEncoder::default();
+        r.trailers_mut().clear();
+        r.trailers_mut().add_field(":path", "/");
+        encode_request(1, &r, &mut e, 16384, &mut s);
+        let mut d = Data::new(1, 1);
+        d.set_end_stream(true);
+        d.export(&mut s);
+        let v = decode(&s, &mut RequestDecoder::default());
+        assert!(matches!(v[0], Err(MessageError::Fields(1, _))));
+        assert!(matches!(v[1], Err(MessageError::Stream(1, ..))));
+    }
+
+    #[test]
+    fn discarded() {
+        let mut r = H2Request::new("POST");
+        r.set_scheme(Some("https"));
+        r.set_path(Some("/"));
+        r.set_authority(Some("example.org"));
+        r.body_mut().put_repeat(30, b'a');
+        let mut s = Vec::new();
+        let mut e = HpackEncoder::default();
+        encode_request(1, &r, &mut e, 10, &mut s);
+        encode_request(3, &r, &mut e, 10, &mut s);
+        let mut m = RequestDecoder::default();
+        m.set_max_body_size(Some(15));
+        let v = decode(&s, &mut m);
+        assert_eq!(v.len(), 2);
+        assert_eq!(v[0].as_ref().unwrap_err().stream_id(), Some(1));
+        assert_eq!(v[1].as_ref().unwrap_err().stream_id(), Some(3));
+        assert!(m.discarded.is_empty());
+
+        let mut s = Vec::new();
+        Data::new(5, 1).export(&mut s);
+        Data::new(5, 1).export(&mut s);
+        let v = decode(&s, &mut m);
+        assert_eq!(v.len(), 1);
+        let mut d = Data::new(5, 1);
+        d.set_end_stream(true);
+        let mut s = Vec::new();
+        d.export(&mut s);
+        assert!(decode(&s, &mut m).is_empty());
+        assert!(m.discarded.is_empty());
+    }
+
+    #[test]
+    fn content_length() {
+        let mut r = H2Request::new("POST");
+        r.set_scheme(Some("https"));
+        r.set_path(Some("/"));
+        r.set_authority(Some("example.org"));
+        r.add_field("content-length", "5");
+        let mut s = Vec::new();
+        let mut e = HpackEncoder::default();
+        for (i, n) in [(1, 5), (3, 3), (5, 8), (7, 0)] {
+            r.body_mut().clear();
+            r.body_mut().put_repeat(n, b'a');
+            encode_request(i, &r, &mut e, 4, &mut s);
+        }
+        let v = decode(&s, &mut RequestDecoder::default());
+        assert_eq!(v.len(), 4);
+        assert_eq!(v[0].as_ref().unwrap().1.body().len(), 5);
+        for (o, i) in v[1..].iter().zip([3, 5, 7]) {
+            assert!(
+                matches!(o, Err(MessageError::Stream(n, ErrorCode::ProtocolError, _)) if *n == i)
+            );
+        }
+
+        let mut r = H2Response::new("200");
+        r.add_field("content-length", "5");
+        let mut s = Vec::new();
+        let mut e = HpackEncoder::default();
+        encode_response(1, &r, &mut e, 16384, &mut s);
+        let mut r = H2Response::new("304");
+        r.add_field("content-length", "5");
+        encode_response(3, &r, &mut e, 16384, &mut s);
+        let mut m = ResponseDecoder::default();
+        m.skip_content_length(1);
+        let v = decode(&s, &mut m);
+        assert!(v.iter().all(|o| o.is_ok()));
+        assert!(m.unchecked.is_empty());
+    }
+}
@@ -56,11 +56,21 @@ assert_eq!(r.path(), req.path());
 ```
 */
 
+pub mod abuse;
+pub mod assembler;
 mod assist;
+pub mod connection;
+pub mod error;
+pub mod flow;
 pub mod frame;
 pub mod hpack;
-pub(crate) mod huffman;
+pub mod huffman;
+pub mod keepalive;
+pub mod message;
 pub(crate) mod prty;
+pub mod scheduler;
+pub mod settings;
+pub mod shutdown;
 
 use crate::io::*;
 use crate::prty::*;
@@ -77,6 +87,8 @@ pub const PSEUDO_AUTHORITY: &[u8] = b":authority";
 pub const PSEUDO_PATH: &[u8] = b":path";
 ///The ":status" pseudo-header field.
 pub const PSEUDO_STATUS: &[u8] = b":status";
+///The ":protocol" pseudo-header field, used by the extended CONNECT.
+pub const PSEUDO_PROTOCOL: &[u8] = b":protocol";
 
 ///Represents an HTTP/2 request.
 #[derive(Debug, Default, Deref, DerefMut, Getters, MutGetters)]
@@ -89,6 +101,8 @@ pub struct H2Request {
     authority: Option<FieldValue>,
     #[getset(get = "pub", get_mut = "pub")]
     path: Option<FieldValue>,
+    #[getset(get = "pub", get_mut = "pub")]
+    protocol: Option<FieldValue>,
     #[deref]
     #[deref_mut]
     headers_body: Entity,
@@ -102,6 +116,7 @@ impl H2Request {
             scheme: None,
             authority: None,
             path: None,
+            protocol: None,
             headers_body: Default::default(),
         }
     }
@@ -121,6 +136,11 @@ impl H2Request {
         self.path = value.map(|o| o.into());
     }
 
+    ///Sets protocol.
+    pub fn set_protocol(&mut self, value: Option<impl Into<FieldValue>>) {
+        self.protocol = value.map(|o| o.into());
+    }
+
     ///Sets a pseudo-header field.
     pub fn set_pseudo(&mut self, name: &[u8], value: impl Into<FieldValue>) {
         let value = value.into();
@@ -137,10 +157,15 @@ impl H2Request {
             PSEUDO_PATH => {
                 self.path.replace(value);
             }
+            PSEUDO_PROTOCOL => {
+                self.protocol.replace(value);
+            }
             _ => {}
         }
     }
 
+    request_pseudo!();
+
     ///Returns a static table index value of ":method".
     pub fn indexed_method(&self) -> IndexRef<'_> {
         match self.method.as_bytes() {
@@ -209,6 +234,8 @@ impl H2Response {
         }
     }
 
+    response_pseudo!();
+
     ///Returns a static table index value of ":status".
     pub fn indexed_status(&self) -> IndexRef<'_> {
         match self.status.as_bytes() {
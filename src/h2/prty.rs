@@ -39,7 +39,7 @@ pub(crate) fn decode_integer(mut n: usize, o: &mut dyn GetU8) -> Result<usize, &
 
 #[inline]
 pub(crate) fn encode_literal_huffman(s: &[u8], o: &mut dyn PutU8) {
-    let mut v = Vec::new();
+    let mut v = Vec::with_capacity(encoded_len(s));
     encode_huffman(s, &mut v);
     let i = v.len();
     encode_integer(i, 7, 0x80, o);
@@ -61,14 +61,14 @@ pub(crate) fn decode_literal(o: &mut dyn GetU8) -> Result<Vec<u8>, &'static str>
             let r = (i & 0x7f) as usize;
             let r = o.get_exact(r).ok_or(READ_BYTE_ERROR)?;
             let mut v = Vec::with_capacity(r.len());
-            decode_huffman(r, &mut v);
+            decode_huffman(r, &mut v)?;
             Ok(v)
         }
         255 => {
             let r = decode_integer(127, o)?;
             let r = o.get_exact(r).ok_or(READ_BYTE_ERROR)?;
             let mut v = Vec::with_capacity(r.len());
-            decode_huffman(r, &mut v);
+            decode_huffman(r, &mut v)?;
             Ok(v)
         }
         0..127 => {
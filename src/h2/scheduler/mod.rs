@@ -0,0 +1,354 @@
+/*!
+A module for scheduling DATA frames.
+
+[`DataScheduler`] queues the bodies of many streams, and interleaves them into DATA frames,
+each frame is limited by the peer's SETTINGS_MAX_FRAME_SIZE and the windows to send of the connection and the stream.
+
+Streams are ordered by the Extensible Prioritization Scheme: a lower urgency is served first,
+non-incremental streams of the same urgency are served one by one in the order of stream identifiers,
+incremental streams of the same urgency share the bandwidth in round-robin.
+With [`SchedulerMode::Weighted`], streams share the bandwidth in proportion to the weights of RFC 7540 priority signals.
+
+# Examples
+```
+use httpenergy::h2::flow::*;
+use httpenergy::h2::scheduler::*;
+use httpenergy::*;
+
+let mut f = FlowControl::default();
+f.open_stream(1);
+f.open_stream(3);
+let mut s = DataScheduler::default();
+s.push(1, &[0; 20000], true);
+s.push(3, b"urgent", true);
+s.set_priority(3, PriorityParams::new(0, false));
+
+let mut v = Vec::new();
+while let Some(o) = s.poll(&mut f) {
+    v.push((o.stream_identifier(), o.data().len(), o.end_stream()));
+}
+assert_eq!(v, [(3, 6, true), (1, 16384, false), (1, 3616, true)]);
+assert!(s.is_empty());
+```
+*/
+
+use super::flow::*;
+use super::frame::*;
+use crate::io::*;
+use crate::priority::PriorityParams;
+use crate::prty::*;
+use derive_more::Debug;
+use std::collections::HashMap;
+
+const DEFAULT_MAX_FRAME_SIZE: u32 = 16384;
+const DEFAULT_WEIGHT: u8 = 15;
+
+///The policy of sharing the bandwidth among streams.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(u8)]
+pub enum SchedulerMode {
+    ///Ordered by the urgency and the incremental of the Extensible Prioritization Scheme.
+    #[default]
+    Extensible,
+    ///Weighted round-robin by the weights of RFC 7540 priority signals.
+    Weighted,
+}
+
+#[derive(Debug)]
+struct Queue {
+    #[debug("{}", data.len() - index)]
+    data: Vec<u8>,
+    index: usize,
+    end_stream: bool,
+    served: u64,
+    virtual_time: u64,
+}
+
+impl Queue {
+    #[inline(always)]
+    fn pending(&self) -> usize {
+        self.data.len() - self.index
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Params {
+    priority: PriorityParams,
+    weight: u8,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            priority: PriorityParams::default(),
+            weight: DEFAULT_WEIGHT,
+        }
+    }
+}
+
+///Interleaves the queued bodies of streams into DATA frames.
+#[derive(Debug, CopyGetters, Setters)]
+pub struct DataScheduler {
+    #[getset(get_copy = "pub", set = "pub")]
+    mode: SchedulerMode,
+    #[getset(get_copy = "pub")]
+    max_frame_size: u32,
+    streams: HashMap<u32, Queue>,
+    params: HashMap<u32, Params>,
+    round: u64,
+    virtual_time: u64,
+}
+
+impl Default for DataScheduler {
+    fn default() -> Self {
+        Self::new(SchedulerMode::default())
+    }
+}
+
+impl DataScheduler {
+    ///Creates.
+    pub fn new(mode: SchedulerMode) -> Self {
+        Self {
+            mode,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            streams: HashMap::new(),
+            params: HashMap::new(),
+            round: 0,
+            virtual_time: 0,
+        }
+    }
+
+    ///Sets the peer's SETTINGS_MAX_FRAME_SIZE, which limits the payload of a DATA frame.
+    pub fn set_max_frame_size(&mut self, n: u32) {
+        self.max_frame_size = n.max(1);
+    }
+
+    ///Returns true if no data is queued.
+    pub fn is_empty(&self) -> bool {
+        self.streams
+            .values()
+            .all(|o| o.pending() == 0 && !o.end_stream)
+    }
+
+    ///Returns the number of bytes queued on a stream.
+    pub fn pending(&self, stream_id: u32) -> usize {
+        self.streams.get(&stream_id).map_or(0, |o| o.pending())
+    }
+
+    ///Queues data on a stream, end_stream is set on the last DATA frame when all data is sent.
+    ///Data can't be queued after end_stream.
+    pub fn push(&mut self, stream_id: u32, data: &[u8], end_stream: bool) -> bool {
+        let virtual_time = self.virtual_time;
+        let o = self.streams.entry(stream_id).or_insert_with(|| Queue {
+            data: Vec::new(),
+            index: 0,
+            end_stream: false,
+            served: 0,
+            virtual_time,
+        });
+        if o.end_stream {
+            return false;
+        }
+        if o.index > 0 {
+            o.data.drain(..o.index);
+            o.index = 0;
+        }
+        o.data.extend_from_slice(data);
+        o.end_stream = end_stream;
+        true
+    }
+
+    ///Sets the priority parameters of a stream, e.g. from the "priority" header field or a PRIORITY_UPDATE frame.
+    ///It can be set before data is queued, and is kept until the stream is removed or END_STREAM is sent.
+    pub fn set_priority(&mut self, stream_id: u32, o: PriorityParams) {
+        self.params.entry(stream_id).or_default().priority = o;
+    }
+
+    ///Sets the weight of a stream from a PRIORITY frame or a HEADERS frame, the effective weight is one more than it.
+    ///It can be set before data is queued, and is kept until the stream is removed or END_STREAM is sent.
+    pub fn set_weight(&mut self, stream_id: u32, o: u8) {
+        self.params.entry(stream_id).or_default().weight = o;
+    }
+
+    ///Removes the queued data and the parameters of a stream, e.g. the stream is reset. Returns the number of bytes removed.
+    pub fn remove(&mut self, stream_id: u32) -> usize {
+        self.params.remove(&stream_id);
+        self.streams.remove(&stream_id).map_or(0, |o| o.pending())
+    }
+
+    ///Returns the next DATA frame to send, the windows to send are consumed.
+    ///Returns None if no data is queued, or all streams with queued data are blocked by flow control.
+    pub fn poll(&mut self, flow: &mut FlowControl) -> Option<Data> {
+        let (stream_id, n) = self.select(flow)?;
+        let weight = self.params(stream_id).weight;
+        let o = self.streams.get_mut(&stream_id)?;
+        let mut r = Data::new(stream_id, n);
+        r.data_mut().put_exact(&o.data[o.index..o.index + n]);
+        o.index += n;
+        let _ = flow.consume_send(stream_id, n as u32);
+        self.round += 1;
+        o.served = self.round;
+        o.virtual_time += n.max(1) as u64 * 256 / (weight as u64 + 1);
+        self.virtual_time = o.virtual_time;
+        if o.pending() == 0 && o.end_stream {
+            r.set_end_stream(true);
+            self.streams.remove(&stream_id);
+            self.params.remove(&stream_id);
+        }
+        Some(r)
+    }
+
+    ///Exports DATA frames into [`PutU8`] until no frame can be sent. Returns the number of frames.
+    pub fn export(&mut self, flow: &mut FlowControl, o: &mut dyn PutU8) -> usize {
+        let mut n = 0;
+        while let Some(r) = self.poll(flow) {
+            r.export(o);
+            n += 1;
+        }
+        n
+    }
+
+    #[inline(always)]
+    fn params(&self, stream_id: u32) -> Params {
+        self.params.get(&stream_id).copied().unwrap_or_default()
+    }
+
+    fn select(&self, flow: &FlowControl) -> Option<(u32, usize)> {
+        let mut best: Option<(u32, &Queue, usize)> = None;
+        for (&stream_id, o) in self.streams.iter() {
+            let n = o
+                .pending()
+                .min(self.max_frame_size as usize)
+                .min(flow.send_capacity(stream_id) as usize);
+            if n == 0 && !(o.pending() == 0 && o.end_stream) {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some((a, b, _)) => self.compare(stream_id, o, a, b),
+            };
+            if better {
+                best = Some((stream_id, o, n));
+            }
+        }
+        best.map(|(a, _, n)| (a, n))
+    }
+
+    #[inline(always)]
+    fn compare(&self, a: u32, x: &Queue, b: u32, y: &Queue) -> bool {
+        match self.mode {
+            SchedulerMode::Extensible => {
+                let key = |i: u32, o: &Queue| {
+                    let p = self.params(i).priority;
+                    let n = if p.incremental() { o.served } else { 0 };
+                    (p.urgency(), p.incremental(), n, i)
+                };
+                key(a, x) < key(b, y)
+            }
+            SchedulerMode::Weighted => (x.virtual_time, a) < (y.virtual_time, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn streams(f: &mut FlowControl, s: &mut DataScheduler, v: &[u32]) {
+        for &i in v {
+            f.open_stream(i);
+            s.push(i, &[0; 100], true);
+        }
+    }
+
+    #[test]
+    fn extensible() {
+        let mut f = FlowControl::default();
+        let mut s = DataScheduler::default();
+        s.set_max_frame_size(40);
+        streams(&mut f, &mut s, &[1, 3, 5, 7]);
+        s.set_priority(5, PriorityParams::new(3, true));
+        s.set_priority(7, PriorityParams::new(3, true));
+        let mut v = Vec::new();
+        while let Some(o) = s.poll(&mut f) {
+            v.push(o.stream_identifier());
+        }
+        assert_eq!(v, [1, 1, 1, 3, 3, 3, 5, 7, 5, 7, 5, 7]);
+    }
+
+    #[test]
+    fn weighted() {
+        let mut f = FlowControl::default();
+        let mut s = DataScheduler::new(SchedulerMode::Weighted);
+        s.set_max_frame_size(10);
+        streams(&mut f, &mut s, &[1, 3]);
+        s.set_weight(1, 255);
+        s.set_weight(3, 63);
+        let mut v = Vec::new();
+        for _ in 0..5 {
+            v.push(s.poll(&mut f).unwrap().stream_identifier());
+        }
+        assert_eq!(v, [1, 3, 1, 1, 1]);
+    }
+
+    #[test]
+    fn flow_control() {
+        let mut f = FlowControl::default();
+        let mut s = DataScheduler::default();
+        f.open_stream(1);
+        f.consume_send(1, 65500).unwrap();
+        s.push(1, &[0; 100], false);
+        let o = s.poll(&mut f).unwrap();
+        assert_eq!(o.data().len(), 35);
+        assert!(s.poll(&mut f).is_none());
+        assert_eq!(s.pending(1), 65);
+        assert!(!s.is_empty());
+
+        f.on_window_update(1, 100).unwrap();
+        f.on_window_update(0, 100).unwrap();
+        assert!(s.push(1, &[], true));
+        let o = s.poll(&mut f).unwrap();
+        assert_eq!(o.data().len(), 65);
+        assert!(o.end_stream());
+        assert!(s.is_empty());
+
+        f.open_stream(3);
+        s.push(3, &[], true);
+        assert!(s.poll(&mut f).unwrap().end_stream());
+        assert_eq!(s.remove(3), 0);
+
+        f.open_stream(5);
+        s.push(5, &[0; 10], false);
+        let o = s.poll(&mut f).unwrap();
+        assert!(!o.end_stream());
+        assert!(s.poll(&mut f).is_none());
+        assert!(s.is_empty());
+        assert!(s.push(5, &[], false));
+        assert!(s.is_empty());
+        assert!(s.push(5, &[0; 10], true));
+        assert!(s.poll(&mut f).unwrap().end_stream());
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn params() {
+        let mut f = FlowControl::default();
+        let mut s = DataScheduler::default();
+        s.set_priority(3, PriorityParams::new(0, false));
+        streams(&mut f, &mut s, &[1, 3]);
+        assert_eq!(s.poll(&mut f).unwrap().stream_identifier(), 3);
+
+        let mut s = DataScheduler::default();
+        s.set_priority(3, PriorityParams::new(0, false));
+        s.push(3, &[0; 10], false);
+        s.poll(&mut f).unwrap();
+        assert!(s.is_empty());
+        s.push(1, &[0; 10], true);
+        s.push(3, &[0; 10], true);
+        assert_eq!(s.poll(&mut f).unwrap().stream_identifier(), 3);
+        assert_eq!(s.poll(&mut f).unwrap().stream_identifier(), 1);
+        s.push(3, &[0; 10], true);
+        s.push(1, &[0; 10], true);
+        assert_eq!(s.poll(&mut f).unwrap().stream_identifier(), 1);
+    }
+}
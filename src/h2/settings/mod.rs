@@ -0,0 +1,273 @@
+/*!
+A module for typed HTTP/2 settings.
+
+[`ConnectionSettings`] holds the values of the defined settings, with the initial values by default.
+It converts from and into SETTINGS frames, unknown identifiers are ignored.
+[`SettingsTracker`] tracks self's settings sent to the peer, which take effect when acknowledged.
+
+# Examples
+```
+use httpenergy::h2::frame::*;
+use httpenergy::h2::settings::*;
+use httpenergy::*;
+
+let mut o = ConnectionSettings::default();
+o.set_enable_push(false);
+o.set_max_frame_size(1 << 20);
+
+let mut s = Vec::new();
+o.to_frame().export(&mut s);
+if let Ok(FrameResult::Settings(r)) = get_frame(&mut s.into_get()) {
+    let mut t = ConnectionSettings::default();
+    t.apply(r.setting()).unwrap();
+    assert_eq!(t, o);
+}
+```
+*/
+
+use super::error::ConnectionError;
+use super::error::ErrorCode;
+use super::frame::*;
+use crate::prty::*;
+use std::collections::VecDeque;
+
+///The initial value of SETTINGS_HEADER_TABLE_SIZE.
+pub const DEFAULT_HEADER_TABLE_SIZE: u32 = 4096;
+///The initial value of SETTINGS_INITIAL_WINDOW_SIZE.
+pub const DEFAULT_INITIAL_WINDOW_SIZE: u32 = 65535;
+///The initial value of SETTINGS_MAX_FRAME_SIZE, also the minimum value.
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16384;
+///The maximum value of SETTINGS_MAX_FRAME_SIZE.
+pub const MAX_MAX_FRAME_SIZE: u32 = 16777215;
+///The maximum value of SETTINGS_INITIAL_WINDOW_SIZE.
+pub const MAX_INITIAL_WINDOW_SIZE: u32 = 0x7fff_ffff;
+
+///Represents the values of the defined settings. None means unlimited.
+#[derive(Clone, Copy, CopyGetters, Debug, Eq, PartialEq, Setters)]
+#[getset(get_copy = "pub", set = "pub")]
+pub struct ConnectionSettings {
+    header_table_size: u32,
+    enable_push: bool,
+    max_concurrent_streams: Option<u32>,
+    initial_window_size: u32,
+    max_frame_size: u32,
+    max_header_list_size: Option<u32>,
+    enable_connect_protocol: bool,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        Self {
+            header_table_size: DEFAULT_HEADER_TABLE_SIZE,
+            enable_push: true,
+            max_concurrent_streams: None,
+            initial_window_size: DEFAULT_INITIAL_WINDOW_SIZE,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_header_list_size: None,
+            enable_connect_protocol: false,
+        }
+    }
+}
+
+impl ConnectionSettings {
+    ///Validates a setting. Returns a connection error if the value is invalid, unknown identifiers are valid.
+    pub fn check(identifier: u16, value: u32) -> Result<(), ConnectionError> {
+        match identifier {
+            SETTINGS_ENABLE_PUSH if value > 1 => Err(ConnectionError::new(
+                ErrorCode::ProtocolError,
+                "invalid SETTINGS_ENABLE_PUSH",
+            )),
+            SETTINGS_INITIAL_WINDOW_SIZE if value > MAX_INITIAL_WINDOW_SIZE => {
+                Err(ConnectionError::new(
+                    ErrorCode::FlowControlError,
+                    "invalid SETTINGS_INITIAL_WINDOW_SIZE",
+                ))
+            }
+            SETTINGS_MAX_FRAME_SIZE
+                if !(DEFAULT_MAX_FRAME_SIZE..=MAX_MAX_FRAME_SIZE).contains(&value) =>
+            {
+                Err(ConnectionError::new(
+                    ErrorCode::ProtocolError,
+                    "invalid SETTINGS_MAX_FRAME_SIZE",
+                ))
+            }
+            SETTINGS_ENABLE_CONNECT_PROTOCOL if value > 1 => Err(ConnectionError::new(
+                ErrorCode::ProtocolError,
+                "invalid SETTINGS_ENABLE_CONNECT_PROTOCOL",
+            )),
+            SETTINGS_NO_RFC7540_PRIORITIES if value > 1 => Err(ConnectionError::new(
+                ErrorCode::ProtocolError,
+                "invalid SETTINGS_NO_RFC7540_PRIORITIES",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    ///Validates self.
+    pub fn validate(&self) -> Result<(), ConnectionError> {
+        for (a, b) in self.diff(&Self::default()) {
+            Self::check(a, b)?;
+        }
+        Ok(())
+    }
+
+    ///Applies the settings of a SETTINGS frame in order. Unknown identifiers are ignored.
+    ///
+    ///Returns a connection error if a value is invalid, or SETTINGS_ENABLE_CONNECT_PROTOCOL changes from 1 to 0,
+    ///and self is not changed.
+    pub fn apply(&mut self, setting: &[(u16, u32)]) -> Result<(), ConnectionError> {
+        let mut t = *self;
+        for &(a, b) in setting {
+            Self::check(a, b)?;
+            match a {
+                SETTINGS_HEADER_TABLE_SIZE => t.header_table_size = b,
+                SETTINGS_ENABLE_PUSH => t.enable_push = b == 1,
+                SETTINGS_MAX_CONCURRENT_STREAMS => t.max_concurrent_streams = Some(b),
+                SETTINGS_INITIAL_WINDOW_SIZE => t.initial_window_size = b,
+                SETTINGS_MAX_FRAME_SIZE => t.max_frame_size = b,
+                SETTINGS_MAX_HEADER_LIST_SIZE => t.max_header_list_size = Some(b),
+                SETTINGS_ENABLE_CONNECT_PROTOCOL => {
+                    if t.enable_connect_protocol && b == 0 {
+                        return Err(ConnectionError::new(
+                            ErrorCode::ProtocolError,
+                            "SETTINGS_ENABLE_CONNECT_PROTOCOL disabled",
+                        ));
+                    }
+                    t.enable_connect_protocol = b == 1;
+                }
+                _ => {}
+            }
+        }
+        *self = t;
+        Ok(())
+    }
+
+    ///Returns the settings of self which differ from the base.
+    pub fn diff(&self, base: &Self) -> Vec<(u16, u32)> {
+        let mut v = Vec::new();
+        if self.header_table_size != base.header_table_size {
+            v.push((SETTINGS_HEADER_TABLE_SIZE, self.header_table_size));
+        }
+        if self.enable_push != base.enable_push {
+            v.push((SETTINGS_ENABLE_PUSH, self.enable_push as u32));
+        }
+        if self.max_concurrent_streams != base.max_concurrent_streams {
+            let n = self.max_concurrent_streams.unwrap_or(u32::MAX);
+            v.push((SETTINGS_MAX_CONCURRENT_STREAMS, n));
+        }
+        if self.initial_window_size != base.initial_window_size {
+            v.push((SETTINGS_INITIAL_WINDOW_SIZE, self.initial_window_size));
+        }
+        if self.max_frame_size != base.max_frame_size {
+            v.push((SETTINGS_MAX_FRAME_SIZE, self.max_frame_size));
+        }
+        if self.max_header_list_size != base.max_header_list_size {
+            let n = self.max_header_list_size.unwrap_or(u32::MAX);
+            v.push((SETTINGS_MAX_HEADER_LIST_SIZE, n));
+        }
+        if self.enable_connect_protocol != base.enable_connect_protocol {
+            v.push((
+                SETTINGS_ENABLE_CONNECT_PROTOCOL,
+                self.enable_connect_protocol as u32,
+            ));
+        }
+        v
+    }
+
+    ///Returns a SETTINGS frame with the values which differ from the initial values.
+    pub fn to_frame(&self) -> Settings {
+        to_frame(self.diff(&Self::default()))
+    }
+}
+
+#[inline(always)]
+fn to_frame(v: Vec<(u16, u32)>) -> Settings {
+    let mut o = Settings::new(v.len());
+    for (a, b) in v {
+        o.push(a, b);
+    }
+    o
+}
+
+///Tracks self's settings, which are pending until acknowledged by the peer.
+#[derive(Debug, Default, CopyGetters)]
+pub struct SettingsTracker {
+    #[getset(get_copy = "pub")]
+    acked: ConnectionSettings,
+    pending: VecDeque<ConnectionSettings>,
+}
+
+impl SettingsTracker {
+    ///Returns the latest settings sent, which may be not acknowledged.
+    pub fn latest(&self) -> ConnectionSettings {
+        self.pending.back().copied().unwrap_or(self.acked)
+    }
+
+    ///Returns the number of SETTINGS frames not acknowledged.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    ///Validates new settings, and returns a SETTINGS frame to send with the values which differ from the latest settings.
+    pub fn send(&mut self, o: ConnectionSettings) -> Result<Settings, ConnectionError> {
+        o.validate()?;
+        let r = to_frame(o.diff(&self.latest()));
+        self.pending.push_back(o);
+        Ok(r)
+    }
+
+    ///Handles a SETTINGS frame with the ACK flag, the earliest pending settings take effect.
+    ///Returns the settings acknowledged, or a connection error if nothing is pending.
+    pub fn on_ack(&mut self) -> Result<ConnectionSettings, ConnectionError> {
+        let o = self.pending.pop_front().ok_or_else(|| {
+            ConnectionError::new(ErrorCode::ProtocolError, "unexpected SETTINGS ACK")
+        })?;
+        self.acked = o;
+        Ok(o)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings() {
+        let mut o = ConnectionSettings::default();
+        o.apply(&[(SETTINGS_MAX_CONCURRENT_STREAMS, 100), (0xff, 1)])
+            .unwrap();
+        assert_eq!(o.max_concurrent_streams(), Some(100));
+        assert!(o.apply(&[(SETTINGS_ENABLE_PUSH, 2)]).is_err());
+        let e = o
+            .apply(&[
+                (SETTINGS_HEADER_TABLE_SIZE, 0),
+                (SETTINGS_MAX_FRAME_SIZE, 100),
+            ])
+            .unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::ProtocolError);
+        assert_eq!(o.header_table_size(), DEFAULT_HEADER_TABLE_SIZE);
+        let e = o
+            .apply(&[(SETTINGS_INITIAL_WINDOW_SIZE, 1 << 31)])
+            .unwrap_err();
+        assert_eq!(e.error_code(), ErrorCode::FlowControlError);
+        o.apply(&[(SETTINGS_ENABLE_CONNECT_PROTOCOL, 1)]).unwrap();
+        assert!(o.apply(&[(SETTINGS_ENABLE_CONNECT_PROTOCOL, 0)]).is_err());
+
+        let mut t = SettingsTracker::default();
+        let mut a = ConnectionSettings::default();
+        a.set_initial_window_size(1000);
+        assert_eq!(t.send(a).unwrap().setting().len(), 1);
+        let mut b = a;
+        b.set_enable_push(false);
+        assert_eq!(
+            t.send(b).unwrap().setting(),
+            &vec![(SETTINGS_ENABLE_PUSH, 0)]
+        );
+        b.set_max_frame_size(1);
+        assert!(t.send(b).is_err());
+        assert_eq!(t.acked(), ConnectionSettings::default());
+        assert_eq!(t.on_ack().unwrap(), a);
+        assert!(!t.on_ack().unwrap().enable_push());
+        assert!(t.on_ack().is_err());
+    }
+}
@@ -0,0 +1,228 @@
+/*!
+A module for the graceful shutdown of a connection.
+
+A graceful shutdown takes two GOAWAY frames. The first one carries the maximum stream identifier, with a PING frame,
+so streams which are in flight are not refused. After the PING is acknowledged, every stream initiated by the peer before the first GOAWAY
+has arrived, the second one carries the real last stream identifier, and streams above it are ignored.
+
+The PING is created by [`PingTracker`], so its opaque data doesn't collide with keepalive PING frames on the connection.
+
+When a GOAWAY frame is received, the streams initiated by self above its last stream identifier are not processed by the peer,
+and are safe to retry on a new connection. So is a stream reset by the peer with REFUSED_STREAM.
+
+[`GracefulShutdown`] exports the frames into [`PutU8`] for a connection driven by itself.
+[`Connection`](super::connection::Connection) tracks the GOAWAY frames it sends, so send them through it instead,
+then it stops opening streams and ignores the streams above the final last stream identifier:
+[`Connection::send_goaway_with`](super::connection::Connection::send_goaway_with) with [`MAX_LAST_STREAM_ID`]
+and [`Connection::send_ping`](super::connection::Connection::send_ping) with the opaque data from [`PingTracker`] start it,
+[`Connection::send_goaway`](super::connection::Connection::send_goaway) sends the final GOAWAY frame after the PING is acknowledged.
+[`GracefulShutdown::on_goaway`], [`GracefulShutdown::on_reset`] and [`GracefulShutdown::is_retry_safe`] work with both.
+
+# Examples
+```
+use httpenergy::h2::error::*;
+use httpenergy::h2::keepalive::*;
+use httpenergy::h2::shutdown::*;
+use std::time::Instant;
+
+let mut t = PingTracker::default();
+let keepalive = t.ping(Instant::now()).opaque_data();
+let mut s = GracefulShutdown::default();
+let mut v = Vec::new();
+assert!(s.start(&mut t, Instant::now(), &mut v));
+assert_eq!(s.state(), ShutdownState::Draining);
+assert!(s.is_accepted(101));
+
+let mut v = Vec::new();
+assert!(!s.on_ping_ack(keepalive, 99, &mut v));
+assert!(s.on_ping_ack(s.opaque_data(), 99, &mut v));
+assert_eq!(s.state(), ShutdownState::Closing);
+assert!(s.is_accepted(99));
+assert!(!s.is_accepted(101));
+
+s.on_goaway(5).unwrap();
+assert!(s.is_retry_safe(7));
+assert!(!s.is_retry_safe(5));
+s.on_reset(3, ErrorCode::RefusedStream);
+assert!(s.is_retry_safe(3));
+```
+*/
+
+use super::error::ErrorCode;
+use super::frame::*;
+use super::keepalive::PingTracker;
+use crate::io::*;
+use crate::prty::*;
+use derive_more::Debug;
+use std::collections::HashSet;
+use std::time::Instant;
+
+///The last stream identifier of the first GOAWAY frame of a graceful shutdown.
+pub const MAX_LAST_STREAM_ID: u32 = 0x7fff_ffff;
+
+///The state of a graceful shutdown.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(u8)]
+pub enum ShutdownState {
+    ///No GOAWAY frame is sent.
+    #[default]
+    Running,
+    ///The first GOAWAY frame and a PING frame are sent, waiting for the PING to be acknowledged.
+    Draining,
+    ///The final GOAWAY frame is sent, streams above its last stream identifier are ignored.
+    Closing,
+}
+
+///Sends the GOAWAY frames of a graceful shutdown, and tracks the GOAWAY frames received.
+#[derive(Debug, CopyGetters, Default)]
+pub struct GracefulShutdown {
+    #[getset(get_copy = "pub")]
+    state: ShutdownState,
+    ///The opaque data of the PING sent with the first GOAWAY frame.
+    #[getset(get_copy = "pub")]
+    opaque_data: u64,
+    ///The last stream identifier of the final GOAWAY frame sent.
+    #[getset(get_copy = "pub")]
+    last_stream_id: Option<u32>,
+    ///The lowest last stream identifier of the GOAWAY frames received.
+    #[getset(get_copy = "pub")]
+    remote_last_stream_id: Option<u32>,
+    refused: HashSet<u32>,
+}
+
+impl GracefulShutdown {
+    ///Starts a graceful shutdown at `now`, exports the first GOAWAY frame and a PING frame created by the tracker into [`PutU8`].
+    ///Returns false if it has started.
+    pub fn start(&mut self, tracker: &mut PingTracker, now: Instant, o: &mut dyn PutU8) -> bool {
+        if self.state != ShutdownState::Running {
+            return false;
+        }
+        let ping = tracker.ping(now);
+        goaway(MAX_LAST_STREAM_ID, ErrorCode::NoError, &[], o);
+        self.opaque_data = ping.opaque_data();
+        ping.export(o);
+        self.state = ShutdownState::Draining;
+        true
+    }
+
+    ///Handles an acknowledged PING. If it is the PING of the first GOAWAY frame,
+    ///exports the final GOAWAY frame with the last stream identifier initiated by the peer into [`PutU8`], and returns true.
+    ///The acknowledgement should also be handled by [`PingTracker::on_ack`].
+    pub fn on_ping_ack(
+        &mut self,
+        opaque_data: u64,
+        last_peer_stream_id: u32,
+        o: &mut dyn PutU8,
+    ) -> bool {
+        if self.state != ShutdownState::Draining || self.opaque_data != opaque_data {
+            return false;
+        }
+        self.close(last_peer_stream_id, ErrorCode::NoError, &[], o);
+        true
+    }
+
+    ///Exports the final GOAWAY frame into [`PutU8`] without waiting, e.g. the shutdown times out or an error occurs.
+    ///The last stream identifier never increases.
+    pub fn close(
+        &mut self,
+        last_peer_stream_id: u32,
+        error_code: ErrorCode,
+        additional_debug_data: &[u8],
+        o: &mut dyn PutU8,
+    ) {
+        let n = self
+            .last_stream_id
+            .map_or(last_peer_stream_id, |n| n.min(last_peer_stream_id));
+        goaway(n, error_code, additional_debug_data, o);
+        self.last_stream_id = Some(n);
+        self.state = ShutdownState::Closing;
+    }
+
+    ///Returns true if a stream initiated by the peer should be processed.
+    pub fn is_accepted(&self, stream_id: u32) -> bool {
+        self.last_stream_id.is_none_or(|n| stream_id <= n)
+    }
+
+    ///Handles a GOAWAY frame received. Returns an error if the last stream identifier increases.
+    pub fn on_goaway(&mut self, last_stream_id: u32) -> Result<(), &'static str> {
+        if self
+            .remote_last_stream_id
+            .is_some_and(|n| last_stream_id > n)
+        {
+            return Err("GOAWAY increases last stream identifier");
+        }
+        self.remote_last_stream_id = Some(last_stream_id);
+        Ok(())
+    }
+
+    ///Handles a RST_STREAM frame received, a stream refused by the peer is not processed.
+    pub fn on_reset(&mut self, stream_id: u32, error_code: ErrorCode) {
+        if error_code == ErrorCode::RefusedStream {
+            self.refused.insert(stream_id);
+        }
+    }
+
+    ///Returns true if a stream initiated by self is not processed by the peer, and can be retried on a new connection.
+    pub fn is_retry_safe(&self, stream_id: u32) -> bool {
+        self.remote_last_stream_id.is_some_and(|n| stream_id > n)
+            || self.refused.contains(&stream_id)
+    }
+}
+
+#[inline(always)]
+fn goaway(last_stream_id: u32, error_code: ErrorCode, s: &[u8], o: &mut dyn PutU8) {
+    let mut r = Goaway::new(s.len());
+    r.set_last_stream_id(last_stream_id);
+    r.set_error_code(error_code);
+    r.additional_debug_data_mut().put_exact(s);
+    r.export(o);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown() {
+        let mut t = PingTracker::default();
+        let now = Instant::now();
+        let a = t.ping(now).opaque_data();
+        let mut s = GracefulShutdown::default();
+        let mut v = Vec::new();
+        assert!(s.start(&mut t, now, &mut v));
+        assert!(!s.start(&mut t, now, &mut v));
+        let b = s.opaque_data();
+        assert_ne!(a, b);
+        assert_eq!(t.outstanding(), 2);
+        let mut g = v.as_slice().into_get();
+        match get_frame(&mut g) {
+            Ok(FrameResult::Goaway(o)) => assert_eq!(o.last_stream_id(), MAX_LAST_STREAM_ID),
+            _ => panic!("GOAWAY"),
+        }
+        assert!(matches!(get_frame(&mut g), Ok(FrameResult::Ping(o)) if !o.ack()));
+
+        let mut v = Vec::new();
+        assert!(!s.on_ping_ack(a, 9, &mut v));
+        assert!(s.on_ping_ack(b, 9, &mut v));
+        assert!(!s.on_ping_ack(b, 11, &mut v));
+        s.close(11, ErrorCode::InternalError, b"timeout", &mut v);
+        assert_eq!(s.last_stream_id(), Some(9));
+        let mut g = v.as_slice().into_get();
+        for _ in 0..2 {
+            match get_frame(&mut g) {
+                Ok(FrameResult::Goaway(o)) => assert_eq!(o.last_stream_id(), 9),
+                _ => panic!("GOAWAY"),
+            }
+        }
+
+        s.on_goaway(MAX_LAST_STREAM_ID).unwrap();
+        assert!(!s.is_retry_safe(3));
+        s.on_goaway(1).unwrap();
+        assert!(s.is_retry_safe(3));
+        assert!(s.on_goaway(5).is_err());
+        s.on_reset(1, ErrorCode::Cancel);
+        assert!(!s.is_retry_safe(1));
+        s.on_reset(1, ErrorCode::RefusedStream);
+        assert!(s.is_retry_safe(1));
+    }
+}
@@ -1,8 +1,9 @@
 use super::frame::*;
 use super::qpack::*;
 use super::*;
+use crate::table::*;
+use crate::validate::*;
 use derive_more::Debug;
-use std::collections::VecDeque;
 
 ///A helper to build one or more DATA frames.
 pub struct DataHelper {
@@ -28,11 +29,12 @@ impl DataHelper {
     }
 
     ///Builds frames into [`PutU8`].
-    pub fn export(self, o: &mut dyn PutU8) {
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), varint::VarintError> {
         let v = self.take();
         for r in v {
-            r.export(o);
+            r.export(o)?;
         }
+        Ok(())
     }
 
     fn buffer(&mut self) -> &mut FiniteVec {
@@ -113,14 +115,36 @@ fields_vec!(add_fields_to_request, H3Request);
 
 fields_vec!(add_fields_to_response, H3Response);
 
-///This struct is used for test, maybe not meet the requirements.
-#[derive(Debug, CopyGetters)]
+macro_rules! try_fields_vec {
+    ($a:ident, $b:ty) => {
+        ///A helper to build a message from some fields, differentiate pseudo-header fields.
+        ///
+        ///Returns an error naming the offending field if the message is malformed, and r is not changed.
+        pub fn $a(v: Vec<(FieldName, FieldValue)>, r: &mut $b) -> Result<(), MalformedError> {
+            check_fields(&v)?;
+            let mut t = <$b>::default();
+            for (name, value) in v {
+                if name.is_pseudo() {
+                    t.try_set_pseudo(name.as_bytes(), value)?;
+                } else {
+                    t.add_field(name, value);
+                }
+            }
+            t.validate()?;
+            *r = t;
+            Ok(())
+        }
+    };
+}
+
+try_fields_vec!(try_add_fields_to_request, H3Request);
+
+try_fields_vec!(try_add_fields_to_response, H3Response);
+
+///A dynamic table, indexed relative to the insert count.
+#[derive(Debug)]
 pub struct DynamicTable {
-    #[getset(get_copy = "pub")]
-    capacity: usize,
-    #[getset(get_copy = "pub")]
-    absolute: usize,
-    inner: VecDeque<(FieldName, FieldValue)>,
+    inner: FieldTable,
 }
 
 impl Default for DynamicTable {
@@ -130,50 +154,62 @@ impl Default for DynamicTable {
 }
 
 impl DynamicTable {
+    ///Creates.
     pub fn new(capacity: usize) -> Self {
         Self {
-            capacity,
-            absolute: 0,
-            inner: VecDeque::new(),
+            inner: FieldTable::new(capacity),
         }
     }
 
+    ///Returns the capacity.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    ///Returns the insert count.
+    pub fn absolute(&self) -> usize {
+        self.inner.insert_count()
+    }
+
+    ///Removes all entries.
     pub fn clear(&mut self) {
         self.inner.clear();
     }
 
+    ///Sets the capacity, evicts entries to fit.
     pub fn set_capacity(&mut self, n: usize) {
-        self.capacity = n;
-        self.eviction();
+        self.inner.set_capacity(n);
     }
 
+    ///Returns the size.
     pub fn size(&self) -> usize {
-        let mut s = 0;
-        for (a, b) in &self.inner {
-            s += a.len() + b.len() + 32;
-        }
-        s
+        self.inner.size()
     }
 
+    ///Evicts entries to fit the capacity.
     pub fn eviction(&mut self) {
-        while self.size() > self.capacity {
-            self.inner.pop_back();
-        }
+        self.inner.set_capacity(self.inner.capacity());
     }
 
+    ///Adds an entry.
     pub fn add(&mut self, name: FieldName, value: FieldValue) {
-        self.inner.push_front((name, value));
-        self.absolute += 1;
-        self.eviction();
+        self.inner.insert(name, value);
     }
 
+    ///Returns an entry with the relative index, 0 for the newest entry.
     pub fn get_entry(&self, n: usize) -> Option<&(FieldName, FieldValue)> {
-        self.inner.get(n)
+        self.inner.get(n + 1)
     }
 
+    ///Returns a field name with the relative index, 0 for the newest entry.
     pub fn get_name(&self, n: usize) -> Option<&FieldName> {
         self.get_entry(n).map(|(name, _)| name)
     }
+
+    ///Returns the table.
+    pub fn table(&self) -> &FieldTable {
+        &self.inner
+    }
 }
 
 ///This function is used for test, maybe not meet the requirements.
@@ -287,6 +323,14 @@ pub fn handle_request_pseudo_header_fields(r: &H3Request, o: &mut dyn PutU8) {
     if let Some(v) = r.indexed_path() {
         handle_index_ref(v, o)
     }
+    if let Some(v) = r.protocol() {
+        build_literal_field_line_with_literal_name(
+            false,
+            PSEUDO_PROTOCOL.into(),
+            v.as_bytes().into(),
+            o,
+        );
+    }
 }
 
 ///This function is used for test, maybe not meet the requirements.
@@ -0,0 +1,225 @@
+/*!
+A module for HTTP/3 and QPACK error codes.
+
+Error codes are used in QUIC RESET_STREAM, STOP_SENDING and CONNECTION_CLOSE frames.
+Error codes of the format 0x1f * N + 0x21 are reserved to exercise the requirement that unknown error codes be treated as
+equivalent to H3_NO_ERROR, [`ErrorCode::grease`] returns one of them.
+
+# Examples
+```
+use httpenergy::h3::error::*;
+
+assert_eq!(ErrorCode::from(0x10c), ErrorCode::RequestCancelled);
+assert_eq!(u64::from(ErrorCode::QpackDecoderStreamError), 0x202);
+assert_eq!(ErrorCode::FrameUnexpected.to_string(), "H3_FRAME_UNEXPECTED");
+assert_eq!(ErrorCode::from(0x21), ErrorCode::Reserved(0x21));
+assert!(ErrorCode::grease(7).is_reserved());
+```
+*/
+
+///Represents an error code.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ErrorCode {
+    ///No error, used when the connection or stream needs to be closed, but there is no error to signal.
+    NoError,
+    ///Peer violated protocol requirements in a way that does not match a more specific error code.
+    GeneralProtocolError,
+    ///An internal error has occurred in the HTTP stack.
+    InternalError,
+    ///The endpoint detected that its peer created a stream that it will not accept.
+    StreamCreationError,
+    ///A stream required by the HTTP/3 connection was closed or reset.
+    ClosedCriticalStream,
+    ///A frame was received that was not permitted in the current state or on the current stream.
+    FrameUnexpected,
+    ///A frame that fails to satisfy layout requirements or with an invalid size was received.
+    FrameError,
+    ///The endpoint detected that its peer is exhibiting a behavior that might be generating excessive load.
+    ExcessiveLoad,
+    ///A stream ID or push ID was used incorrectly.
+    IdError,
+    ///An endpoint detected an error in the payload of a SETTINGS frame.
+    SettingsError,
+    ///No SETTINGS frame was received at the beginning of the control stream.
+    MissingSettings,
+    ///A server rejected a request without performing any application processing.
+    RequestRejected,
+    ///The request or its response is cancelled.
+    RequestCancelled,
+    ///The client's stream terminated without containing a fully formed request.
+    RequestIncomplete,
+    ///An HTTP message was malformed and cannot be processed.
+    MessageError,
+    ///The TCP connection established in response to a CONNECT request was reset or abnormally closed.
+    ConnectError,
+    ///The requested operation cannot be served over HTTP/3. The peer should retry over HTTP/1.1.
+    VersionFallback,
+    ///The decoder failed to interpret an encoded field section.
+    QpackDecompressionFailed,
+    ///The decoder failed to interpret an encoder instruction received on the encoder stream.
+    QpackEncoderStreamError,
+    ///The encoder failed to interpret a decoder instruction received on the decoder stream.
+    QpackDecoderStreamError,
+    ///A reserved error code, 0x1f * N + 0x21.
+    Reserved(u64),
+    ///An unknown error code.
+    Unknown(u64),
+}
+
+impl ErrorCode {
+    ///Returns a reserved error code, 0x1f * N + 0x21. N is limited to keep the value in the range of varint.
+    pub fn grease(n: u64) -> Self {
+        Self::Reserved(0x1f * n.min(0x0210_8421_0842_1083) + 0x21)
+    }
+
+    ///Returns true if the error code is reserved.
+    pub fn is_reserved(&self) -> bool {
+        matches!(self, Self::Reserved(_))
+    }
+
+    ///Returns true if the error code is reserved or unknown, which is treated as equivalent to H3_NO_ERROR.
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Self::Reserved(_) | Self::Unknown(_))
+    }
+
+    ///Returns the name, or None if reserved or unknown.
+    pub fn name(&self) -> Option<&'static str> {
+        let r = match self {
+            Self::NoError => "H3_NO_ERROR",
+            Self::GeneralProtocolError => "H3_GENERAL_PROTOCOL_ERROR",
+            Self::InternalError => "H3_INTERNAL_ERROR",
+            Self::StreamCreationError => "H3_STREAM_CREATION_ERROR",
+            Self::ClosedCriticalStream => "H3_CLOSED_CRITICAL_STREAM",
+            Self::FrameUnexpected => "H3_FRAME_UNEXPECTED",
+            Self::FrameError => "H3_FRAME_ERROR",
+            Self::ExcessiveLoad => "H3_EXCESSIVE_LOAD",
+            Self::IdError => "H3_ID_ERROR",
+            Self::SettingsError => "H3_SETTINGS_ERROR",
+            Self::MissingSettings => "H3_MISSING_SETTINGS",
+            Self::RequestRejected => "H3_REQUEST_REJECTED",
+            Self::RequestCancelled => "H3_REQUEST_CANCELLED",
+            Self::RequestIncomplete => "H3_REQUEST_INCOMPLETE",
+            Self::MessageError => "H3_MESSAGE_ERROR",
+            Self::ConnectError => "H3_CONNECT_ERROR",
+            Self::VersionFallback => "H3_VERSION_FALLBACK",
+            Self::QpackDecompressionFailed => "QPACK_DECOMPRESSION_FAILED",
+            Self::QpackEncoderStreamError => "QPACK_ENCODER_STREAM_ERROR",
+            Self::QpackDecoderStreamError => "QPACK_DECODER_STREAM_ERROR",
+            Self::Reserved(_) | Self::Unknown(_) => return None,
+        };
+        Some(r)
+    }
+}
+
+impl From<u64> for ErrorCode {
+    fn from(o: u64) -> Self {
+        match o {
+            0x100 => Self::NoError,
+            0x101 => Self::GeneralProtocolError,
+            0x102 => Self::InternalError,
+            0x103 => Self::StreamCreationError,
+            0x104 => Self::ClosedCriticalStream,
+            0x105 => Self::FrameUnexpected,
+            0x106 => Self::FrameError,
+            0x107 => Self::ExcessiveLoad,
+            0x108 => Self::IdError,
+            0x109 => Self::SettingsError,
+            0x10a => Self::MissingSettings,
+            0x10b => Self::RequestRejected,
+            0x10c => Self::RequestCancelled,
+            0x10d => Self::RequestIncomplete,
+            0x10e => Self::MessageError,
+            0x10f => Self::ConnectError,
+            0x110 => Self::VersionFallback,
+            0x200 => Self::QpackDecompressionFailed,
+            0x201 => Self::QpackEncoderStreamError,
+            0x202 => Self::QpackDecoderStreamError,
+            _ if o >= 0x21 && (o - 0x21).is_multiple_of(0x1f) => Self::Reserved(o),
+            _ => Self::Unknown(o),
+        }
+    }
+}
+
+impl From<ErrorCode> for u64 {
+    fn from(o: ErrorCode) -> Self {
+        match o {
+            ErrorCode::NoError => 0x100,
+            ErrorCode::GeneralProtocolError => 0x101,
+            ErrorCode::InternalError => 0x102,
+            ErrorCode::StreamCreationError => 0x103,
+            ErrorCode::ClosedCriticalStream => 0x104,
+            ErrorCode::FrameUnexpected => 0x105,
+            ErrorCode::FrameError => 0x106,
+            ErrorCode::ExcessiveLoad => 0x107,
+            ErrorCode::IdError => 0x108,
+            ErrorCode::SettingsError => 0x109,
+            ErrorCode::MissingSettings => 0x10a,
+            ErrorCode::RequestRejected => 0x10b,
+            ErrorCode::RequestCancelled => 0x10c,
+            ErrorCode::RequestIncomplete => 0x10d,
+            ErrorCode::MessageError => 0x10e,
+            ErrorCode::ConnectError => 0x10f,
+            ErrorCode::VersionFallback => 0x110,
+            ErrorCode::QpackDecompressionFailed => 0x200,
+            ErrorCode::QpackEncoderStreamError => 0x201,
+            ErrorCode::QpackDecoderStreamError => 0x202,
+            ErrorCode::Reserved(n) | ErrorCode::Unknown(n) => n,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reserved(n) => write!(f, "RESERVED({n:#x})"),
+            Self::Unknown(n) => write!(f, "UNKNOWN({n:#x})"),
+            _ => f.write_str(self.name().unwrap_or_default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_code() {
+        for n in (0x100..=0x110).chain(0x200..=0x202) {
+            let o = ErrorCode::from(n);
+            assert!(!o.is_unknown());
+            assert_eq!(u64::from(o), n);
+            assert_eq!(ErrorCode::from(u64::from(o)), o);
+            assert!(o.name().is_some_and(|s| o.to_string() == s));
+        }
+        for n in [0x111, 0x203, 0x20] {
+            let o = ErrorCode::from(n);
+            assert_eq!(o, ErrorCode::Unknown(n));
+            assert_eq!(u64::from(o), n);
+            assert_eq!(ErrorCode::from(u64::from(o)), o);
+            assert_eq!(o.name(), None);
+        }
+        assert_eq!(ErrorCode::from(0x111).to_string(), "UNKNOWN(0x111)");
+        assert_eq!(ErrorCode::NoError.to_string(), "H3_NO_ERROR");
+        assert_eq!(
+            ErrorCode::QpackDecompressionFailed.to_string(),
+            "QPACK_DECOMPRESSION_FAILED"
+        );
+    }
+
+    #[test]
+    fn grease() {
+        assert_eq!(ErrorCode::grease(0), ErrorCode::Reserved(0x21));
+        assert_eq!(ErrorCode::grease(1), ErrorCode::Reserved(0x40));
+        assert_eq!(ErrorCode::from(0x21), ErrorCode::Reserved(0x21));
+        assert_eq!(ErrorCode::from(0x21 + 0x1f), ErrorCode::Reserved(0x40));
+        assert_eq!(ErrorCode::from(0x41), ErrorCode::Unknown(0x41));
+        assert_eq!(ErrorCode::from(0x40).to_string(), "RESERVED(0x40)");
+        assert!(ErrorCode::from(0x21).is_unknown());
+
+        let max = (1 << 62) - 1;
+        let n = u64::from(ErrorCode::grease(u64::MAX));
+        assert!(n <= max && n + 0x1f > max);
+        assert_eq!(ErrorCode::from(n), ErrorCode::Reserved(n));
+        assert_eq!(ErrorCode::from(n - 1), ErrorCode::Unknown(n - 1));
+    }
+}
@@ -6,21 +6,45 @@ This module provides several types and functions for working with frames.
 Each frame type can be created, then use export method.
 
 To parse a frame, you can use [`get_frame`], returns a specific frame type.
+If the input is truncated, it returns [`FrameError::Incomplete`] and the input is not consumed, [`FrameParser`] also limits the payload size.
+A frame of unknown type, including a reserved type 0x1f * N + 0x21, is returned as [`FrameResult::Unknown`] and should be ignored,
+[`Unknown::grease`] creates a frame of reserved type to exercise the requirement.
+
+Integers are encoded by [`varint`], exporting a frame with a value above [`MAX_VARINT`](varint::MAX_VARINT) returns an error.
 */
 
-use super::prty::*;
+use super::error::ErrorCode;
 use super::qpack::*;
+use super::varint::{self, VarintError};
 use super::*;
+use crate::priority::PriorityParams;
 use derive_more::{Debug, Deref, DerefMut};
 use std::num::NonZeroUsize;
 
-const DATA_FRAME_TYPE: u8 = 0x00;
-const HEADERS_FRAME_TYPE: u8 = 0x01;
-const CANCEL_PUSH_FRAME_TYPE: u8 = 0x03;
-const SETTINGS_FRAME_TYPE: u8 = 0x04;
-const PUSH_PROMISE_FRAME_TYPE: u8 = 0x05;
-const GOAWAY_FRAME_TYPE: u8 = 0x07;
-const MAX_PUSH_ID_FRAME_TYPE: u8 = 0x0d;
+const DATA_FRAME_TYPE: u64 = 0x00;
+const HEADERS_FRAME_TYPE: u64 = 0x01;
+const CANCEL_PUSH_FRAME_TYPE: u64 = 0x03;
+const SETTINGS_FRAME_TYPE: u64 = 0x04;
+const PUSH_PROMISE_FRAME_TYPE: u64 = 0x05;
+const GOAWAY_FRAME_TYPE: u64 = 0x07;
+const MAX_PUSH_ID_FRAME_TYPE: u64 = 0x0d;
+const PRIORITY_UPDATE_REQUEST_FRAME_TYPE: u64 = 0xf0700;
+const PRIORITY_UPDATE_PUSH_FRAME_TYPE: u64 = 0xf0701;
+
+const H2_PRIORITY_FRAME_TYPE: u64 = 0x02;
+const H2_PING_FRAME_TYPE: u64 = 0x06;
+const H2_WINDOW_UPDATE_FRAME_TYPE: u64 = 0x08;
+const H2_CONTINUATION_FRAME_TYPE: u64 = 0x09;
+
+///The default maximum size of a frame payload.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: u64 = 16 * 1024 * 1024;
+
+const RESERVED_BASE: u64 = 0x21;
+const RESERVED_STEP: u64 = 0x1f;
+const MAX_RESERVED_N: u64 = 0x0210_8421_0842_1083;
+
+///Allows the use of the extended CONNECT, the value is 0 or 1.
+pub const SETTINGS_ENABLE_CONNECT_PROTOCOL: u64 = 0x08;
 
 #[inline(always)]
 fn check_capacity(capacity: u64) -> NonZeroUsize {
@@ -32,9 +56,19 @@ fn check_capacity(capacity: u64) -> NonZeroUsize {
 }
 
 #[inline(always)]
-fn fill_header(frame_type: u8, length: usize, o: &mut dyn PutU8) {
-    o.put_u8(frame_type);
-    encode_u64(length as u64, o)
+fn fill_header(frame_type: u64, length: usize, o: &mut dyn PutU8) -> Result<(), VarintError> {
+    let mut v = Vec::with_capacity(16);
+    varint::encode(frame_type, &mut v)?;
+    varint::encode(length as u64, &mut v)?;
+    o.put_exact(&v);
+    Ok(())
+}
+
+#[inline(always)]
+fn u64_to_var(n: u64) -> Result<Vec<u8>, VarintError> {
+    let mut v = Vec::with_capacity(8);
+    varint::encode(n, &mut v)?;
+    Ok(v)
 }
 
 ///Represents a DATA frame.
@@ -53,10 +87,11 @@ impl Data {
         }
     }
 
-    ///Exports self into [`PutU8`].
-    pub fn export(self, o: &mut dyn PutU8) {
-        fill_header(DATA_FRAME_TYPE, self.data.len(), o);
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
+        fill_header(DATA_FRAME_TYPE, self.data.len(), o)?;
         o.put_exact(&self.data);
+        Ok(())
     }
 }
 
@@ -76,10 +111,11 @@ impl Headers {
         }
     }
 
-    ///Exports self into [`PutU8`].
-    pub fn export(self, o: &mut dyn PutU8) {
-        fill_header(HEADERS_FRAME_TYPE, self.encoded_field_section.len(), o);
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
+        fill_header(HEADERS_FRAME_TYPE, self.encoded_field_section.len(), o)?;
         o.put_exact(&self.encoded_field_section);
+        Ok(())
     }
 }
 
@@ -96,11 +132,12 @@ impl CancelPush {
         Self { push_id }
     }
 
-    ///Exports self into [`PutU8`].
-    pub fn export(self, o: &mut dyn PutU8) {
-        let push_id = u64_to_var(self.push_id);
-        fill_header(CANCEL_PUSH_FRAME_TYPE, push_id.len(), o);
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
+        let push_id = u64_to_var(self.push_id)?;
+        fill_header(CANCEL_PUSH_FRAME_TYPE, push_id.len(), o)?;
         o.put_exact(&push_id);
+        Ok(())
     }
 }
 
@@ -120,15 +157,38 @@ impl Settings {
         }
     }
 
-    ///Exports self into [`PutU8`].
-    pub fn export(self, o: &mut dyn PutU8) {
+    ///Add SETTINGS_ENABLE_CONNECT_PROTOCOL.
+    pub fn push_enable_connect_protocol(&mut self, o: bool) {
+        self.setting
+            .push((SETTINGS_ENABLE_CONNECT_PROTOCOL, o as u64));
+    }
+
+    ///Returns the last value of SETTINGS_ENABLE_CONNECT_PROTOCOL, or None if absent.
+    ///Returns an error if the value is other than 0 or 1.
+    pub fn enable_connect_protocol(&self) -> Result<Option<bool>, &'static str> {
+        let r = self
+            .setting
+            .iter()
+            .rev()
+            .find(|(a, _)| *a == SETTINGS_ENABLE_CONNECT_PROTOCOL);
+        match r.map(|(_, b)| *b) {
+            Some(0) => Ok(Some(false)),
+            Some(1) => Ok(Some(true)),
+            Some(_) => Err("invalid SETTINGS_ENABLE_CONNECT_PROTOCOL"),
+            None => Ok(None),
+        }
+    }
+
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
         let mut v = Vec::with_capacity(self.setting.len() * 16);
         for (a, b) in self.setting {
-            encode_u64(a, &mut v);
-            encode_u64(b, &mut v);
+            varint::encode(a, &mut v)?;
+            varint::encode(b, &mut v)?;
         }
-        fill_header(SETTINGS_FRAME_TYPE, v.len(), o);
+        fill_header(SETTINGS_FRAME_TYPE, v.len(), o)?;
         o.put_exact(&v);
+        Ok(())
     }
 }
 
@@ -151,16 +211,17 @@ impl PushPromise {
         }
     }
 
-    ///Exports self into [`PutU8`].
-    pub fn export(self, o: &mut dyn PutU8) {
-        let push_id = u64_to_var(self.push_id);
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
+        let push_id = u64_to_var(self.push_id)?;
         fill_header(
             PUSH_PROMISE_FRAME_TYPE,
             push_id.len() + self.encoded_field_section.len(),
             o,
-        );
+        )?;
         o.put_exact(&push_id);
         o.put_exact(&self.encoded_field_section);
+        Ok(())
     }
 }
 
@@ -177,11 +238,12 @@ impl Goaway {
         Self { push_id }
     }
 
-    ///Exports self into [`PutU8`].
-    pub fn export(self, o: &mut dyn PutU8) {
-        let push_id = u64_to_var(self.push_id);
-        fill_header(GOAWAY_FRAME_TYPE, push_id.len(), o);
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
+        let push_id = u64_to_var(self.push_id)?;
+        fill_header(GOAWAY_FRAME_TYPE, push_id.len(), o)?;
         o.put_exact(&push_id);
+        Ok(())
     }
 }
 
@@ -198,11 +260,149 @@ impl MaxPushId {
         Self { push_id }
     }
 
-    ///Exports self into [`PutU8`].
-    pub fn export(self, o: &mut dyn PutU8) {
-        let push_id = u64_to_var(self.push_id);
-        fill_header(MAX_PUSH_ID_FRAME_TYPE, push_id.len(), o);
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
+        let push_id = u64_to_var(self.push_id)?;
+        fill_header(MAX_PUSH_ID_FRAME_TYPE, push_id.len(), o)?;
         o.put_exact(&push_id);
+        Ok(())
+    }
+}
+
+///Represents a PRIORITY_UPDATE frame, which signals the priority of a request stream or a push stream.
+#[derive(CopyGetters, Debug, Getters, MutGetters, Setters)]
+pub struct PriorityUpdate {
+    #[getset(get_copy = "pub", set = "pub")]
+    push: bool,
+    #[getset(get_copy = "pub", set = "pub")]
+    prioritized_element_id: u64,
+    #[getset(get = "pub", get_mut = "pub")]
+    priority_field_value: Vec<u8>,
+}
+
+impl PriorityUpdate {
+    ///Creates. The prioritized element is a request stream id, or a push id if push is true.
+    pub fn new(push: bool, prioritized_element_id: u64, priority: &PriorityParams) -> Self {
+        let mut priority_field_value = Vec::new();
+        priority.export(&mut priority_field_value);
+        Self {
+            push,
+            prioritized_element_id,
+            priority_field_value,
+        }
+    }
+
+    ///Parses priority field value.
+    ///Returns an error if the field value can't be parsed, then the field value should be ignored.
+    pub fn priority(&self) -> Result<PriorityParams, &'static str> {
+        PriorityParams::parse(&self.priority_field_value)
+    }
+
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
+        let id = u64_to_var(self.prioritized_element_id)?;
+        let frame_type = if self.push {
+            PRIORITY_UPDATE_PUSH_FRAME_TYPE
+        } else {
+            PRIORITY_UPDATE_REQUEST_FRAME_TYPE
+        };
+        fill_header(frame_type, id.len() + self.priority_field_value.len(), o)?;
+        o.put_exact(&id);
+        o.put_exact(&self.priority_field_value);
+        Ok(())
+    }
+}
+
+///Represents a frame of unknown type, which is ignored by the peer.
+#[derive(CopyGetters, Debug, Deref, DerefMut, Setters)]
+pub struct Unknown {
+    #[getset(get_copy = "pub", set = "pub")]
+    ty: u64,
+    #[deref]
+    #[deref_mut]
+    payload: FiniteVec,
+}
+
+impl Unknown {
+    ///Creates.
+    pub fn new(ty: u64, capacity: u64) -> Self {
+        Self {
+            ty,
+            payload: check_capacity(capacity).into(),
+        }
+    }
+
+    ///Creates a frame of reserved type, 0x1f * N + 0x21. N is limited to keep the value in the range of varint.
+    pub fn grease(n: u64, capacity: u64) -> Self {
+        Self::new(
+            RESERVED_STEP * n.min(MAX_RESERVED_N) + RESERVED_BASE,
+            capacity,
+        )
+    }
+
+    ///Exports self into [`PutU8`]. Returns an error if an integer exceeds the range of varint, then nothing is exported.
+    pub fn export(self, o: &mut dyn PutU8) -> Result<(), VarintError> {
+        fill_header(self.ty, self.payload.len(), o)?;
+        o.put_exact(&self.payload);
+        Ok(())
+    }
+}
+
+///Returns true if the frame type is reserved, 0x1f * N + 0x21.
+pub fn is_reserved_type(ty: u64) -> bool {
+    ty >= RESERVED_BASE && (ty - RESERVED_BASE).is_multiple_of(RESERVED_STEP)
+}
+
+///Represents an error of parsing frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameError {
+    ///Needs n more bytes to parse a frame, the input is not consumed.
+    Incomplete(usize),
+    ///A frame payload exceeds the maximum, treated as H3_EXCESSIVE_LOAD.
+    TooLarge(u64),
+    ///A frame type of HTTP/2 which has no equivalent in HTTP/3, treated as H3_FRAME_UNEXPECTED.
+    Unexpected(u64),
+    ///A frame fails to satisfy layout requirements, treated as H3_FRAME_ERROR.
+    Malformed(&'static str),
+}
+
+impl FrameError {
+    ///Returns the error code to signal, or None if incomplete.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Incomplete(_) => None,
+            Self::TooLarge(_) => Some(ErrorCode::ExcessiveLoad),
+            Self::Unexpected(_) => Some(ErrorCode::FrameUnexpected),
+            Self::Malformed(_) => Some(ErrorCode::FrameError),
+        }
+    }
+}
+
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Incomplete(n) => write!(f, "needs {n} more bytes"),
+            Self::TooLarge(n) => write!(f, "frame too large: {n}"),
+            Self::Unexpected(n) => write!(f, "unexpected frame type: {n:#x}"),
+            Self::Malformed(s) => f.write_str(s),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<varint::VarintError> for FrameError {
+    fn from(o: varint::VarintError) -> Self {
+        match o {
+            varint::VarintError::Incomplete(n) => Self::Incomplete(n),
+            _ => Self::Malformed("invalid integer"),
+        }
+    }
+}
+
+impl From<&'static str> for FrameError {
+    fn from(o: &'static str) -> Self {
+        Self::Malformed(o)
     }
 }
 
@@ -216,79 +416,133 @@ pub enum FrameResult {
     PushPromise(PushPromiseResult),
     Goaway(Goaway),
     MaxPushId(MaxPushId),
+    PriorityUpdate(PriorityUpdate),
+    Unknown(UnknownResult),
+}
+
+///A parser of frames with the maximum frame payload size.
+#[derive(Clone, Copy, CopyGetters, Debug, Setters)]
+pub struct FrameParser {
+    #[getset(get_copy = "pub", set = "pub")]
+    max_payload_size: u64,
+}
+
+impl Default for FrameParser {
+    fn default() -> Self {
+        Self {
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+        }
+    }
+}
+
+impl FrameParser {
+    ///Parses bytes. Returns a frame, or error. A frame of unknown type is returned as [`FrameResult::Unknown`].
+    ///
+    ///Returns [`FrameError::Incomplete`] with the number of bytes needed if the frame is truncated,
+    ///and [`FrameError::TooLarge`] if the payload exceeds the maximum, the input is not consumed on error.
+    pub fn parse(&self, o: &mut dyn GetU8) -> Result<FrameResult, FrameError> {
+        let i = o.index();
+        let r = get_frame_inner(o, self.max_payload_size);
+        if r.is_err() {
+            o.set_index(i);
+        }
+        r
+    }
 }
 
-///Parses bytes. Returns a frame, or error.
-pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, &str> {
-    let i = decode_var(o).ok_or("empty")?;
-    if i >= 256 {
-        return Err("invalid type");
+///Parses bytes. Returns a frame, or error. A frame of unknown type is returned as [`FrameResult::Unknown`].
+///
+///The payload size is not limited, use [`FrameParser`] to limit it. The input is not consumed on error.
+pub fn get_frame(o: &mut dyn GetU8) -> Result<FrameResult, FrameError> {
+    let i = o.index();
+    let r = get_frame_inner(o, u64::MAX);
+    if r.is_err() {
+        o.set_index(i);
     }
-    let length = decode_var(o).ok_or("invalid length")?;
-    match i as u8 {
+    r
+}
+
+fn get_frame_inner(o: &mut dyn GetU8, max: u64) -> Result<FrameResult, FrameError> {
+    let i = varint::decode(o)?;
+    let length = varint::decode(o)?;
+    if length > max {
+        return Err(FrameError::TooLarge(length));
+    }
+    let length = usize::try_from(length).map_err(|_| FrameError::TooLarge(length))?;
+    let n = o.surplus();
+    if n < length {
+        return Err(FrameError::Incomplete(length - n));
+    }
+    let i_b = o.index();
+    let i_c = i_b + length;
+    let r = match i {
         DATA_FRAME_TYPE => {
-            let i_b = o.index();
-            let i_c = i_b + length as usize;
             o.set_index(i_c);
-            Ok(FrameResult::Data(DataResult(i_b, i_c)))
+            FrameResult::Data(DataResult(i_b, i_c))
         }
         HEADERS_FRAME_TYPE => {
-            let i_b = o.index();
-            let i_c = i_b + length as usize;
             o.set_index(i_c);
-            Ok(FrameResult::Headers(HeadersResult(i_b, i_c)))
+            FrameResult::Headers(HeadersResult(i_b, i_c))
         }
         CANCEL_PUSH_FRAME_TYPE => {
-            if length == 0 {
-                return Err("shortage");
-            }
-            let k = o.get_exact(length as usize).ok_or("shortage")?;
-            let push_id = decode_slice_var(k).ok_or("invalid integer")?;
-            Ok(FrameResult::CancelPush(CancelPush { push_id }))
+            let push_id = get_single_var(o, length)?;
+            FrameResult::CancelPush(CancelPush { push_id })
         }
         SETTINGS_FRAME_TYPE => {
             let mut setting = Vec::new();
             if length > 0 {
-                let mut k = o.get_exact_to(length as usize).ok_or("shortage")?;
+                let mut k = o.get_exact_to(length).ok_or("shortage")?;
                 let k = k.as_mut();
                 while k.is_surplus() {
-                    let a = decode_var(k).ok_or("invalid integer")?;
-                    let b = decode_var(k).ok_or("invalid integer")?;
+                    let a = varint::decode(k).map_err(|_| "invalid integer")?;
+                    let b = varint::decode(k).map_err(|_| "invalid integer")?;
                     setting.push((a, b));
                 }
             }
-            Ok(FrameResult::Settings(Settings { setting }))
+            FrameResult::Settings(Settings { setting })
         }
         PUSH_PROMISE_FRAME_TYPE => {
-            if length == 0 {
-                return Err("shortage");
-            }
-            let i_b = o.index();
-            let i_c = i_b + length as usize;
-            let k = o.get_exact(length as usize).ok_or("shortage")?;
-            let push_id = decode_slice_var(k).ok_or("invalid integer")?;
-            o.set_index(i_c);
-            Ok(FrameResult::PushPromise(PushPromiseResult(
-                push_id, i_b, i_c,
-            )))
+            let k = o.get_exact(length).ok_or("shortage")?;
+            let (push_id, n) = varint::decode_slice(k).map_err(|_| "invalid integer")?;
+            FrameResult::PushPromise(PushPromiseResult(push_id, i_b + n, i_c))
         }
         GOAWAY_FRAME_TYPE => {
-            if length == 0 {
-                return Err("shortage");
-            }
-            let k = o.get_exact(length as usize).ok_or("shortage")?;
-            let push_id = decode_slice_var(k).ok_or("invalid integer")?;
-            Ok(FrameResult::Goaway(Goaway { push_id }))
+            let push_id = get_single_var(o, length)?;
+            FrameResult::Goaway(Goaway { push_id })
         }
         MAX_PUSH_ID_FRAME_TYPE => {
-            if length == 0 {
-                return Err("shortage");
-            }
-            let k = o.get_exact(length as usize).ok_or("shortage")?;
-            let push_id = decode_slice_var(k).ok_or("invalid integer")?;
-            Ok(FrameResult::MaxPushId(MaxPushId { push_id }))
+            let push_id = get_single_var(o, length)?;
+            FrameResult::MaxPushId(MaxPushId { push_id })
+        }
+        PRIORITY_UPDATE_REQUEST_FRAME_TYPE | PRIORITY_UPDATE_PUSH_FRAME_TYPE => {
+            let mut k = o.get_exact_to(length).ok_or("shortage")?;
+            let k = k.as_mut();
+            let prioritized_element_id = varint::decode(k).map_err(|_| "invalid integer")?;
+            let priority_field_value = k.get_surplus().to_vec();
+            FrameResult::PriorityUpdate(PriorityUpdate {
+                push: i == PRIORITY_UPDATE_PUSH_FRAME_TYPE,
+                prioritized_element_id,
+                priority_field_value,
+            })
+        }
+        H2_PRIORITY_FRAME_TYPE
+        | H2_PING_FRAME_TYPE
+        | H2_WINDOW_UPDATE_FRAME_TYPE
+        | H2_CONTINUATION_FRAME_TYPE => return Err(FrameError::Unexpected(i)),
+        ty => {
+            o.set_index(i_c);
+            FrameResult::Unknown(UnknownResult(ty, i_b, i_c))
         }
-        _ => Err("invalid type"),
+    };
+    Ok(r)
+}
+
+#[inline(always)]
+fn get_single_var(o: &mut dyn GetU8, length: usize) -> Result<u64, FrameError> {
+    let k = o.get_exact(length).ok_or("shortage")?;
+    match varint::decode_slice(k) {
+        Ok((n, m)) if m == length => Ok(n),
+        _ => Err(FrameError::Malformed("payload is not a single integer")),
     }
 }
 
@@ -365,3 +619,150 @@ impl PushPromiseResult {
         })
     }
 }
+
+///Represents a parsed frame of unknown type, which should be ignored.
+pub struct UnknownResult(u64, usize, usize);
+
+impl UnknownResult {
+    ///Returns the frame type.
+    pub fn ty(&self) -> u64 {
+        self.0
+    }
+
+    ///Returns true if the frame type is reserved.
+    pub fn is_reserved(&self) -> bool {
+        is_reserved_type(self.0)
+    }
+
+    ///Returns frame payload.
+    pub fn payload<'a>(&self, o: &'a mut dyn GetU8) -> Option<Box<dyn GetU8 + 'a>> {
+        o.sub_to(self.1, self.2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priority_update() {
+        let o = PriorityUpdate::new(false, 4, &PriorityParams::new(0, false));
+        let mut v = Vec::new();
+        o.export(&mut v).unwrap();
+        assert_eq!(&v[..4], &[0x80, 0x0f, 0x07, 0x00]);
+
+        let mut v = v.into_get();
+        match get_frame(&mut v) {
+            Ok(FrameResult::PriorityUpdate(o)) => {
+                assert!(!o.push());
+                assert_eq!(o.prioritized_element_id(), 4);
+                assert_eq!(o.priority(), Ok(PriorityParams::new(0, false)));
+            }
+            _ => panic!("priority update"),
+        }
+    }
+
+    #[test]
+    fn unknown() {
+        let mut v = Vec::new();
+        let mut o = Unknown::grease(3, 4);
+        o.put_exact(b"abcd");
+        o.export(&mut v).unwrap();
+        Unknown::new(0x1234, 0).export(&mut v).unwrap();
+        MaxPushId::new(1).export(&mut v).unwrap();
+        v.extend_from_slice(&[0x06, 0x08, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut g = v.into_get();
+        match get_frame(&mut g) {
+            Ok(FrameResult::Unknown(o)) => {
+                assert_eq!(o.ty(), 0x1f * 3 + 0x21);
+                assert!(o.is_reserved());
+                assert_eq!(o.payload(&mut g).unwrap().get_surplus(), b"abcd");
+            }
+            _ => panic!("unknown"),
+        }
+        match get_frame(&mut g) {
+            Ok(FrameResult::Unknown(o)) => assert!(!o.is_reserved()),
+            _ => panic!("unknown"),
+        }
+        assert!(matches!(get_frame(&mut g), Ok(FrameResult::MaxPushId(_))));
+        let e = get_frame(&mut g).err().unwrap();
+        assert_eq!(e, FrameError::Unexpected(0x06));
+        assert_eq!(e.error_code(), Some(ErrorCode::FrameUnexpected));
+        assert!(is_reserved_type(0x21));
+        assert!(!is_reserved_type(0x20));
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut v = Vec::new();
+        let n = varint::MAX_VARINT + 1;
+        assert_eq!(
+            Goaway::new(n).export(&mut v),
+            Err(VarintError::OutOfRange(n))
+        );
+        let mut o = Settings::new(1);
+        o.push((0x21, n));
+        assert!(o.export(&mut v).is_err());
+        assert!(Unknown::new(n, 0).export(&mut v).is_err());
+        assert!(v.is_empty());
+        Goaway::new(varint::MAX_VARINT).export(&mut v).unwrap();
+        match get_frame(&mut v.as_slice().into_get()) {
+            Ok(FrameResult::Goaway(o)) => assert_eq!(o.push_id(), varint::MAX_VARINT),
+            _ => panic!("goaway"),
+        }
+    }
+
+    #[test]
+    fn partial() {
+        let mut v = Vec::new();
+        let mut o = PushPromise::new(3);
+        o.set_push_id(64);
+        o.put_exact(b"abc");
+        o.export(&mut v).unwrap();
+        for n in 0..v.len() {
+            let mut g = v[..n].into_get();
+            assert!(matches!(get_frame(&mut g), Err(FrameError::Incomplete(_))));
+            assert_eq!(g.index(), 0);
+        }
+        let mut g = v.as_slice().into_get();
+        match get_frame(&mut g) {
+            Ok(FrameResult::PushPromise(o)) => {
+                assert_eq!(
+                    o.encoded_field_section(&mut g).unwrap().get_surplus(),
+                    b"abc"
+                );
+            }
+            _ => panic!("push promise"),
+        }
+
+        let mut p = FrameParser::default();
+        p.set_max_payload_size(2);
+        let mut g = v.as_slice().into_get();
+        assert_eq!(p.parse(&mut g).err(), Some(FrameError::TooLarge(5)));
+        let s = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let n = (1 << 62) - 1;
+        let e = get_frame(&mut s.as_slice().into_get()).err().unwrap();
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(e, FrameError::Incomplete(n as usize));
+        #[cfg(not(target_pointer_width = "64"))]
+        assert_eq!(e, FrameError::TooLarge(n));
+        p.set_max_payload_size(n - 1);
+        let mut g = s.as_slice().into_get();
+        assert_eq!(p.parse(&mut g).err(), Some(FrameError::TooLarge(n)));
+        assert_eq!(g.index(), 0);
+
+        for s in [
+            &[0x07, 0x02, 0x01, 0x02][..],
+            &[0x0d, 0x00],
+            &[0x03, 0x01, 0x40],
+        ] {
+            let e = get_frame(&mut s.into_get()).err().unwrap();
+            assert_eq!(e.error_code(), Some(ErrorCode::FrameError));
+        }
+        assert!(matches!(
+            get_frame(&mut [0x07, 0x02, 0x40, 0x01].as_slice().into_get()),
+            Ok(FrameResult::Goaway(o)) if o.push_id() == 1
+        ));
+    }
+}
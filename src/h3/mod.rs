@@ -9,9 +9,11 @@ and several assist types and traits for helping to use these functions.
 
 */
 mod assist;
+pub mod error;
 pub mod frame;
 mod prty;
 pub mod qpack;
+pub mod varint;
 
 use crate::io::*;
 use crate::prty::*;
@@ -28,6 +30,8 @@ pub const PSEUDO_AUTHORITY: &[u8] = b":authority";
 pub const PSEUDO_PATH: &[u8] = b":path";
 ///The ":status" pseudo-header field.
 pub const PSEUDO_STATUS: &[u8] = b":status";
+///The ":protocol" pseudo-header field, used by the extended CONNECT.
+pub const PSEUDO_PROTOCOL: &[u8] = b":protocol";
 
 ///Represents an HTTP/3 request.
 #[derive(Debug, Default, Deref, DerefMut, Getters, MutGetters)]
@@ -40,6 +44,8 @@ pub struct H3Request {
     authority: Option<FieldValue>,
     #[getset(get = "pub", get_mut = "pub")]
     path: Option<FieldValue>,
+    #[getset(get = "pub", get_mut = "pub")]
+    protocol: Option<FieldValue>,
     #[deref]
     #[deref_mut]
     headers_body: Entity,
@@ -53,6 +59,7 @@ impl H3Request {
             scheme: None,
             authority: None,
             path: None,
+            protocol: None,
             headers_body: Default::default(),
         }
     }
@@ -72,6 +79,11 @@ impl H3Request {
         self.path = value.map(|o| o.into());
     }
 
+    ///Sets protocol.
+    pub fn set_protocol(&mut self, value: Option<impl Into<FieldValue>>) {
+        self.protocol = value.map(|o| o.into());
+    }
+
     ///Sets a pseudo-header field.
     pub fn set_pseudo(&mut self, name: &[u8], value: impl Into<FieldValue>) {
         let value = value.into();
@@ -88,10 +100,15 @@ impl H3Request {
             PSEUDO_PATH => {
                 self.path.replace(value);
             }
+            PSEUDO_PROTOCOL => {
+                self.protocol.replace(value);
+            }
             _ => {}
         }
     }
 
+    request_pseudo!();
+
     ///Returns a static table index value of ":method".
     pub fn indexed_method(&self) -> IndexRef<'_> {
         let o = self.method.as_bytes();
@@ -171,6 +188,8 @@ impl H3Response {
         }
     }
 
+    response_pseudo!();
+
     ///Returns a static table index value of ":status".
     pub fn indexed_status(&self) -> IndexRef<'_> {
         let o = self.status.as_bytes();
@@ -0,0 +1,164 @@
+/*!
+Utilities for the QUIC variable-length integer.
+
+A variable-length integer is encoded in 1, 2, 4 or 8 bytes, the two most significant bits of the first byte are the base-2 logarithm of the length,
+and the remaining bits are the value in network byte order. The maximum value is [`MAX_VARINT`].
+
+[`decode`] accepts any length, and [`decode_minimal`] rejects the encodings which are longer than necessary.
+If the input is truncated, an error tells how many more bytes are needed, and the input is not consumed.
+
+# Examples
+```
+use httpenergy::h3::varint::*;
+use httpenergy::*;
+
+let mut v = Vec::new();
+assert_eq!(encode(15293, &mut v), Ok(2));
+assert_eq!(v, [0x7b, 0xbd]);
+assert_eq!(encoded_len(15293), Ok(2));
+assert_eq!(decode(&mut v.as_slice().into_get()), Ok(15293));
+
+assert_eq!(decode(&mut [0x80, 0x01].as_slice().into_get()), Err(VarintError::Incomplete(2)));
+assert_eq!(decode_minimal(&mut [0x40, 0x25].as_slice().into_get()), Err(VarintError::NonMinimal(0x25)));
+assert_eq!(encode(1 << 62, &mut v), Err(VarintError::OutOfRange(1 << 62)));
+```
+*/
+
+use crate::io::*;
+
+///The maximum value of a variable-length integer, which is 2^62-1.
+pub const MAX_VARINT: u64 = (1 << 62) - 1;
+
+const USABLE_BITS: u8 = 0b0011_1111;
+
+///Represents an error of encoding or decoding a variable-length integer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VarintError {
+    ///Needs n more bytes to decode, the input is not consumed.
+    Incomplete(usize),
+    ///The value exceeds [`MAX_VARINT`] and can't be encoded.
+    OutOfRange(u64),
+    ///The value is encoded in more bytes than necessary, the input is consumed.
+    NonMinimal(u64),
+}
+
+impl std::fmt::Display for VarintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Incomplete(n) => write!(f, "needs {n} more bytes"),
+            Self::OutOfRange(n) => write!(f, "out of range: {n}"),
+            Self::NonMinimal(n) => write!(f, "non-minimal encoding: {n}"),
+        }
+    }
+}
+
+impl std::error::Error for VarintError {}
+
+///Returns the number of bytes of the minimal encoding. Returns an error if the value exceeds [`MAX_VARINT`].
+#[inline]
+pub fn encoded_len(n: u64) -> Result<usize, VarintError> {
+    match n {
+        0..=63 => Ok(1),
+        64..=16383 => Ok(2),
+        16384..=1073741823 => Ok(4),
+        1073741824..=MAX_VARINT => Ok(8),
+        _ => Err(VarintError::OutOfRange(n)),
+    }
+}
+
+///Returns true if the length is the minimal one to encode the value.
+#[inline]
+pub fn is_minimal(n: u64, len: usize) -> bool {
+    encoded_len(n) == Ok(len)
+}
+
+///Encodes a value in the minimal length into [`PutU8`]. Returns the number of bytes.
+///Returns an error if the value exceeds [`MAX_VARINT`], then nothing is written.
+#[inline]
+pub fn encode(n: u64, o: &mut dyn PutU8) -> Result<usize, VarintError> {
+    let len = encoded_len(n)?;
+    let mut v = n.to_be_bytes();
+    let s = &mut v[8 - len..];
+    s[0] |= (len.trailing_zeros() as u8) << 6;
+    o.put_exact(s);
+    Ok(len)
+}
+
+///Decodes a value from [`GetU8`], any length is accepted.
+///Returns an error if the input is truncated, then the input is not consumed.
+#[inline]
+pub fn decode(o: &mut dyn GetU8) -> Result<u64, VarintError> {
+    decode_len(o).map(|(n, _)| n)
+}
+
+///Decodes a value from [`GetU8`], a length longer than necessary is an error.
+///Returns an error if the input is truncated, then the input is not consumed.
+#[inline]
+pub fn decode_minimal(o: &mut dyn GetU8) -> Result<u64, VarintError> {
+    let (n, len) = decode_len(o)?;
+    if !is_minimal(n, len) {
+        return Err(VarintError::NonMinimal(n));
+    }
+    Ok(n)
+}
+
+///Decodes a value from the beginning of a slice. Returns the value and the number of bytes.
+#[inline]
+pub fn decode_slice(s: &[u8]) -> Result<(u64, usize), VarintError> {
+    decode_len(&mut s.into_get())
+}
+
+#[inline(always)]
+fn decode_len(o: &mut dyn GetU8) -> Result<(u64, usize), VarintError> {
+    let i = o.index();
+    let b = o.get_u8().ok_or(VarintError::Incomplete(1))?;
+    let len = 1 << (b >> 6);
+    let Some(s) = o.get_exact(len - 1) else {
+        let n = o.surplus();
+        o.set_index(i);
+        return Err(VarintError::Incomplete(len - 1 - n));
+    };
+    let n = s
+        .iter()
+        .fold((b & USABLE_BITS) as u64, |n, &b| (n << 8) | b as u64);
+    Ok((n, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint() {
+        let v: [(&[u8], u64); 4] = [
+            (
+                &[0xc2, 0x19, 0x7c, 0x5e, 0xff, 0x14, 0xe8, 0x8c],
+                151288809941952652,
+            ),
+            (&[0x9d, 0x7f, 0x3e, 0x7d], 494878333),
+            (&[0x7b, 0xbd], 15293),
+            (&[0x25], 37),
+        ];
+        for (s, n) in v {
+            assert_eq!(decode_slice(s), Ok((n, s.len())));
+            let mut a = Vec::new();
+            assert_eq!(encode(n, &mut a), Ok(s.len()));
+            assert_eq!(a, s);
+        }
+        let mut a = Vec::new();
+        assert_eq!(encode(MAX_VARINT, &mut a), Ok(8));
+        assert_eq!(decode_slice(&a), Ok((MAX_VARINT, 8)));
+        assert!(encode(MAX_VARINT + 1, &mut a).is_err());
+        assert_eq!(a.len(), 8);
+
+        let mut g = [0xc0, 0, 0, 0, 0, 0, 0].as_slice().into_get();
+        assert_eq!(decode(&mut g), Err(VarintError::Incomplete(1)));
+        assert_eq!(g.index(), 0);
+        assert_eq!(decode_slice(&[]), Err(VarintError::Incomplete(1)));
+        let mut g = [0x40, 0x25, 0x25].as_slice().into_get();
+        assert_eq!(decode_minimal(&mut g), Err(VarintError::NonMinimal(37)));
+        assert_eq!(decode_minimal(&mut g), Ok(37));
+        assert!(is_minimal(64, 2));
+        assert!(!is_minimal(63, 2));
+    }
+}
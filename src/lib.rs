@@ -40,19 +40,28 @@ assert_eq!("GET", r.method());
 
 #![allow(dead_code)]
 
+mod altsvc;
 mod common;
+#[macro_use]
+mod validate;
 pub mod h2;
 pub mod h3;
 mod io;
+mod priority;
 mod prty;
 #[macro_use]
 mod request;
 mod response;
+mod table;
 
+pub use altsvc::*;
 pub use io::*;
+pub use priority::*;
 pub use prty::*;
 pub use request::*;
 pub use response::*;
+pub use table::*;
+pub use validate::*;
 
 #[cfg(test)]
 mod tests {
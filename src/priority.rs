@@ -0,0 +1,350 @@
+use crate::io::*;
+use crate::prty::*;
+
+///The "priority" header field.
+pub const PRIORITY: &[u8] = b"priority";
+
+const DEFAULT_URGENCY: u8 = 3;
+const MAX_URGENCY: u8 = 7;
+
+const INVALID_PRIORITY: &str = "invalid priority field value";
+
+///Represents the priority parameters of the Extensible Prioritization Scheme.
+///
+///The urgency ("u") is an integer between 0 and 7, a lower value is a higher priority, 3 by default.
+///The incremental ("i") is a boolean, indicates whether the response can be processed incrementally, false by default.
+#[derive(Clone, Copy, CopyGetters, Debug, Eq, PartialEq, Setters)]
+#[getset(get_copy = "pub")]
+pub struct PriorityParams {
+    urgency: u8,
+    #[getset(set = "pub")]
+    incremental: bool,
+}
+
+impl Default for PriorityParams {
+    fn default() -> Self {
+        Self {
+            urgency: DEFAULT_URGENCY,
+            incremental: false,
+        }
+    }
+}
+
+impl PriorityParams {
+    ///Creates. The urgency is limited to 7.
+    pub fn new(urgency: u8, incremental: bool) -> Self {
+        Self {
+            urgency: urgency.min(MAX_URGENCY),
+            incremental,
+        }
+    }
+
+    ///Sets urgency. The urgency is limited to 7.
+    pub fn set_urgency(&mut self, o: u8) {
+        self.urgency = o.min(MAX_URGENCY);
+    }
+
+    ///Parses a priority field value, a structured field dictionary.
+    ///Unknown parameters and parameters with invalid values are ignored.
+    ///Returns an error if the field value can't be parsed, then the field value should be ignored.
+    pub fn parse(value: &[u8]) -> Result<Self, &'static str> {
+        let mut r = Self::default();
+        r.update(value)?;
+        Ok(r)
+    }
+
+    ///Returns the priority parameters from the "priority" header field, or defaults if absent or can't be parsed.
+    pub fn from_fields(fields: &Fields) -> Self {
+        let mut r = Self::default();
+        if let Some(v) = fields.get(&FieldName::from(PRIORITY)) {
+            let mut s = v.one().as_bytes().to_vec();
+            for o in v.rest() {
+                s.extend_from_slice(b", ");
+                s.extend_from_slice(o.as_bytes());
+            }
+            if r.update(&s).is_err() {
+                r = Self::default();
+            }
+        }
+        r
+    }
+
+    fn update(&mut self, value: &[u8]) -> Result<(), &'static str> {
+        let mut p = Sf(value, 0);
+        p.skip_sp();
+        if p.is_end() {
+            return Ok(());
+        }
+        let mut urgency = None;
+        let mut incremental = None;
+        loop {
+            let key = p.key()?;
+            let item = if p.eat(b'=') {
+                if p.peek() == Some(b'(') {
+                    p.inner_list()?;
+                    None
+                } else {
+                    Some(p.bare_item()?)
+                }
+            } else {
+                Some(Item::Boolean(true))
+            };
+            p.params()?;
+            match key {
+                b"u" => urgency = Some(item),
+                b"i" => incremental = Some(item),
+                _ => {}
+            }
+            p.skip_ows();
+            if p.is_end() {
+                break;
+            }
+            if !p.eat(b',') {
+                return Err(INVALID_PRIORITY);
+            }
+            p.skip_ows();
+            if p.is_end() {
+                return Err(INVALID_PRIORITY);
+            }
+        }
+        if let Some(Some(Item::Integer(n))) = urgency
+            && (0..=MAX_URGENCY as i64).contains(&n)
+        {
+            self.urgency = n as u8;
+        }
+        if let Some(Some(Item::Boolean(o))) = incremental {
+            self.incremental = o;
+        }
+        Ok(())
+    }
+
+    ///Exports a priority field value. Default parameters are omitted.
+    pub fn export(&self, o: &mut dyn PutU8) {
+        if self.urgency != DEFAULT_URGENCY {
+            o.put_exact(b"u=");
+            o.put_u8(b'0' + self.urgency);
+        }
+        if self.incremental {
+            if self.urgency != DEFAULT_URGENCY {
+                o.put_exact(b", ");
+            }
+            o.put_u8(b'i');
+        }
+    }
+
+    ///Returns a priority field value.
+    pub fn to_field_value(&self) -> FieldValue {
+        let mut v = Vec::new();
+        self.export(&mut v);
+        FieldValue::owned(v)
+    }
+}
+
+enum Item {
+    Integer(i64),
+    Boolean(bool),
+    Other,
+}
+
+///A parser of structured field values.
+struct Sf<'a>(&'a [u8], usize);
+
+impl<'a> Sf<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.0.get(self.1).copied()
+    }
+
+    fn is_end(&self) -> bool {
+        self.1 >= self.0.len()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let r = self.peek() == Some(b);
+        if r {
+            self.1 += 1;
+        }
+        r
+    }
+
+    fn skip_sp(&mut self) {
+        while self.peek() == Some(b' ') {
+            self.1 += 1;
+        }
+    }
+
+    fn skip_ows(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.1 += 1;
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &'a [u8] {
+        let a = self.1;
+        while self.peek().is_some_and(&f) {
+            self.1 += 1;
+        }
+        &self.0[a..self.1]
+    }
+
+    fn key(&mut self) -> Result<&'a [u8], &'static str> {
+        match self.peek() {
+            Some(b'a'..=b'z' | b'*') => Ok(self.take_while(
+                |b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' | b'*'),
+            )),
+            _ => Err(INVALID_PRIORITY),
+        }
+    }
+
+    fn params(&mut self) -> Result<(), &'static str> {
+        while self.eat(b';') {
+            self.skip_sp();
+            self.key()?;
+            if self.eat(b'=') {
+                self.bare_item()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn inner_list(&mut self) -> Result<(), &'static str> {
+        self.1 += 1;
+        loop {
+            self.skip_sp();
+            if self.eat(b')') {
+                return self.params();
+            }
+            self.bare_item()?;
+            self.params()?;
+            match self.peek() {
+                Some(b' ' | b')') => {}
+                _ => return Err(INVALID_PRIORITY),
+            }
+        }
+    }
+
+    fn bare_item(&mut self) -> Result<Item, &'static str> {
+        match self.peek() {
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'?') => {
+                self.1 += 1;
+                match self.peek() {
+                    Some(b'0') => {
+                        self.1 += 1;
+                        Ok(Item::Boolean(false))
+                    }
+                    Some(b'1') => {
+                        self.1 += 1;
+                        Ok(Item::Boolean(true))
+                    }
+                    _ => Err(INVALID_PRIORITY),
+                }
+            }
+            Some(b'"') => {
+                self.1 += 1;
+                loop {
+                    match self.peek() {
+                        Some(b'\\') => {
+                            self.1 += 1;
+                            if !matches!(self.peek(), Some(b'"' | b'\\')) {
+                                return Err(INVALID_PRIORITY);
+                            }
+                        }
+                        Some(b'"') => {
+                            self.1 += 1;
+                            return Ok(Item::Other);
+                        }
+                        Some(0x20..=0x7e) => {}
+                        _ => return Err(INVALID_PRIORITY),
+                    }
+                    self.1 += 1;
+                }
+            }
+            Some(b':') => {
+                self.1 += 1;
+                self.take_while(|b| {
+                    b.is_ascii_alphanumeric() || b == b'+' || b == b'/' || b == b'='
+                });
+                if self.eat(b':') {
+                    Ok(Item::Other)
+                } else {
+                    Err(INVALID_PRIORITY)
+                }
+            }
+            Some(b'A'..=b'Z' | b'a'..=b'z' | b'*') => {
+                self.take_while(|b| {
+                    b.is_ascii_graphic()
+                        && !matches!(b, b'"' | b'(' | b')' | b',' | b';' | b'=' | b'\\')
+                        && !matches!(b, b'[' | b']' | b'{' | b'}' | b'<' | b'>' | b'?' | b'@')
+                });
+                Ok(Item::Other)
+            }
+            _ => Err(INVALID_PRIORITY),
+        }
+    }
+
+    fn number(&mut self) -> Result<Item, &'static str> {
+        let negative = self.eat(b'-');
+        let a = self.take_while(|b| b.is_ascii_digit());
+        if a.is_empty() || a.len() > 15 {
+            return Err(INVALID_PRIORITY);
+        }
+        if self.eat(b'.') {
+            let b = self.take_while(|b| b.is_ascii_digit());
+            if b.is_empty() || b.len() > 3 || a.len() > 12 {
+                return Err(INVALID_PRIORITY);
+            }
+            return Ok(Item::Other);
+        }
+        let n = a.iter().fold(0i64, |n, b| n * 10 + (b - b'0') as i64);
+        Ok(Item::Integer(if negative { -n } else { n }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params() {
+        assert_eq!(PriorityParams::parse(b""), Ok(PriorityParams::default()));
+        assert_eq!(
+            PriorityParams::parse(b"u=5, i"),
+            Ok(PriorityParams::new(5, true))
+        );
+        assert_eq!(
+            PriorityParams::parse(b"i=?0, u=1;a=b, foo=(1 2), bar=\"x\""),
+            Ok(PriorityParams::new(1, false))
+        );
+        assert_eq!(
+            PriorityParams::parse(b"u=9, i=1"),
+            Ok(PriorityParams::default())
+        );
+        assert_eq!(
+            PriorityParams::parse(b"u=1, u=9"),
+            Ok(PriorityParams::default())
+        );
+        assert_eq!(
+            PriorityParams::parse(b"u=1, u"),
+            Ok(PriorityParams::default())
+        );
+        assert_eq!(
+            PriorityParams::parse(b"u=9, u=1, i, i=?0"),
+            Ok(PriorityParams::new(1, false))
+        );
+        assert!(PriorityParams::parse(b"u=1,").is_err());
+        assert!(PriorityParams::parse(b"U=1").is_err());
+
+        let mut v = Vec::new();
+        PriorityParams::new(0, true).export(&mut v);
+        assert_eq!(v, b"u=0, i");
+        assert_eq!(PriorityParams::default().to_field_value(), "");
+
+        let mut f = Fields::default();
+        f.add_field("priority", "u=2");
+        f.add_field("priority", "i");
+        assert_eq!(
+            PriorityParams::from_fields(&f),
+            PriorityParams::new(2, true)
+        );
+    }
+}
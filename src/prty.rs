@@ -13,6 +13,8 @@ pub struct Entity {
     #[debug("{}", body.len())]
     #[getset(get = "pub", get_mut = "pub")]
     body: Vec<u8>,
+    #[getset(get = "pub", get_mut = "pub")]
+    trailers: Fields,
     #[getset(get_copy = "pub", set = "pub(crate)")]
     err: bool,
 }
@@ -59,6 +61,7 @@ impl Fields {
 pub struct FieldValues {
     #[getset(get = "pub(crate)", get_mut = "pub(crate)")]
     one: FieldValue,
+    #[getset(get = "pub(crate)")]
     rest: Vec<FieldValue>,
 }
 
@@ -246,6 +249,12 @@ macro_rules! partial_eq {
 
 partial_eq!(FieldName);
 
+impl std::borrow::Borrow<[u8]> for FieldName {
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
 ///Represents field value.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[debug("{:?}",self.as_str().unwrap_or(""))]
@@ -312,7 +321,13 @@ impl From<&str> for FieldValue {
 
 partial_eq!(FieldValue);
 
-#[derive(Clone, Debug, Eq, Hash)]
+impl std::borrow::Borrow<[u8]> for FieldValue {
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+#[derive(Clone, Debug, Eq)]
 #[repr(u8)]
 enum Fref {
     Ref(&'static [u8]),
@@ -325,6 +340,12 @@ impl PartialEq for Fref {
     }
 }
 
+impl std::hash::Hash for Fref {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
 impl Fref {
     fn as_bytes(&self) -> &[u8] {
         match self {
@@ -0,0 +1,209 @@
+use crate::prty::*;
+use derive_more::Debug;
+use std::collections::{HashMap, VecDeque};
+
+///The overhead of an entry in the dynamic table.
+pub const ENTRY_OVERHEAD: usize = 32;
+
+///Returns the size of an entry, which is the sum of its name's length, its value's length, and 32.
+#[inline(always)]
+pub fn entry_size(name: &[u8], value: &[u8]) -> usize {
+    name.len() + value.len() + ENTRY_OVERHEAD
+}
+
+///A dynamic table shared by HPACK and QPACK.
+///
+///Entries are kept in a ring buffer with a running size, and indexed by (name, value) and by name,
+///so inserting, evicting and looking up are constant time.
+///Each entry has an absolute index, which is 0 for the first entry inserted and increases by one for each insertion.
+///HPACK relative indexes start at 1 for the newest entry, QPACK relative indexes are relative to a base.
+#[derive(Debug, CopyGetters)]
+pub struct FieldTable {
+    #[getset(get_copy = "pub")]
+    capacity: usize,
+    #[getset(get_copy = "pub")]
+    size: usize,
+    #[getset(get_copy = "pub")]
+    insert_count: usize,
+    entries: VecDeque<(FieldName, FieldValue)>,
+    #[debug(ignore)]
+    names: HashMap<FieldName, usize>,
+    #[debug(ignore)]
+    pairs: HashMap<FieldName, HashMap<FieldValue, usize>>,
+}
+
+impl Default for FieldTable {
+    fn default() -> Self {
+        Self::new(4096)
+    }
+}
+
+impl FieldTable {
+    ///Creates.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            size: 0,
+            insert_count: 0,
+            entries: VecDeque::new(),
+            names: HashMap::new(),
+            pairs: HashMap::new(),
+        }
+    }
+
+    ///Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    ///Returns true if there is no entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    ///Returns the absolute index of the oldest entry, which equals to the insert count if empty.
+    pub fn dropped_count(&self) -> usize {
+        self.insert_count - self.entries.len()
+    }
+
+    ///Removes all entries, the insert count is kept.
+    pub fn clear(&mut self) {
+        while self.evict_one() {}
+    }
+
+    ///Sets the capacity, evicts entries to fit.
+    pub fn set_capacity(&mut self, n: usize) {
+        self.capacity = n;
+        self.evict(n);
+    }
+
+    ///Returns true if an entry can be inserted without evicting any entry.
+    pub fn has_room(&self, name: &[u8], value: &[u8]) -> bool {
+        self.size + entry_size(name, value) <= self.capacity
+    }
+
+    ///Inserts an entry, evicts the oldest entries to fit.
+    ///Returns false if the entry is larger than the capacity, then the table is emptied and the entry is not inserted.
+    pub fn insert(&mut self, name: FieldName, value: FieldValue) -> bool {
+        let n = entry_size(name.as_bytes(), value.as_bytes());
+        if n > self.capacity {
+            self.clear();
+            return false;
+        }
+        self.evict(self.capacity - n);
+        let a = self.insert_count;
+        self.names.insert(name.clone(), a);
+        self.pairs
+            .entry(name.clone())
+            .or_default()
+            .insert(value.clone(), a);
+        self.entries.push_back((name, value));
+        self.size += n;
+        self.insert_count += 1;
+        true
+    }
+
+    ///Returns the entry with the absolute index.
+    pub fn get_absolute(&self, a: usize) -> Option<&(FieldName, FieldValue)> {
+        a.checked_sub(self.dropped_count())
+            .and_then(|i| self.entries.get(i))
+    }
+
+    ///Returns the absolute index of the newest entry matching the name and value.
+    pub fn find_absolute(&self, name: &[u8], value: &[u8]) -> Option<usize> {
+        self.pairs.get(name).and_then(|o| o.get(value)).copied()
+    }
+
+    ///Returns the absolute index of the newest entry matching the name.
+    pub fn find_name_absolute(&self, name: &[u8]) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    ///Returns the entry with the HPACK relative index, 1 for the newest entry.
+    pub fn get(&self, n: usize) -> Option<&(FieldName, FieldValue)> {
+        n.checked_sub(1)
+            .and_then(|n| self.entries.len().checked_sub(n + 1))
+            .and_then(|i| self.entries.get(i))
+    }
+
+    ///Returns the HPACK relative index of the newest entry matching the name and value.
+    pub fn find(&self, name: &[u8], value: &[u8]) -> Option<usize> {
+        self.find_absolute(name, value)
+            .map(|a| self.insert_count - a)
+    }
+
+    ///Returns the HPACK relative index of the newest entry matching the name.
+    pub fn find_name(&self, name: &[u8]) -> Option<usize> {
+        self.find_name_absolute(name).map(|a| self.insert_count - a)
+    }
+
+    ///Returns the entry with the QPACK relative index, 0 for the entry with the absolute index base - 1.
+    pub fn get_relative(&self, base: usize, n: usize) -> Option<&(FieldName, FieldValue)> {
+        base.checked_sub(n + 1).and_then(|a| self.get_absolute(a))
+    }
+
+    ///Returns the entry with the QPACK post-base index, 0 for the entry with the absolute index base.
+    pub fn get_post_base(&self, base: usize, n: usize) -> Option<&(FieldName, FieldValue)> {
+        self.get_absolute(base + n)
+    }
+
+    fn evict(&mut self, n: usize) {
+        while self.size > n && self.evict_one() {}
+    }
+
+    fn evict_one(&mut self) -> bool {
+        let a = self.dropped_count();
+        let Some((name, value)) = self.entries.pop_front() else {
+            return false;
+        };
+        self.size -= entry_size(name.as_bytes(), value.as_bytes());
+        if self.names.get(&name) == Some(&a) {
+            self.names.remove(&name);
+        }
+        if let Some(o) = self.pairs.get_mut(&name) {
+            if o.get(&value) == Some(&a) {
+                o.remove(&value);
+            }
+            if o.is_empty() {
+                self.pairs.remove(&name);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table() {
+        let mut t = FieldTable::new(100);
+        assert!(t.insert("a".into(), "1".into()));
+        assert!(t.insert("b".into(), "2".into()));
+        assert!(t.insert("a".into(), "3".into()));
+        assert_eq!(t.len(), 2);
+        assert_eq!(t.size(), 68);
+        assert_eq!(t.dropped_count(), 1);
+        assert_eq!(t.find(b"a", b"3"), Some(1));
+        assert_eq!(t.find(b"a", b"1"), None);
+        assert_eq!(t.find_name(b"b"), Some(2));
+        assert_eq!(t.get(2).unwrap().1, b"2");
+        assert!(t.get(3).is_none());
+        assert!(t.get(0).is_none());
+        assert_eq!(t.find_absolute(b"a", b"3"), Some(2));
+        assert!(t.get_absolute(0).is_none());
+        assert_eq!(t.get_relative(3, 0).unwrap().1, b"3");
+        assert_eq!(t.get_post_base(1, 1).unwrap().1, b"3");
+
+        assert!(t.insert("b".into(), "2".into()));
+        assert_eq!(t.find(b"b", b"2"), Some(1));
+        assert_eq!(t.find_name(b"a"), Some(2));
+        t.set_capacity(40);
+        assert_eq!(t.len(), 1);
+        assert_eq!(t.find_name(b"a"), None);
+        assert!(!t.insert("c".into(), "0123456789".into()));
+        assert!(t.is_empty());
+        assert_eq!(t.insert_count(), 4);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::prty::*;
+
+///Represents a malformed message, which is treated as a stream error.
+#[derive(Clone, CopyGetters, Debug, Eq, Getters, PartialEq)]
+pub struct MalformedError {
+    #[getset(get = "pub")]
+    field: FieldName,
+    #[getset(get_copy = "pub")]
+    reason: &'static str,
+}
+
+impl MalformedError {
+    ///Creates.
+    pub fn new(field: impl Into<FieldName>, reason: &'static str) -> Self {
+        Self {
+            field: field.into(),
+            reason,
+        }
+    }
+}
+
+impl std::fmt::Display for MalformedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = String::from_utf8_lossy(self.field.as_bytes());
+        write!(f, "malformed message, {:?}: {}", name, self.reason)
+    }
+}
+
+impl std::error::Error for MalformedError {}
+
+///Connection-specific fields, which are not used in HTTP/2 and HTTP/3.
+const CONNECTION_SPECIFIC: &[&[u8]] = &[
+    b"connection",
+    b"keep-alive",
+    b"proxy-connection",
+    b"transfer-encoding",
+    b"upgrade",
+];
+
+#[inline(always)]
+fn is_whitespace(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+///Checks a field name. A field name must not contain characters in the ranges 0x00-0x20, 0x41-0x5a, or 0x7f-0xff, and a colon is only allowed at the start of a pseudo-header field name.
+pub(crate) fn check_field_name(name: &FieldName) -> Result<(), MalformedError> {
+    let o = name.as_bytes();
+    let s = if name.is_pseudo() { &o[1..] } else { o };
+    if s.is_empty() {
+        return Err(MalformedError::new(name.clone(), "empty name"));
+    }
+    for &b in s {
+        match b {
+            b'A'..=b'Z' => return Err(MalformedError::new(name.clone(), "uppercase name")),
+            0x00..=0x20 | 0x7f..=0xff | b':' => {
+                return Err(MalformedError::new(name.clone(), "invalid name character"));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+///Checks a field value. A field value must not contain NUL, CR or LF, and must not start or end with whitespace.
+pub(crate) fn check_field_value(
+    name: &FieldName,
+    value: &FieldValue,
+) -> Result<(), MalformedError> {
+    let o = value.as_bytes();
+    if o.iter().any(|&b| b == 0x00 || b == b'\r' || b == b'\n') {
+        return Err(MalformedError::new(name.clone(), "invalid value character"));
+    }
+    if o.first().is_some_and(|&b| is_whitespace(b)) || o.last().is_some_and(|&b| is_whitespace(b)) {
+        return Err(MalformedError::new(
+            name.clone(),
+            "leading or trailing whitespace",
+        ));
+    }
+    Ok(())
+}
+
+///Checks a field line, includes name, value and connection-specific fields.
+pub(crate) fn check_field(name: &FieldName, value: &FieldValue) -> Result<(), MalformedError> {
+    check_field_name(name)?;
+    check_field_value(name, value)?;
+    let o = name.as_bytes();
+    if CONNECTION_SPECIFIC.contains(&o) {
+        return Err(MalformedError::new(
+            name.clone(),
+            "connection-specific field",
+        ));
+    }
+    if o == b"te" && !value.as_bytes().eq_ignore_ascii_case(b"trailers") {
+        return Err(MalformedError::new(name.clone(), "te other than trailers"));
+    }
+    Ok(())
+}
+
+///Checks some field lines decoded from a field block. Pseudo-header fields must appear before regular fields.
+pub(crate) fn check_fields(v: &[(FieldName, FieldValue)]) -> Result<(), MalformedError> {
+    let mut regular = false;
+    for (name, value) in v {
+        check_field(name, value)?;
+        if name.is_pseudo() {
+            if regular {
+                return Err(MalformedError::new(
+                    name.clone(),
+                    "pseudo-header field after regular field",
+                ));
+            }
+        } else {
+            regular = true;
+        }
+    }
+    Ok(())
+}
+
+///Checks the field lines of a trailer section, which must not include pseudo-header fields.
+pub(crate) fn check_trailers(v: &[(FieldName, FieldValue)]) -> Result<(), MalformedError> {
+    for (name, value) in v {
+        check_field(name, value)?;
+        if name.is_pseudo() {
+            return Err(MalformedError::new(
+                name.clone(),
+                "pseudo-header field in trailers",
+            ));
+        }
+    }
+    Ok(())
+}
+
+const CONNECT: &[u8] = b"CONNECT";
+const HOST: &[u8] = b"host";
+
+///Checks the pseudo-header fields of a request.
+///
+///":method" is required. A CONNECT request must include ":authority" and omit ":scheme" and ":path",
+///other requests must include ":scheme" and a nonempty ":path".
+///":protocol" is only allowed in an extended CONNECT request, which must include ":scheme", ":path" and ":authority".
+///A request with an "http" or "https" scheme must include ":authority" or "host", and the two must be identical if both are present.
+///Its ":authority" must not be empty.
+pub(crate) fn check_request_pseudo(
+    method: &FieldValue,
+    scheme: Option<&FieldValue>,
+    authority: Option<&FieldValue>,
+    path: Option<&FieldValue>,
+    protocol: Option<&FieldValue>,
+    host: Option<&FieldValues>,
+) -> Result<(), MalformedError> {
+    let missing = |o: &'static [u8]| Err(MalformedError::new(o, "missing pseudo-header field"));
+    let forbidden = |o: &'static [u8]| Err(MalformedError::new(o, "forbidden pseudo-header field"));
+    if method.is_empty() {
+        return missing(b":method");
+    }
+    let connect = method.as_bytes() == CONNECT;
+    if protocol.is_some() {
+        if !connect {
+            return forbidden(b":protocol");
+        }
+        if authority.is_none() {
+            return missing(b":authority");
+        }
+    }
+    if connect && protocol.is_none() {
+        if scheme.is_some() {
+            return forbidden(b":scheme");
+        }
+        if path.is_some() {
+            return forbidden(b":path");
+        }
+        if authority.is_none() {
+            return missing(b":authority");
+        }
+    } else {
+        let Some(scheme) = scheme else {
+            return missing(b":scheme");
+        };
+        match path {
+            Some(o) if o.is_empty() => {
+                return Err(MalformedError::new(&b":path"[..], "empty :path"));
+            }
+            Some(_) => {}
+            None => return missing(b":path"),
+        }
+        let o = scheme.as_bytes();
+        if o == b"http" || o == b"https" {
+            if authority.is_some_and(|a| a.is_empty()) {
+                return Err(MalformedError::new(&b":authority"[..], "empty :authority"));
+            }
+            if authority.is_none() && host.is_none() {
+                return missing(b":authority");
+            }
+        }
+    }
+    if let (Some(a), Some(h)) = (authority, host)
+        && (h.has_rest() || a != h.one())
+    {
+        return Err(MalformedError::new(HOST, "authority and host differ"));
+    }
+    Ok(())
+}
+
+///Checks the pseudo-header fields of a response. ":status" is required and must be a three-digit code.
+pub(crate) fn check_response_pseudo(status: &FieldValue) -> Result<(), MalformedError> {
+    let o = status.as_bytes();
+    if o.is_empty() {
+        return Err(MalformedError::new(
+            &b":status"[..],
+            "missing pseudo-header field",
+        ));
+    }
+    if o.len() != 3 || !o.iter().all(|b| b.is_ascii_digit()) {
+        return Err(MalformedError::new(&b":status"[..], "invalid :status"));
+    }
+    Ok(())
+}
+
+#[inline(always)]
+pub(crate) fn pseudo_error(name: &[u8], reason: &'static str) -> MalformedError {
+    MalformedError::new(name.to_vec(), reason)
+}
+
+macro_rules! request_pseudo {
+    () => {
+        ///Sets a pseudo-header field.
+        ///Returns an error if the pseudo-header field is unknown or already set.
+        pub fn try_set_pseudo(
+            &mut self,
+            name: &[u8],
+            value: impl Into<FieldValue>,
+        ) -> Result<(), $crate::MalformedError> {
+            let duplicated = match name {
+                PSEUDO_METHOD => !self.method.is_empty(),
+                PSEUDO_SCHEME => self.scheme.is_some(),
+                PSEUDO_AUTHORITY => self.authority.is_some(),
+                PSEUDO_PATH => self.path.is_some(),
+                PSEUDO_PROTOCOL => self.protocol.is_some(),
+                _ => {
+                    return Err($crate::validate::pseudo_error(
+                        name,
+                        "unknown pseudo-header field",
+                    ));
+                }
+            };
+            if duplicated {
+                return Err($crate::validate::pseudo_error(
+                    name,
+                    "duplicated pseudo-header field",
+                ));
+            }
+            self.set_pseudo(name, value);
+            Ok(())
+        }
+
+        ///Checks required and forbidden pseudo-header fields, and the consistency of ":authority" and "host".
+        pub fn validate(&self) -> Result<(), $crate::MalformedError> {
+            $crate::validate::check_request_pseudo(
+                &self.method,
+                self.scheme.as_ref(),
+                self.authority.as_ref(),
+                self.path.as_ref(),
+                self.protocol.as_ref(),
+                self.headers_body.get(&FieldName::from(&b"host"[..])),
+            )
+        }
+
+        ///Checks like [`Self::validate`], and rejects ":protocol" if the extended CONNECT is not enabled by SETTINGS_ENABLE_CONNECT_PROTOCOL.
+        pub fn validate_with_connect_protocol(
+            &self,
+            enabled: bool,
+        ) -> Result<(), $crate::MalformedError> {
+            if !enabled && self.protocol.is_some() {
+                return Err($crate::validate::pseudo_error(
+                    PSEUDO_PROTOCOL,
+                    "extended CONNECT not enabled",
+                ));
+            }
+            self.validate()
+        }
+    };
+}
+
+macro_rules! response_pseudo {
+    () => {
+        ///Sets a pseudo-header field.
+        ///Returns an error if the pseudo-header field is unknown or already set.
+        pub fn try_set_pseudo(
+            &mut self,
+            name: &[u8],
+            value: impl Into<FieldValue>,
+        ) -> Result<(), $crate::MalformedError> {
+            match name {
+                PSEUDO_STATUS if self.status.is_empty() => {
+                    self.set_pseudo(name, value);
+                    Ok(())
+                }
+                PSEUDO_STATUS => Err($crate::validate::pseudo_error(
+                    name,
+                    "duplicated pseudo-header field",
+                )),
+                _ => Err($crate::validate::pseudo_error(
+                    name,
+                    "unknown pseudo-header field",
+                )),
+            }
+        }
+
+        ///Checks the ":status" pseudo-header field.
+        pub fn validate(&self) -> Result<(), $crate::MalformedError> {
+            $crate::validate::check_response_pseudo(&self.status)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let ok: Vec<(FieldName, FieldValue)> = vec![
+            (":method".into(), "GET".into()),
+            ("accept".into(), "*/*".into()),
+            ("te".into(), "trailers".into()),
+        ];
+        assert!(check_fields(&ok).is_ok());
+
+        let f = |name: &str, value: &str| {
+            check_fields(&[(name.into(), value.into())]).map_err(|e| e.reason())
+        };
+        assert_eq!(f("", ""), Err("empty name"));
+        assert_eq!(f(":", ""), Err("empty name"));
+        assert_eq!(f("Accept", ""), Err("uppercase name"));
+        assert_eq!(f("a b", ""), Err("invalid name character"));
+        assert_eq!(f("a", "x\r\ny"), Err("invalid value character"));
+        assert_eq!(f("a", " x"), Err("leading or trailing whitespace"));
+        assert_eq!(f("connection", "close"), Err("connection-specific field"));
+        assert_eq!(f("te", "gzip"), Err("te other than trailers"));
+
+        let v: Vec<(FieldName, FieldValue)> = vec![
+            ("accept".into(), "*/*".into()),
+            (":path".into(), "/".into()),
+        ];
+        let e = check_fields(&v).unwrap_err();
+        assert_eq!(e.field(), b":path");
+    }
+
+    #[test]
+    fn pseudo() {
+        use crate::h2::*;
+
+        let mut r = H2Request::default();
+        assert_eq!(r.validate().unwrap_err().field(), b":method");
+        assert!(r.try_set_pseudo(PSEUDO_METHOD, "GET").is_ok());
+        let e = r.try_set_pseudo(PSEUDO_METHOD, "POST").unwrap_err();
+        assert_eq!(e.reason(), "duplicated pseudo-header field");
+        let e = r.try_set_pseudo(b":foo", "bar").unwrap_err();
+        assert_eq!(e.reason(), "unknown pseudo-header field");
+        assert_eq!(r.validate().unwrap_err().field(), b":scheme");
+        r.set_scheme(Some("https"));
+        assert_eq!(r.validate().unwrap_err().field(), b":path");
+        r.set_path(Some(""));
+        assert_eq!(r.validate().unwrap_err().reason(), "empty :path");
+        r.set_path(Some("/"));
+        assert_eq!(r.validate().unwrap_err().field(), b":authority");
+        r.add_field("host", "example.org");
+        assert!(r.validate().is_ok());
+        r.set_authority(Some("example.com"));
+        assert_eq!(r.validate().unwrap_err().field(), b"host");
+        r.set_authority(Some(""));
+        assert_eq!(r.validate().unwrap_err().reason(), "empty :authority");
+
+        let mut r = H2Request::new("CONNECT");
+        r.set_authority(Some("example.org:443"));
+        assert!(r.validate().is_ok());
+        r.set_path(Some("/"));
+        assert_eq!(
+            r.validate().unwrap_err().reason(),
+            "forbidden pseudo-header field"
+        );
+        r.set_scheme(Some("https"));
+        r.set_protocol(Some("websocket"));
+        assert!(r.validate().is_ok());
+        assert!(r.validate_with_connect_protocol(false).is_err());
+        assert!(r.validate_with_connect_protocol(true).is_ok());
+        *r.method_mut() = "GET".into();
+        assert_eq!(r.validate().unwrap_err().field(), b":protocol");
+
+        let mut r = H2Response::default();
+        assert!(r.validate().is_err());
+        assert!(r.try_set_pseudo(PSEUDO_STATUS, "2000").is_ok());
+        assert_eq!(r.validate().unwrap_err().reason(), "invalid :status");
+        assert!(r.try_set_pseudo(PSEUDO_STATUS, "200").is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use httpenergy::h2::assembler::*;
 use httpenergy::h2::frame::*;
 use httpenergy::h2::hpack::*;
 use httpenergy::h2::*;
@@ -22,36 +23,25 @@ pub fn h2_request() {
     let mut helper = HeadersHelper::new(1, 100, 100);
     handle_request_pseudo_header_fields(&r, &mut helper);
     handle_fields(&r, &mut helper);
+    let (headers, mut v) = helper.take();
+    v.last_mut().unwrap().set_end_headers(true);
     let mut s = Vec::new();
-    helper.export(&mut s);
+    headers.export(&mut s);
+    for o in v {
+        o.export(&mut s);
+    }
     assert!(s.len() > 0);
 
     let mut t = DynamicTable::default();
     let mut req = H2Request::default();
+    let mut a = FieldBlockAssembler::default();
     let mut g = s.into_get();
-    if let Ok(rst) = get_frame(&mut g) {
-        match rst {
-            FrameResult::Headers(o) => {
-                let mut r = Vec::new();
-                if let Some(mut f) = o.field_block_fragment(&mut g) {
-                    r = f.get_surplus().to_vec();
-                }
-                while let Ok(rst) = get_frame(&mut g) {
-                    match rst {
-                        FrameResult::Continuation(b) => {
-                            if let Some(mut f) = b.field_block_fragment(&mut g) {
-                                r.extend_from_slice(f.get_surplus());
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                if let Ok(v) = get_hfris_to_vec(&mut r.into_get()) {
-                    let v = update_dynamic_table_to_vec(v, &mut t);
-                    add_fields_to_request(v, &mut req);
-                }
-            }
-            _ => {}
+    while let Ok(rst) = get_frame(&mut g) {
+        if let Some(b) = a.push(&rst, &mut g).unwrap()
+            && let Ok(v) = get_hfris_to_vec(&mut b.into_field_block().into_get())
+        {
+            let v = update_dynamic_table_to_vec(v, &mut t);
+            add_fields_to_request(v, &mut req);
         }
     }
 
@@ -60,3 +50,47 @@ pub fn h2_request() {
     assert_eq!(r.authority(), req.authority());
     assert_eq!(r.path(), req.path());
 }
+
+#[test]
+pub fn h2_malformed_request() {
+    let v: Vec<(FieldName, FieldValue)> = vec![
+        (":method".into(), "GET".into()),
+        (":scheme".into(), "https".into()),
+        (":path".into(), "/".into()),
+        ("connection".into(), "keep-alive".into()),
+    ];
+    let mut req = H2Request::default();
+    let e = try_add_fields_to_request(v, &mut req).unwrap_err();
+    assert_eq!(e.field(), "connection");
+    assert!(req.method().is_empty());
+}
+
+#[test]
+pub fn h2_extended_connect() {
+    let mut r = H2Request::new("CONNECT");
+    r.set_protocol(Some("websocket"));
+    r.set_scheme(Some("https"));
+    r.set_authority(Some("example.org"));
+    r.set_path(Some("/chat"));
+    r.add_field("sec-websocket-version", "13");
+    assert!(r.validate_with_connect_protocol(true).is_ok());
+
+    let mut helper = HeadersHelper::new(1, 100, 100);
+    handle_request_pseudo_header_fields(&r, &mut helper);
+    handle_fields(&r, &mut helper);
+    let mut s = Vec::new();
+    helper.export(&mut s);
+
+    let mut t = DynamicTable::default();
+    let mut req = H2Request::default();
+    let mut g = s.into_get();
+    if let Ok(FrameResult::Headers(o)) = get_frame(&mut g)
+        && let Some(mut f) = o.field_block_fragment(&mut g)
+        && let Ok(v) = get_hfris_to_vec(f.as_mut())
+    {
+        let v = update_dynamic_table_to_vec(v, &mut t);
+        assert!(try_add_fields_to_request(v, &mut req).is_ok());
+    }
+    assert_eq!(r.protocol(), req.protocol());
+    assert!(req.validate_with_connect_protocol(false).is_err());
+}
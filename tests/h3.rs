@@ -16,7 +16,7 @@ pub fn h3_request() {
     handle_request_pseudo_header_fields(&r, &mut *h);
     handle_fields(&r, &mut *h);
     let mut s = Vec::new();
-    h.export(&mut s);
+    h.export(&mut s).unwrap();
 
     let mut t = DynamicTable::default();
     let mut req = H3Request::default();
@@ -38,3 +38,17 @@ pub fn h3_request() {
     assert_eq!(r.authority(), req.authority());
     assert_eq!(r.path(), req.path());
 }
+
+#[test]
+pub fn h3_duplicated_pseudo() {
+    let v: Vec<(FieldName, FieldValue)> = vec![
+        (":method".into(), "GET".into()),
+        (":scheme".into(), "https".into()),
+        (":path".into(), "/".into()),
+        (":path".into(), "/index.html".into()),
+    ];
+    let mut req = H3Request::default();
+    let e = try_add_fields_to_request(v, &mut req).unwrap_err();
+    assert_eq!(e.field(), ":path");
+    assert!(req.method().is_empty());
+}